@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
-// use serde_derive::Deserialize;
-// use serde_derive::Serialize;
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
 
 use crate::board;
 // use crate::piece;
@@ -9,21 +9,403 @@ use crate::board;
 // use std::borrow::Borrow;
 // use std::borrow::Borrow;
 // use crate::common;
+use crate::common;
 use crate::coord;
 use crate::piece;
+use num_enum::TryFromPrimitive;
 // use crate::coord::CoordPair;
 // use std::borrow::BorrowMut;
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::rc::Weak;
 
-#[derive(Debug)] //, Serialize, Deserialize
+// JSON格式的着法节点(根节点无着法字段)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MoveJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coordpair: Option<[usize; 4]>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub iccs: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub zh: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub remark: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<AnnotationJson>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<MoveJson>,
+}
+
+// JSON格式的结构化注释，格子及箭头标记同注释命令，如"RC0"、"GA0C0"
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnnotationJson {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nags: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub squares: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arrows: Vec<String>,
+}
+
+// 箭头及格子标记的颜色，对应注释命令中的R/G/B/Y
+#[derive(Clone, Copy, Debug, TryFromPrimitive, PartialEq)]
+#[repr(u8)]
+pub enum MarkColor {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+}
+
+const MARKCOLORCHARS: [char; 4] = ['R', 'G', 'B', 'Y'];
+
+impl MarkColor {
+    pub fn to_char(self) -> char {
+        MARKCOLORCHARS[self as usize]
+    }
+
+    pub fn from_char(ch: char) -> Option<Self> {
+        let index = MARKCOLORCHARS.iter().position(|&color_ch| color_ch == ch)?;
+        Self::try_from(index as u8).ok()
+    }
+}
+
+// 着法的结构化注释
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Annotation {
+    // 数字注释符号(NAG)，如1为!，2为?，4为??
+    pub nags: Vec<u8>,
+    // 引擎分值(红方视角，兵值100)及搜索深度
+    pub score: Option<i32>,
+    pub depth: Option<u32>,
+    pub arrows: Vec<(MarkColor, coord::CoordPair)>,
+    pub squares: Vec<(MarkColor, coord::Coord)>,
+    // 走完本着后的剩余用时及本着所用时间(秒)
+    pub clock: Option<u32>,
+    pub elapsed: Option<u32>,
+}
+
+// 秒数表示为h:mm:ss
+pub fn time_string(seconds: u32) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// 解析h:mm:ss、mm:ss或秒数
+pub fn parse_time(time_str: &str) -> Option<u32> {
+    time_str.trim().split(':').try_fold(0, |seconds, part_str| {
+        part_str.parse::<u32>().ok().map(|part| seconds * 60 + part)
+    })
+}
+
+impl Annotation {
+    pub fn is_empty(&self) -> bool {
+        *self == Annotation::default()
+    }
+
+    pub fn add_nag(&mut self, nag: u8) {
+        if !self.nags.contains(&nag) {
+            self.nags.push(nag);
+        }
+    }
+
+    // PGN的$n形式，每个前置空格
+    pub fn nags_string(&self) -> String {
+        self.nags.iter().map(|nag| format!(" ${nag}")).collect()
+    }
+
+    pub fn parse_nags(nags_str: &str) -> Vec<u8> {
+        nags_str
+            .split_whitespace()
+            .filter_map(|nag_str| nag_str.trim_start_matches('$').parse().ok())
+            .collect()
+    }
+
+    // 注释命令[%eval][%clk][%emt][%csl][%cal]，杀棋分值记为"#着数"，被杀为负
+    pub fn commands_string(&self) -> String {
+        let mut result = String::new();
+        if let Some(score) = self.score {
+            match common::mate_moves(score) {
+                Some(moves) => result.push_str(&format!("[%eval #{moves}")),
+                None => result.push_str(&format!("[%eval {:.2}", score as f64 / 100.0)),
+            }
+            if let Some(depth) = self.depth {
+                result.push_str(&format!(",{depth}"));
+            }
+            result.push(']');
+        }
+        if let Some(clock) = self.clock {
+            result.push_str(&format!("[%clk {}]", time_string(clock)));
+        }
+        if let Some(elapsed) = self.elapsed {
+            result.push_str(&format!("[%emt {}]", time_string(elapsed)));
+        }
+        if !self.squares.is_empty() {
+            result.push_str(&format!("[%csl {}]", self.square_strings().join(",")));
+        }
+        if !self.arrows.is_empty() {
+            result.push_str(&format!("[%cal {}]", self.arrow_strings().join(",")));
+        }
+
+        result
+    }
+
+    // 取出备注中的注释命令，返回其余的备注文字，无法识别的命令原样保留
+    pub fn take_commands(&mut self, remark: &str) -> String {
+        let command_re = regex::Regex::new(r"\[%(eval|clk|emt|csl|cal) ([^\]]*)\]").unwrap();
+        let mut has_command = false;
+        for caps in command_re.captures_iter(remark) {
+            has_command = true;
+            let args = caps.at(2).unwrap_or_default().trim();
+            match caps.at(1).unwrap_or_default() {
+                "eval" => {
+                    let mut parts = args.split(',');
+                    self.score = parts.next().and_then(|score_str| {
+                        let score_str = score_str.trim();
+                        match score_str.strip_prefix('#') {
+                            Some(moves_str) => moves_str
+                                .parse::<i32>()
+                                .ok()
+                                .filter(|moves| *moves != 0)
+                                .map(common::mate_score),
+                            None => score_str
+                                .parse::<f64>()
+                                .ok()
+                                .map(|score| (score * 100.0).round() as i32),
+                        }
+                    });
+                    self.depth = parts
+                        .next()
+                        .and_then(|depth_str| depth_str.trim().parse().ok());
+                }
+                "clk" => self.clock = parse_time(args),
+                "emt" => self.elapsed = parse_time(args),
+                "csl" => {
+                    self.squares = Self::parse_marks(args, |coord_str| {
+                        coord::Coord::from_string(coord_str, coord::RecordType::PgnIccs).ok()
+                    });
+                }
+                "cal" => {
+                    self.arrows = Self::parse_marks(args, |coordpair_str| {
+                        coord::CoordPair::from_string(coordpair_str, coord::RecordType::PgnIccs)
+                            .ok()
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        if has_command {
+            command_re.replace_all(remark, "").trim().to_string()
+        } else {
+            remark.to_string()
+        }
+    }
+
+    fn square_strings(&self) -> Vec<String> {
+        self.squares
+            .iter()
+            .map(|(color, coord)| {
+                format!(
+                    "{}{}",
+                    color.to_char(),
+                    coord.to_string(coord::RecordType::PgnIccs)
+                )
+            })
+            .collect()
+    }
+
+    fn arrow_strings(&self) -> Vec<String> {
+        self.arrows
+            .iter()
+            .map(|(color, coordpair)| {
+                format!(
+                    "{}{}",
+                    color.to_char(),
+                    coordpair.to_string(coord::RecordType::PgnIccs)
+                )
+            })
+            .collect()
+    }
+
+    fn parse_marks<T>(args: &str, parse: impl Fn(&str) -> Option<T>) -> Vec<(MarkColor, T)> {
+        args.split(',')
+            .filter_map(|mark_str| {
+                let mark_str = mark_str.trim();
+                let color = MarkColor::from_char(mark_str.chars().next()?)?;
+                Some((color, parse(mark_str.get(1..)?)?))
+            })
+            .collect()
+    }
+
+    // 标记首字符为颜色，其余为坐标
+    fn split_mark(mark_str: &str) -> common::Result<(MarkColor, &str)> {
+        let color = mark_str
+            .chars()
+            .next()
+            .and_then(MarkColor::from_char)
+            .ok_or(common::Error::StringParse)?;
+
+        Ok((color, mark_str.get(1..).ok_or(common::Error::StringParse)?))
+    }
+
+    // 无注释时为None，JSON中省略该字段
+    pub fn to_json(&self) -> Option<AnnotationJson> {
+        if self.is_empty() {
+            return None;
+        }
+
+        Some(AnnotationJson {
+            nags: self.nags.clone(),
+            score: self.score,
+            depth: self.depth,
+            clock: self.clock,
+            elapsed: self.elapsed,
+            squares: self.square_strings(),
+            arrows: self.arrow_strings(),
+        })
+    }
+
+    pub fn from_json(annotation_json: &AnnotationJson) -> common::Result<Self> {
+        let mut annotation = Annotation {
+            nags: annotation_json.nags.clone(),
+            score: annotation_json.score,
+            depth: annotation_json.depth,
+            clock: annotation_json.clock,
+            elapsed: annotation_json.elapsed,
+            ..Default::default()
+        };
+        for square_str in &annotation_json.squares {
+            let (color, coord_str) = Self::split_mark(square_str)?;
+            let coord = coord::Coord::from_string(coord_str, coord::RecordType::PgnIccs)?;
+            annotation.squares.push((color, coord));
+        }
+        for arrow_str in &annotation_json.arrows {
+            let (color, coordpair_str) = Self::split_mark(arrow_str)?;
+            let coordpair =
+                coord::CoordPair::from_string(coordpair_str, coord::RecordType::PgnIccs)?;
+            annotation.arrows.push((color, coordpair));
+        }
+
+        Ok(annotation)
+    }
+
+    // 二进制格式：各项数量或有无标志在前，数量以一字节记录
+    pub(crate) fn write_bytes(&self, output: &mut Vec<u8>) -> common::Result<()> {
+        let write_len = |output: &mut Vec<u8>, len: usize, name: &str| -> common::Result<()> {
+            let len = u8::try_from(len)
+                .map_err(|_| common::Error::Bin(format!("too many {name}: {len}")))?;
+            output.push(len);
+            Ok(())
+        };
+
+        write_len(output, self.nags.len(), "nags")?;
+        output.extend_from_slice(&self.nags);
+
+        let mut flag = 0;
+        for (index, is_some) in [
+            self.score.is_some(),
+            self.depth.is_some(),
+            self.clock.is_some(),
+            self.elapsed.is_some(),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if is_some {
+                flag |= 1 << index;
+            }
+        }
+        output.push(flag);
+        if let Some(score) = self.score {
+            common::write_be_u32(output, score as u32);
+        }
+        if let Some(depth) = self.depth {
+            common::write_be_u32(output, depth);
+        }
+        if let Some(clock) = self.clock {
+            common::write_be_u32(output, clock);
+        }
+        if let Some(elapsed) = self.elapsed {
+            common::write_be_u32(output, elapsed);
+        }
+
+        write_len(output, self.arrows.len(), "arrows")?;
+        for (color, coordpair) in &self.arrows {
+            output.push(*color as u8);
+            common::write_coordpair(output, coordpair);
+        }
+        write_len(output, self.squares.len(), "squares")?;
+        for (color, coord) in &self.squares {
+            output.append(&mut vec![*color as u8, coord.row as u8, coord.col as u8]);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn read_bytes(input: &mut &[u8]) -> common::Result<Self> {
+        let read_u8 =
+            |input: &mut &[u8]| -> common::Result<u8> { Ok(common::read_bytes(input, 1)?[0]) };
+        let read_color = |input: &mut &[u8]| -> common::Result<MarkColor> {
+            MarkColor::try_from(read_u8(input)?)
+                .map_err(|_| common::Error::Bin(String::from("invalid mark color")))
+        };
+
+        let mut annotation = Annotation::default();
+        let nag_len = read_u8(input)? as usize;
+        annotation.nags = common::read_bytes(input, nag_len)?;
+
+        let flag = read_u8(input)?;
+        if flag & 1 != 0 {
+            annotation.score = Some(common::read_be_u32(input)? as i32);
+        }
+        if flag & 2 != 0 {
+            annotation.depth = Some(common::read_be_u32(input)?);
+        }
+        if flag & 4 != 0 {
+            annotation.clock = Some(common::read_be_u32(input)?);
+        }
+        if flag & 8 != 0 {
+            annotation.elapsed = Some(common::read_be_u32(input)?);
+        }
+
+        for _ in 0..read_u8(input)? {
+            let color = read_color(input)?;
+            annotation
+                .arrows
+                .push((color, common::read_coordpair(input)?));
+        }
+        for _ in 0..read_u8(input)? {
+            let color = read_color(input)?;
+            let bytes = common::read_bytes(input, 2)?;
+            let coord = coord::Coord::from(bytes[0] as usize, bytes[1] as usize)?;
+            annotation.squares.push((color, coord));
+        }
+
+        Ok(annotation)
+    }
+}
+
+#[derive(Debug)]
 pub struct Move {
     before: Option<Weak<Move>>,
     after: RefCell<Option<Vec<Rc<Move>>>>,
 
     pub coordpair: coord::CoordPair,
     remark: RefCell<Option<String>>,
+    annotation: RefCell<Annotation>,
 
     to_piece: RefCell<piece::Piece>,
 }
@@ -36,6 +418,7 @@ impl Move {
 
             coordpair: coord::CoordPair::new(),
             remark: RefCell::new(None),
+            annotation: RefCell::new(Annotation::default()),
 
             to_piece: RefCell::new(piece::Piece::None),
         })
@@ -70,6 +453,30 @@ impl Move {
         }
     }
 
+    // 备注不同时追加合并
+    pub fn merge_remark(&self, remark: String) {
+        let old_remark = self.remark();
+        if old_remark.is_empty() {
+            self.set_remark(remark);
+        } else if !remark.is_empty() && !old_remark.contains(&remark) {
+            self.set_remark(format!("{old_remark}\n{remark}"));
+        }
+    }
+
+    pub fn annotation(&self) -> Annotation {
+        self.annotation.borrow().clone()
+    }
+
+    pub fn set_annotation(&self, annotation: Annotation) {
+        *self.annotation.borrow_mut() = annotation;
+    }
+
+    pub fn find_after(&self, coordpair: &coord::CoordPair) -> Option<Rc<Self>> {
+        self.after()
+            .into_iter()
+            .find(|amove| amove.coordpair == *coordpair)
+    }
+
     pub fn append(self: &Rc<Self>, coordpair: coord::CoordPair, remark: String) -> Rc<Self> {
         let amove = Rc::new(Self {
             before: Some(Rc::downgrade(self)),
@@ -81,6 +488,7 @@ impl Move {
             } else {
                 Some(remark)
             }),
+            annotation: RefCell::new(Annotation::default()),
 
             to_piece: RefCell::new(piece::Piece::None),
         });
@@ -134,7 +542,13 @@ impl Move {
             }
         };
 
-        let mut remark = self.remark();
+        let annotation = self.annotation.borrow();
+        let mut remark = annotation.commands_string();
+        let text = self.remark();
+        if !remark.is_empty() && !text.is_empty() {
+            remark.push(' ');
+        }
+        remark.push_str(&text);
         if !remark.is_empty() {
             remark = format!("{{{}}}", remark);
         }
@@ -146,7 +560,13 @@ impl Move {
             String::new()
         };
 
-        format!("{}{}{}\n", coordpair_string, remark, after_num)
+        format!(
+            "{}{}{}{}\n",
+            coordpair_string,
+            annotation.nags_string(),
+            remark,
+            after_num
+        )
     }
 }
 
@@ -169,5 +589,87 @@ mod tests {
             "(0,0)(0,2){Hello, move.}\n",
             amove.to_string(coord::RecordType::Txt, &board)
         );
+
+        let annotation = Annotation {
+            nags: vec![1, 14],
+            score: Some(-125),
+            depth: Some(12),
+            arrows: vec![(MarkColor::Green, coordpair)],
+            squares: vec![(MarkColor::Red, to_coord)],
+            clock: Some(3725),
+            elapsed: Some(75),
+        };
+        amove.set_annotation(annotation.clone());
+        assert_eq!(
+            "A0C0 $1 $14{[%eval -1.25,12][%clk 1:02:05][%emt 0:01:15][%csl RC0][%cal GA0C0] Hello, move.}\n",
+            amove.to_string(coord::RecordType::PgnIccs, &board)
+        );
+
+        let mut parsed = Annotation {
+            nags: Annotation::parse_nags(" $1 $14"),
+            ..Default::default()
+        };
+        let remark = parsed.take_commands(
+            "[%eval -1.25,12][%clk 1:02:05][%emt 0:01:15][%csl RC0][%cal GA0C0] Hello, move.",
+        );
+        assert_eq!("Hello, move.", remark);
+        assert_eq!(annotation, parsed);
+        assert_eq!("[%x y] z", Annotation::default().take_commands("[%x y] z"));
+        // 无法识别的命令无论先后均保留，已识别的只取出一次
+        for (remark, text) in [
+            ("[%eval 1.0][%foo x] text", "[%foo x] text"),
+            ("[%foo x][%eval 1.0] text", "[%foo x] text"),
+        ] {
+            let mut parsed = Annotation::default();
+            assert_eq!(text, parsed.take_commands(remark));
+            assert_eq!(Some(100), parsed.score);
+            let amove = root_move.append(coordpair, text.to_string());
+            amove.set_annotation(parsed);
+            assert_eq!(
+                "A0C0{[%eval 1.00] [%foo x] text}\n",
+                amove.to_string(coord::RecordType::PgnIccs, &board)
+            );
+            let mut reparsed = Annotation::default();
+            assert_eq!(text, reparsed.take_commands("[%eval 1.00] [%foo x] text"));
+            assert_eq!(amove.annotation(), reparsed);
+        }
+
+        // 杀棋分值以着数表示
+        let mate_annotation = Annotation {
+            score: Some(-(common::MATESCORE - 3)),
+            depth: Some(2),
+            ..Default::default()
+        };
+        assert_eq!("[%eval #-2,2]", mate_annotation.commands_string());
+        let mut parsed = Annotation::default();
+        assert_eq!("", parsed.take_commands("[%eval #-2,2]"));
+        assert_eq!(mate_annotation, parsed);
+
+        let annotation_json = annotation.to_json().unwrap();
+        assert_eq!(vec!["RC0"], annotation_json.squares);
+        assert_eq!(vec!["GA0C0"], annotation_json.arrows);
+        assert_eq!(annotation, Annotation::from_json(&annotation_json).unwrap());
+        assert_eq!(None, Annotation::default().to_json());
+        let annotation_json = AnnotationJson {
+            arrows: vec![String::from("XA0C0")],
+            ..Default::default()
+        };
+        assert!(Annotation::from_json(&annotation_json).is_err());
+
+        let mut bytes = Vec::new();
+        annotation.write_bytes(&mut bytes).unwrap();
+        let mut input = bytes.as_slice();
+        assert_eq!(annotation, Annotation::read_bytes(&mut input).unwrap());
+        assert!(input.is_empty());
+
+        // 数量超出一字节范围时报错，而非截断
+        let many_nags = Annotation {
+            nags: vec![1; 256],
+            ..Default::default()
+        };
+        assert!(matches!(
+            many_nags.write_bytes(&mut Vec::new()),
+            Err(common::Error::Bin(_))
+        ));
     }
 }
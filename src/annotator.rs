@@ -0,0 +1,260 @@
+#![allow(dead_code)]
+
+use crate::amove;
+use crate::board;
+use crate::coord::CoordPair;
+use crate::manual;
+use crate::piece;
+use crate::search;
+use std::rc::Rc;
+
+#[derive(Clone, Copy, Debug)]
+pub struct AnnotateOptions {
+    // 搜索层数
+    pub depth: usize,
+    // 疑问手、劣着、败着的最低失分
+    pub inaccuracy: i32,
+    pub mistake: i32,
+    pub blunder: i32,
+    // 是否将对方的反击着法作为变着插入
+    pub add_refutation: bool,
+}
+
+impl AnnotateOptions {
+    pub fn new(depth: usize) -> Self {
+        AnnotateOptions {
+            depth,
+            inaccuracy: 50,
+            mistake: 150,
+            blunder: 300,
+            add_refutation: false,
+        }
+    }
+}
+
+impl Default for AnnotateOptions {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+// 失分对应的NAG(4为??，2为?，6为?!)及名称
+fn get_nag(loss: i32, options: &AnnotateOptions) -> Option<(u8, &'static str)> {
+    if loss >= options.blunder {
+        Some((4, "败着"))
+    } else if loss >= options.mistake {
+        Some((2, "劣着"))
+    } else if loss >= options.inaccuracy {
+        Some((6, "疑问手"))
+    } else {
+        None
+    }
+}
+
+// 逐着评估主线着法，与最佳着法相比失分较多者添加NAG、分值及备注，返回注释的着数
+pub fn annotate(manual: &manual::Manual, options: &AnnotateOptions) -> usize {
+    let manual_move = manual.manual_move();
+    let mut board = *manual_move.board();
+    // 先取出主线，插入的反击变例不再评估
+    let mut main_moves = Vec::new();
+    let mut before_move = manual_move.root_move();
+    while let Some(amove) = before_move.after().first().cloned() {
+        main_moves.push(amove.clone());
+        before_move = amove;
+    }
+
+    let mut count = 0;
+    for amove in main_moves {
+        let from_index = amove.coordpair.from_coord.index();
+        let color = match board.pieces()[from_index] {
+            piece::Piece::Some(color, _) => color,
+            piece::Piece::None => break,
+        };
+
+        let best = search::search(&board, color, options.depth);
+        let played = search::search_move(&board, color, &amove.coordpair, options.depth);
+        let better_coordpair = best
+            .pv
+            .first()
+            .filter(|coordpair| **coordpair != amove.coordpair);
+        if let Some(better_coordpair) = better_coordpair {
+            if let Some((nag, name)) = get_nag(best.score - played.score, options) {
+                let mut annotation = amove.annotation();
+                annotation.add_nag(nag);
+                annotation.score = Some(match color {
+                    piece::Color::Red => played.score,
+                    piece::Color::Black => -played.score,
+                });
+                annotation.depth = Some(options.depth as u32);
+                amove.set_annotation(annotation);
+                amove.merge_remark(format!(
+                    "{name}，宜走{}",
+                    board.get_zhstr_from_coordpair(better_coordpair)
+                ));
+                if options.add_refutation {
+                    append_refutation(&amove, &played.pv[1..]);
+                }
+                count += 1;
+            }
+        }
+
+        board.do_move(&amove);
+    }
+
+    count
+}
+
+// 反击变例已存在的部分沿用原着法
+fn append_refutation(amove: &Rc<amove::Move>, pv: &[CoordPair]) {
+    let mut before_move = amove.clone();
+    for (index, coordpair) in pv.iter().enumerate() {
+        before_move = match before_move.find_after(coordpair) {
+            Some(after_move) => after_move,
+            None => {
+                let remark = if index == 0 { "反击" } else { "" };
+                before_move.append(*coordpair, String::from(remark))
+            }
+        };
+    }
+}
+
+// 分析amove之后color方走子的局面，各候选变例作为amove的后续着法插入(已有者沿用)，
+// 首着添加分值及备注
+pub(crate) fn insert_analysis(
+    amove: &Rc<amove::Move>,
+    board: &board::Board,
+    color: piece::Color,
+    options: &search::AnalysisOptions,
+) -> search::Analysis {
+    let analysis = search::analyze(board, color, options);
+    for (index, line) in analysis.lines.iter().enumerate() {
+        let mut before_move = amove.clone();
+        for (move_index, coordpair) in line.pv.iter().enumerate() {
+            before_move = match before_move.find_after(coordpair) {
+                Some(after_move) => after_move,
+                None => before_move.append(*coordpair, String::new()),
+            };
+            if move_index > 0 {
+                continue;
+            }
+
+            let mut annotation = before_move.annotation();
+            annotation.score = Some(match color {
+                piece::Color::Red => line.score,
+                piece::Color::Black => -line.score,
+            });
+            annotation.depth = Some(analysis.depth as u32);
+            before_move.set_annotation(annotation);
+            before_move.merge_remark(format!(
+                "候选{} {}(深度{})",
+                index + 1,
+                search::score_string(line.score),
+                analysis.depth
+            ));
+        }
+    }
+
+    analysis
+}
+
+// 自开始局面起依次走path所列序号的后续着法(0为主着，其余为变着序号)，
+// 分析其后的局面并插入候选变例，可用于变着中的局面，序号不存在时返回None
+pub fn analyze_path(
+    manual: &manual::Manual,
+    path: &[usize],
+    options: &search::AnalysisOptions,
+) -> Option<search::Analysis> {
+    let manual_move = manual.manual_move();
+    let mut board = *manual_move.board();
+    let mut amove = manual_move.root_move();
+    let mut color = manual.first_color();
+    for &index in path {
+        amove = amove.after().get(index)?.clone();
+        board.do_move(&amove);
+        color = piece::other_color(color);
+    }
+
+    Some(insert_analysis(&amove, &board, color, options))
+}
+
+// 分析主线第ply着之后(0为开始局面)的局面并插入候选变例，主线不足ply着时返回None
+pub fn analyze_ply(
+    manual: &manual::Manual,
+    ply: usize,
+    options: &search::AnalysisOptions,
+) -> Option<search::Analysis> {
+    analyze_path(manual, &vec![0; ply], options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_annotator() {
+        // 红车不吃黑车反而退车，黑将又不抓住机会
+        let manual_str =
+            "[FEN: 4k4/9/9/R7r/9/9/9/9/9/3K5 r - - 0 1]\n\n(1)\n(3,0)(5,0)(1)\n(0,4)(1,4)\n";
+        let manual =
+            manual::Manual::read_from(manual_str.as_bytes(), coord::RecordType::Txt).unwrap();
+        assert_eq!(2, annotate(&manual, &AnnotateOptions::new(2)));
+        let result = manual.to_string(coord::RecordType::PgnZh);
+        assert!(result.contains("车九退二 $4{[%eval 0.00,2] 败着，宜走车九平一}"));
+        // 黑方应车平四将军
+        assert!(result.contains("将５进１ $4{"));
+        assert!(result.contains("败着，宜走车９平４}"));
+
+        let manual =
+            manual::Manual::read_from(manual_str.as_bytes(), coord::RecordType::Txt).unwrap();
+        let mut options = AnnotateOptions::new(2);
+        options.add_refutation = true;
+        assert_eq!(2, annotate(&manual, &options));
+        let root_move = manual.manual_move().root_move();
+        let first_move = root_move.after()[0].clone();
+        assert_eq!(vec![4], first_move.annotation().nags);
+        assert_eq!(2, first_move.after_len());
+        assert_eq!("反击", first_move.after()[1].remark());
+
+        // 再次注释时不重复添加
+        annotate(&manual, &options);
+        assert_eq!(2, first_move.after_len());
+        assert_eq!(vec![4], first_move.annotation().nags);
+        assert_eq!(1, first_move.remark().matches("败着").count());
+
+        let manual =
+            manual::Manual::read_from(manual_str.as_bytes(), coord::RecordType::Txt).unwrap();
+        let options = search::AnalysisOptions::new(2, 2);
+        let analysis = analyze_ply(&manual, 0, &options).unwrap();
+        assert_eq!(2, analysis.lines.len());
+        let root_move = manual.manual_move().root_move();
+        let best_move = root_move.find_after(&analysis.lines[0].pv[0]).unwrap();
+        assert_eq!("候选1 +9.00(深度2)", best_move.remark());
+        assert_eq!(Some(900), best_move.annotation().score);
+        assert_eq!(
+            analysis.lines[0].pv.len(),
+            best_move.before_moves().len() + best_move.after_len() + 1
+        );
+        let after_len = root_move.after_len();
+        assert!(after_len >= 2);
+        // 已有的着法沿用，不重复插入
+        analyze_ply(&manual, 0, &options);
+        assert_eq!(after_len, root_move.after_len());
+        assert_eq!("候选1 +9.00(深度2)", best_move.remark());
+        // 黑方车平四将军成杀
+        assert!(analyze_ply(&manual, 1, &options).is_some());
+        assert!(analyze_ply(&manual, 3, &options).is_none());
+        let result = manual.to_string(coord::RecordType::PgnZh);
+        assert!(result.contains("车九平一{[%eval 9.00,2] 候选1 +9.00(深度2)}"));
+        assert!(result.contains("车９平４{[%eval #-2,2] 候选1 2着杀(深度2)}"));
+
+        // 变着之后的局面：候选变例插入于该变着之下
+        let variation_move = root_move.after()[1].clone();
+        let analysis = analyze_path(&manual, &[1], &options).unwrap();
+        assert!(!analysis.lines.is_empty());
+        let reply_move = variation_move.find_after(&analysis.lines[0].pv[0]).unwrap();
+        assert!(Rc::ptr_eq(&variation_move, &reply_move.before().unwrap()));
+        assert!(reply_move.remark().starts_with("候选1 "));
+        assert!(analyze_path(&manual, &[root_move.after_len()], &options).is_none());
+    }
+}
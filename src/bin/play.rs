@@ -0,0 +1,101 @@
+// 终端人机对局：play [--black] [--depth N]，着法可用中文纵线、ICCS或WXF表示
+use lib::game::Game;
+use lib::piece::Color;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str =
+    "输入着法(如 炮二平五 / H7E7 / C2.5)，或 undo 悔棋  hint 提示  resign 认输  draw 提和  save <文件> 保存  quit 退出";
+
+fn main() {
+    let mut player_color = Color::Red;
+    let mut depth = 3;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--black" => player_color = Color::Black,
+            "--depth" => match args.next().and_then(|depth_str| depth_str.parse().ok()) {
+                Some(value) => depth = value,
+                None => {
+                    eprintln!("--depth 需要数字参数");
+                    std::process::exit(2);
+                }
+            },
+            _ => {
+                eprintln!("用法: play [--black] [--depth N]");
+                std::process::exit(2);
+            }
+        }
+    }
+    let engine_color = lib::piece::other_color(player_color);
+
+    let mut game = Game::new();
+    let stdin = io::stdin();
+    println!("{HELP}");
+    loop {
+        if game.result().is_none() && game.to_move() == engine_color {
+            if let Some(zhstr) = game.engine_move(depth) {
+                println!("电脑: {zhstr}");
+            }
+        }
+
+        println!("\n{}", game.board().to_string());
+        match game.result() {
+            Some(result) => println!("对局结束: {result}，{}", result.win_string()),
+            None => println!("第{}着，轮到你走", game.move_count() + 1),
+        }
+        print!("> ");
+        let _ = io::stdout().flush();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        let line = line.trim();
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "" => continue,
+            "quit" | "q" => break,
+            "help" => println!("{HELP}"),
+            // 悔棋连同电脑的应着
+            "undo" => {
+                let count = if game.to_move() == player_color { 2 } else { 1 };
+                game.undo(count);
+            }
+            "hint" => match game.hint(depth) {
+                Some(coordpair) => println!("提示: {}", game.zhstr(&coordpair)),
+                None => println!("无着可走"),
+            },
+            "resign" => game.resign(),
+            "draw" => {
+                if game.offer_draw(engine_color, depth) {
+                    println!("电脑同意和棋");
+                } else {
+                    println!("电脑拒绝和棋");
+                }
+            }
+            "save" => {
+                let file_name = argument.trim();
+                if file_name.is_empty() {
+                    println!("请指定文件名，扩展名决定格式");
+                } else {
+                    match game.to_manual().write(file_name) {
+                        Ok(()) => println!("已保存: {file_name}"),
+                        Err(err) => println!("{err}"),
+                    }
+                }
+            }
+            _ => {
+                if game.result().is_some() {
+                    println!("对局已结束");
+                } else {
+                    match game.parse_move(line) {
+                        Some(coordpair) => {
+                            game.play(&coordpair);
+                        }
+                        None => println!("无效着法: {line}"),
+                    }
+                }
+            }
+        }
+    }
+}
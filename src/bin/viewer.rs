@@ -0,0 +1,168 @@
+// 终端棋谱浏览：读取命令行指定的棋谱文件，终端置于按键模式，单键即浏览，无需回车
+use lib::manual;
+use lib::navigator::Navigator;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+const HELP: &str =
+    "→/↓/n/空格 下一着  ←/↑/b 上一着  0-9 选择变着  g 跳至第N着  s 开局  e 终局  f 翻转  q 退出";
+
+// 以stty将终端置于按键模式(不等回车、不回显)，析构时恢复原有设置
+struct KeyMode {
+    saved: String,
+}
+
+impl KeyMode {
+    fn enable() -> Option<Self> {
+        let output = Command::new("stty")
+            .arg("-g")
+            .stdin(Stdio::inherit())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let saved = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        stty(&["-icanon", "-echo", "min", "1"]).then_some(KeyMode { saved })
+    }
+}
+
+impl Drop for KeyMode {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> bool {
+    Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+enum Key {
+    Char(char),
+    Forward,
+    Back,
+}
+
+fn read_byte(input: &mut impl Read) -> Option<u8> {
+    let mut byte = [0];
+    match input.read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    }
+}
+
+// 方向键为ESC [ A/B/C/D三字节序列
+fn read_key(input: &mut impl Read) -> Option<Key> {
+    let byte = read_byte(input)?;
+    if byte != 0x1b {
+        return Some(Key::Char(byte as char));
+    }
+
+    if read_byte(input)? != b'[' {
+        return Some(Key::Char('\x1b'));
+    }
+    Some(match read_byte(input)? {
+        b'B' | b'C' => Key::Forward,
+        b'A' | b'D' => Key::Back,
+        other => Key::Char(other as char),
+    })
+}
+
+// 按键模式下自行回显，读取数字直至回车
+fn read_number(input: &mut impl Read) -> Option<usize> {
+    let mut number_str = String::new();
+    loop {
+        match read_byte(input)? {
+            b'\n' | b'\r' => break,
+            0x7f | 0x08 if number_str.pop().is_some() => print!("\x08 \x08"),
+            byte if byte.is_ascii_digit() => {
+                number_str.push(byte as char);
+                print!("{}", byte as char);
+            }
+            _ => (),
+        }
+        let _ = io::stdout().flush();
+    }
+
+    number_str.parse().ok()
+}
+
+fn main() {
+    let file_name = match std::env::args().nth(1) {
+        Some(file_name) => file_name,
+        None => {
+            eprintln!("用法: viewer <棋谱文件>");
+            std::process::exit(2);
+        }
+    };
+    let manual = match manual::Manual::from(&file_name) {
+        Ok(manual) => manual,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+    let key_mode = match KeyMode::enable() {
+        Some(key_mode) => key_mode,
+        None => {
+            eprintln!("viewer 需要在终端中运行");
+            std::process::exit(1);
+        }
+    };
+
+    let mut navigator = Navigator::new(manual);
+    let mut stdin = io::stdin().lock();
+    let mut message = String::new();
+    loop {
+        // 清屏后重绘
+        print!("\x1b[2J\x1b[H{}\n{HELP}\n", navigator.render());
+        if !message.is_empty() {
+            println!("{message}");
+            message.clear();
+        }
+        let _ = io::stdout().flush();
+
+        let Some(key) = read_key(&mut stdin) else {
+            break;
+        };
+        let moved = match key {
+            Key::Forward | Key::Char('n' | ' ' | '\n' | '\r') => navigator.forward(0),
+            Key::Back | Key::Char('b' | 'p') => navigator.back(),
+            Key::Char('s') => {
+                navigator.to_start();
+                true
+            }
+            Key::Char('e') => {
+                navigator.to_end();
+                true
+            }
+            Key::Char('f') => {
+                navigator.flip();
+                true
+            }
+            Key::Char('q') => break,
+            Key::Char('g') => {
+                print!("跳至第几着: ");
+                let _ = io::stdout().flush();
+                match read_number(&mut stdin) {
+                    Some(ply) => navigator.go_to(ply) == ply,
+                    None => false,
+                }
+            }
+            Key::Char(ch) => match ch.to_digit(10) {
+                Some(variation) => navigator.forward(variation as usize),
+                None => {
+                    message = format!("无效按键: {}", ch.escape_default());
+                    true
+                }
+            },
+        };
+        if !moved && message.is_empty() {
+            message = String::from("无法移动");
+        }
+    }
+    drop(key_mode);
+    println!();
+}
@@ -0,0 +1,329 @@
+// 棋谱库工具：xqtool <子命令> ...
+use lib::annotator;
+use lib::board_text::{TextOptions, TextStyle};
+use lib::coord::RecordType;
+use lib::dedup::{DedupOptions, Deduplicator};
+use lib::info::Win;
+use lib::manual::Manual;
+use lib::metadata::{AliasTable, MetadataIndex, Normalizer};
+use lib::navigator::Navigator;
+use lib::png::{self, PngOptions, Theme};
+use lib::search::{self, AnalysisOptions};
+use lib::stats::{self, ManualSource, StatsOptions};
+use std::path::Path;
+use std::time::Duration;
+
+const USAGE: &str = "用法:
+  xqtool stats <目录> [--json] [--moves N] [--top N] [--result 红胜|黑胜|和棋|未知]
+  xqtool normalize <目录> [--aliases <别名表>] [--output <目录> [--format <扩展名>]]
+  xqtool dedup <目录> [--min-moves N]
+  xqtool board <棋谱> [--style plain|ascii|grid] [--coords] [--color] [--flip] [--ply N]
+  xqtool png <棋谱> <输出> [--size N] [--theme wood|print] [--flip] [--ply N | --remarks]
+  xqtool analyze <棋谱> [--ply N | --path 0,1,...] [--depth N] [--multipv N] [--time 毫秒] [--output <文件>]";
+
+fn exit_usage(message: &str) -> ! {
+    eprintln!("{message}\n{USAGE}");
+    std::process::exit(2);
+}
+
+fn parse_number(value: Option<String>, flag: &str) -> usize {
+    value
+        .and_then(|num_str| num_str.parse().ok())
+        .unwrap_or_else(|| exit_usage(&format!("{flag} 需要数字参数")))
+}
+
+// 统计目录(含子目录)下的全部棋谱
+fn run_stats(args: &mut impl Iterator<Item = String>) {
+    let mut dir = None;
+    let mut json = false;
+    let mut options = StatsOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--moves" => options.opening_moves = parse_number(args.next(), "--moves"),
+            "--top" => options.top = parse_number(args.next(), "--top"),
+            "--result" => match args.next().as_deref().and_then(Win::from_string) {
+                Some(win) => options.result = Some(win),
+                None => exit_usage("--result 需要对局结果参数"),
+            },
+            _ if dir.is_none() && !arg.starts_with("--") => dir = Some(arg),
+            _ => exit_usage(&format!("未知参数 {arg}")),
+        }
+    }
+    let dir = dir.unwrap_or_else(|| exit_usage("缺少棋谱目录"));
+    if !Path::new(&dir).is_dir() {
+        exit_usage(&format!("{dir} 不是目录"));
+    }
+
+    match stats::collect_dir(Path::new(&dir), options) {
+        Ok(report) if json => println!("{}", report.to_json()),
+        Ok(report) => print!("{report}"),
+        Err(err) => {
+            eprintln!("读取 {dir} 失败: {err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+// 规范化目录下棋谱的棋手、日期等信息，输出索引，可另存规范化后的棋谱
+fn run_normalize(args: &mut impl Iterator<Item = String>) {
+    let mut dir = None;
+    let mut aliases = AliasTable::new();
+    let mut output_dir = None;
+    let mut ext_name = String::from("txt");
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--aliases" => {
+                let file_name = args
+                    .next()
+                    .unwrap_or_else(|| exit_usage("--aliases 需要文件参数"));
+                match std::fs::read_to_string(&file_name) {
+                    Ok(table_str) => aliases = AliasTable::from_string(&table_str),
+                    Err(err) => exit_usage(&format!("读取 {file_name} 失败: {err}")),
+                }
+            }
+            "--output" => output_dir = args.next(),
+            "--format" => ext_name = args.next().unwrap_or(ext_name),
+            _ if dir.is_none() && !arg.starts_with("--") => dir = Some(arg),
+            _ => exit_usage(&format!("未知参数 {arg}")),
+        }
+    }
+    let dir = dir.unwrap_or_else(|| exit_usage("缺少棋谱目录"));
+    let source = Path::new(&dir);
+    let file_names = source.names().unwrap_or_else(|err| {
+        eprintln!("读取 {dir} 失败: {err}");
+        std::process::exit(1);
+    });
+
+    let normalizer = Normalizer::new(aliases);
+    let mut index = MetadataIndex::new();
+    for file_name in file_names {
+        let mut manual = match source.read(&file_name) {
+            Ok(manual) => manual,
+            Err(err) => {
+                eprintln!("{err}");
+                continue;
+            }
+        };
+        let (metadata, errors) = normalizer.normalize(&mut manual, Some(&file_name));
+        for err in errors {
+            eprintln!("{}", err.in_file(&file_name));
+        }
+        index.add(&file_name, &metadata);
+
+        if let Some(output_dir) = &output_dir {
+            let stem = Path::new(&file_name)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let output_name = Path::new(output_dir)
+                .join(format!("{stem}.{ext_name}"))
+                .to_string_lossy()
+                .to_string();
+            if let Err(err) = manual.write(&output_name) {
+                eprintln!("{err}");
+            }
+        }
+    }
+
+    print!("{index}");
+}
+
+// 查找目录下重复的棋谱，列出每组应保留的文件
+fn run_dedup(args: &mut impl Iterator<Item = String>) {
+    let mut dir = None;
+    let mut options = DedupOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--min-moves" => options.min_moves = parse_number(args.next(), "--min-moves"),
+            _ if dir.is_none() && !arg.starts_with("--") => dir = Some(arg),
+            _ => exit_usage(&format!("未知参数 {arg}")),
+        }
+    }
+    let dir = dir.unwrap_or_else(|| exit_usage("缺少棋谱目录"));
+
+    let mut deduplicator = Deduplicator::new(options);
+    if let Err(err) = deduplicator.add_dir(Path::new(&dir)) {
+        eprintln!("读取 {dir} 失败: {err}");
+        std::process::exit(1);
+    }
+    for (file_name, error) in &deduplicator.failures {
+        eprintln!("{file_name}: {error}");
+    }
+    let groups = deduplicator.groups();
+    for group in &groups {
+        print!("{group}");
+    }
+    println!("重复棋谱: {} 组", groups.len());
+}
+
+// 输出棋谱第N着后的局面
+fn run_board(args: &mut impl Iterator<Item = String>) {
+    let mut file_name = None;
+    let mut options = TextOptions::default();
+    let mut ply = 0;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--style" => {
+                options.style = match args.next().as_deref() {
+                    Some("plain") => TextStyle::Plain,
+                    Some("ascii") => TextStyle::Ascii,
+                    Some("grid") => TextStyle::Grid,
+                    _ => exit_usage("--style 需要 plain、ascii 或 grid"),
+                }
+            }
+            "--coords" => options.coords = true,
+            "--color" => options.color = true,
+            "--flip" => options.flipped = true,
+            "--ply" => ply = parse_number(args.next(), "--ply"),
+            _ if file_name.is_none() && !arg.starts_with("--") => file_name = Some(arg),
+            _ => exit_usage(&format!("未知参数 {arg}")),
+        }
+    }
+    let file_name = file_name.unwrap_or_else(|| exit_usage("缺少棋谱文件"));
+
+    let manual = Manual::from(&file_name).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1);
+    });
+    let mut navigator = Navigator::new(manual);
+    navigator.go_to(ply);
+    print!("{}", navigator.board_text(&options));
+}
+
+// 输出局面图：第N着后的局面存为一个文件，或每条备注各存一图于输出目录
+fn run_png(args: &mut impl Iterator<Item = String>) {
+    let mut names = Vec::new();
+    let mut options = PngOptions::new();
+    let mut flipped = false;
+    let mut ply = 0;
+    let mut remarks = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" => options.grid_size = parse_number(args.next(), "--size"),
+            "--theme" => match args.next().as_deref().and_then(Theme::from_name) {
+                Some(theme) => options.theme = theme,
+                None => exit_usage("--theme 需要 wood 或 print"),
+            },
+            "--flip" => flipped = true,
+            "--ply" => ply = parse_number(args.next(), "--ply"),
+            "--remarks" => remarks = true,
+            _ if names.len() < 2 && !arg.starts_with("--") => names.push(arg),
+            _ => exit_usage(&format!("未知参数 {arg}")),
+        }
+    }
+    let [file_name, output_name] =
+        <[String; 2]>::try_from(names).unwrap_or_else(|_| exit_usage("需要棋谱文件及输出路径"));
+
+    let manual = Manual::from(&file_name).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1);
+    });
+    let write = |name: &str, png: &[u8]| {
+        if let Err(err) = std::fs::write(name, png) {
+            eprintln!("写入 {name} 失败: {err}");
+            std::process::exit(1);
+        }
+    };
+    if remarks {
+        if flipped {
+            options.bottom_color = lib::piece::other_color(options.bottom_color);
+        }
+        if let Err(err) = std::fs::create_dir_all(&output_name) {
+            exit_usage(&format!("创建 {output_name} 失败: {err}"));
+        }
+        let diagrams = png::remark_diagrams(&manual, &options);
+        for (index, diagram) in diagrams.iter().enumerate() {
+            let name = Path::new(&output_name).join(format!("{:03}.png", index + 1));
+            write(&name.to_string_lossy(), &diagram.png);
+            println!(
+                "{}: {}",
+                name.display(),
+                diagram.remark.lines().next().unwrap_or("")
+            );
+        }
+    } else {
+        let mut navigator = Navigator::new(manual);
+        navigator.go_to(ply);
+        if flipped {
+            navigator.flip();
+        }
+        write(&output_name, &navigator.png(&options));
+    }
+}
+
+// 分析主线第N着后或按序号路径走入变着后的局面，列出候选着法，可将候选变例插入棋谱另存
+fn run_analyze(args: &mut impl Iterator<Item = String>) {
+    let mut file_name = None;
+    let mut output_name = None;
+    let mut options = AnalysisOptions::default();
+    let mut path = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ply" => path = vec![0; parse_number(args.next(), "--ply")],
+            "--path" => {
+                path = args
+                    .next()
+                    .and_then(|path_str| {
+                        path_str
+                            .split(',')
+                            .map(|index_str| index_str.trim().parse().ok())
+                            .collect()
+                    })
+                    .unwrap_or_else(|| exit_usage("--path 需要以逗号分隔的着法序号"))
+            }
+            "--depth" => options.depth = parse_number(args.next(), "--depth"),
+            "--multipv" => options.multi_pv = parse_number(args.next(), "--multipv"),
+            "--time" => {
+                let millis = parse_number(args.next(), "--time");
+                options.time_limit = Some(Duration::from_millis(millis as u64));
+            }
+            "--output" => output_name = args.next(),
+            _ if file_name.is_none() && !arg.starts_with("--") => file_name = Some(arg),
+            _ => exit_usage(&format!("未知参数 {arg}")),
+        }
+    }
+    let file_name = file_name.unwrap_or_else(|| exit_usage("缺少棋谱文件"));
+
+    let manual = Manual::from(&file_name).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1);
+    });
+    let analysis = annotator::analyze_path(&manual, &path, &options)
+        .unwrap_or_else(|| exit_usage(&format!("棋谱中没有着法路径 {path:?}")));
+    println!("深度 {}", analysis.depth);
+    for (index, line) in analysis.lines.iter().enumerate() {
+        let pv: Vec<String> = line
+            .pv
+            .iter()
+            .map(|coordpair| coordpair.to_string(RecordType::PgnIccs))
+            .collect();
+        println!(
+            "{}. {} {}",
+            index + 1,
+            search::score_string(line.score),
+            pv.join(" ")
+        );
+    }
+
+    if let Some(output_name) = output_name {
+        if let Err(err) = manual.write(&output_name) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("stats") => run_stats(&mut args),
+        Some("normalize") => run_normalize(&mut args),
+        Some("dedup") => run_dedup(&mut args),
+        Some("board") => run_board(&mut args),
+        Some("png") => run_png(&mut args),
+        Some("analyze") => run_analyze(&mut args),
+        Some(command) => exit_usage(&format!("未知子命令 {command}")),
+        None => exit_usage("缺少子命令"),
+    }
+}
@@ -80,6 +80,10 @@ impl BitBoard {
         }
     }
 
+    pub(crate) fn get_kind(&self, index: usize) -> piece::Kind {
+        self.kinds[index]
+    }
+
     fn get_move_from_index(&self, index: usize) -> bit_constant::BitAtom {
         let color = self.get_color(index).unwrap();
         let kind = self.kinds[index];
@@ -123,7 +127,7 @@ impl BitBoard {
         self.get_move_from_bitatom(self.color_pieces[color as usize])
     }
 
-    fn is_killed(&self, color: piece::Color) -> bool {
+    pub(crate) fn is_killed(&self, color: piece::Color) -> bool {
         let other_color = piece::other_color(color);
         let king_bitatom = self.color_kind_pieces[color as usize][piece::Kind::King as usize];
         let otherking_bitatom =
@@ -150,13 +154,38 @@ impl BitBoard {
             true
         };
 
-        king_face() || (self.get_move_from_color(other_color) & king_bitatom) != 0
+        king_face() || self.is_checked(color)
+    }
+
+    // color方将帅是否受对方攻击(不含照面)
+    pub(crate) fn is_checked(&self, color: piece::Color) -> bool {
+        let king_bitatom = self.color_kind_pieces[color as usize][piece::Kind::King as usize];
+
+        (self.get_move_from_color(piece::other_color(color)) & king_bitatom) != 0
     }
 
     fn is_failed(&self, color: piece::Color) -> bool {
         self.get_move_from_color(color) == 0
     }
 
+    // color方走后不被将军、不照面的全部着法
+    pub(crate) fn get_legal_moves(&mut self, color: piece::Color) -> Vec<(usize, usize)> {
+        let mut result = Vec::new();
+        for from_index in bit_constant::get_indexs_from_bitatom(self.color_pieces[color as usize]) {
+            for to_index in
+                bit_constant::get_indexs_from_bitatom(self.get_move_from_index(from_index))
+            {
+                let eat_kind = self.do_move(from_index, to_index);
+                if eat_kind == piece::Kind::King || !self.is_killed(color) {
+                    result.push((from_index, to_index));
+                }
+                self.undo_move(from_index, to_index, eat_kind);
+            }
+        }
+
+        result
+    }
+
     pub fn do_move(&mut self, from_index: usize, to_index: usize) -> piece::Kind {
         self.operate_move(from_index, to_index, false, piece::Kind::NoKind)
     }
@@ -306,6 +335,11 @@ impl BitBoard {
         self.lock ^ bit_constant::COLORZOBRISTLOCK[color as usize]
     }
 
+    // 局面哈希(color为轮走方)
+    pub fn get_key_lock(&self, color: piece::Color) -> (u64, u64) {
+        (self.get_key(color), self.get_lock(color))
+    }
+
     fn get_zorbist_evaluation(
         &self,
         color: piece::Color,
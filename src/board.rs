@@ -152,7 +152,7 @@ pub fn get_bottom_color(pieces: &Pieces) -> piece::Color {
         }
     }
 
-    assert!(false, "没有找到将帅棋子。");
+    // 没有找到将帅棋子时默认红方在下，局面是否合法由validation检查
     piece::Color::Red
 }
 
@@ -167,6 +167,14 @@ impl Board {
         }
     }
 
+    pub fn pieces(&self) -> &Pieces {
+        &self.pieces
+    }
+
+    pub fn bottom_color(&self) -> piece::Color {
+        get_bottom_color(&self.pieces)
+    }
+
     pub fn get_fen(&self) -> String {
         piece_chars_to_fen(&pieces_to_piece_chars(&self.pieces))
     }
@@ -286,22 +294,32 @@ impl Board {
     }
 
     pub fn get_coordpair_from_zhstr(&self, zhstr: &str) -> CoordPair {
+        self.try_get_coordpair_from_zhstr(zhstr)
+            .unwrap_or_else(|| panic!("invalid zhstr: {zhstr}"))
+    }
+
+    // 着法字符串不合规范或与局面不符时返回None，不检查着法是否合法
+    pub fn try_get_coordpair_from_zhstr(&self, zhstr: &str) -> Option<CoordPair> {
         let zh_chs: Vec<char> = zhstr.chars().collect();
-        assert_eq!(zh_chs.len(), 4);
+        if zh_chs.len() != 4 {
+            return None;
+        }
 
         let color = Self::get_color(zh_chs[3]);
         let color_is_bottom = color == get_bottom_color(&self.pieces);
         let mut index = 0;
-        let move_dir = Self::get_move_dir(zh_chs[2]);
+        let move_dir = Self::get_move_dir(zh_chs[2])?;
         let abs_row_sub = (move_dir == MoveDir::Forward) == color_is_bottom;
 
         let mut live_coords: Vec<Coord>;
         let mut kind = piece::kind_from_name(zh_chs[0]);
         if kind != piece::Kind::NoKind {
-            let col = Self::get_col(color, zh_chs[1]);
+            let col = Self::get_col(color, zh_chs[1])?;
             let from_col = Coord::get_side_col(col, color_is_bottom);
             live_coords = self.get_coords_from_color_kind_col(color, kind, from_col);
-            assert!(live_coords.len() > 0);
+            if live_coords.is_empty() {
+                return None;
+            }
 
             // 士、象同列时不分前后，以进、退区分棋子位置
             if live_coords.len() == 2 && move_dir == MoveDir::Forward {
@@ -309,55 +327,54 @@ impl Board {
             }
         } else {
             kind = piece::kind_from_name(zh_chs[1]);
-            live_coords = if kind == piece::Kind::Pawn {
-                self.get_coords_from_color_multi_pawn(color)
-            } else {
-                self.get_coords_from_color_kind(color, kind)
+            live_coords = match kind {
+                piece::Kind::NoKind => return None,
+                piece::Kind::Pawn => self.get_coords_from_color_multi_pawn(color),
+                _ => self.get_coords_from_color_kind(color, kind),
             };
-            assert!(live_coords.len() > 1);
+            if live_coords.len() < 2 {
+                return None;
+            }
 
             let pre_chars = Self::get_pre_chars(live_coords.len());
-            index = pre_chars.iter().position(|&ch| ch == zh_chs[0]).unwrap();
+            index = pre_chars.iter().position(|&ch| ch == zh_chs[0])?;
+        }
+        if live_coords.len() <= index {
+            return None;
         }
-        assert!(live_coords.len() > index);
 
         Self::sort_coords(&mut live_coords, color_is_bottom);
         let from_coord = live_coords[index];
         let mut to_row = from_coord.row;
-        let col = Self::get_col(color, zh_chs[3]);
+        let col = Self::get_col(color, zh_chs[3])?;
         let mut to_col = Coord::get_side_col(col, color_is_bottom);
-        if piece::is_line_move(kind) {
-            if move_dir != MoveDir::Parallel {
+        let row_inc = if piece::is_line_move(kind) {
+            if move_dir == MoveDir::Parallel {
+                0
+            } else {
                 to_col = from_coord.col;
-                if abs_row_sub {
-                    to_row -= col + 1;
-                } else {
-                    to_row += col + 1;
-                }
+                col + 1
             }
         } else {
             // 斜线走子：仕、相、马
-            let col_away = (to_col as isize - from_coord.col as isize).abs() as usize;
+            let col_away = (to_col as isize - from_coord.col as isize).unsigned_abs();
             //  相距1或2列
-            let row_inc = if kind == piece::Kind::Advisor || kind == piece::Kind::Bishop {
+            if kind == piece::Kind::Advisor || kind == piece::Kind::Bishop {
                 col_away
+            } else if col_away == 1 {
+                2
             } else {
-                if col_away == 1 {
-                    2
-                } else {
-                    1
-                }
-            };
-
-            if abs_row_sub {
-                to_row -= row_inc;
-            } else {
-                to_row += row_inc;
+                1
             }
+        };
+        if abs_row_sub {
+            to_row = to_row.checked_sub(row_inc)?;
+        } else {
+            to_row += row_inc;
         }
 
-        let to_coord = Coord::from(to_row, to_col).unwrap();
-        CoordPair::from(from_coord, to_coord)
+        let to_coord = Coord::from(to_row, to_col).ok()?;
+        Some(CoordPair::from(from_coord, to_coord))
     }
 
     fn get_coords_from_color_kind(&self, color: piece::Color, kind: piece::Kind) -> Vec<Coord> {
@@ -421,15 +438,14 @@ impl Board {
         });
     }
 
-    fn get_col_ch(color: piece::Color, col: usize) -> char {
+    pub fn get_col_ch(color: piece::Color, col: usize) -> char {
         NUMCHARS[color as usize][col]
     }
 
-    fn get_col(color: piece::Color, col_char: char) -> usize {
+    fn get_col(color: piece::Color, col_char: char) -> Option<usize> {
         NUMCHARS[color as usize]
             .iter()
             .position(|&ch| ch == col_char)
-            .unwrap()
     }
 
     fn get_color(num_ch: char) -> piece::Color {
@@ -460,9 +476,9 @@ impl Board {
         }]
     }
 
-    fn get_move_dir(move_ch: char) -> MoveDir {
-        MoveDir::try_from_primitive(MOVECHARS.iter().position(|&ch| ch == move_ch).unwrap())
-            .unwrap()
+    fn get_move_dir(move_ch: char) -> Option<MoveDir> {
+        let index = MOVECHARS.iter().position(|&ch| ch == move_ch)?;
+        MoveDir::try_from_primitive(index).ok()
     }
 
     pub fn get_pgnzh_pattern() -> String {
@@ -0,0 +1,264 @@
+#![allow(dead_code)]
+
+use crate::board;
+use crate::coord::{COLCOUNT, ROWCOUNT};
+use crate::piece::{Color, Piece};
+
+// 棋盘文本的样式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextStyle {
+    // 全角汉字，空位为"－"，同Board::to_string
+    Plain,
+    // 英文字母KABNRCP，红方大写、黑方小写，空位为"."
+    Ascii,
+    // 带棋盘线、九宫斜线及"楚河 汉界"的传统棋盘
+    Grid,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextOptions {
+    pub style: TextStyle,
+    // 左侧及下方标注ICCS坐标
+    pub coords: bool,
+    // 以ANSI转义序列为双方棋子着色
+    pub color: bool,
+    // 翻转棋盘，上下左右对调
+    pub flipped: bool,
+}
+
+impl TextOptions {
+    pub fn new(style: TextStyle) -> Self {
+        TextOptions {
+            style,
+            coords: false,
+            color: false,
+            flipped: false,
+        }
+    }
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self::new(TextStyle::Plain)
+    }
+}
+
+const REDANSI: &str = "\x1b[1;31m";
+const BLACKANSI: &str = "\x1b[1;32m";
+const RESETANSI: &str = "\x1b[0m";
+
+// 河界所在的行间
+const RIVERROW: usize = 4;
+const RIVERNEXTROW: usize = RIVERROW + 1;
+const ROWLAST: usize = ROWCOUNT - 1;
+
+fn colored(text: &str, color: Color, options: &TextOptions) -> String {
+    if !options.color {
+        return text.to_string();
+    }
+
+    let ansi = match color {
+        Color::Red => REDANSI,
+        Color::Black => BLACKANSI,
+    };
+    format!("{ansi}{text}{RESETANSI}")
+}
+
+fn piece_text(piece: Piece, options: &TextOptions) -> Option<String> {
+    match piece {
+        Piece::Some(color, _) => {
+            let ch = match options.style {
+                TextStyle::Ascii => piece.ch(),
+                _ => piece.print_name(),
+            };
+            Some(colored(&ch.to_string(), color, options))
+        }
+        Piece::None => None,
+    }
+}
+
+// Grid样式中空位处的棋盘线交点，竖线在河界处中断，两侧边线贯通
+fn cross_char(row: usize, col: usize) -> char {
+    let (left, right) = (col == 0, col == COLCOUNT - 1);
+    match (row, left, right) {
+        (0, true, _) => '┌',
+        (0, _, true) => '┐',
+        (0, _, _) => '┬',
+        (ROWLAST, true, _) => '└',
+        (ROWLAST, _, true) => '┘',
+        (ROWLAST, _, _) => '┴',
+        (_, true, _) => '├',
+        (_, _, true) => '┤',
+        (RIVERROW, _, _) => '┴',
+        (RIVERNEXTROW, _, _) => '┬',
+        _ => '┼',
+    }
+}
+
+// Grid样式中row行与row+1行之间的连线，九宫内有斜线
+fn grid_between(row: usize) -> String {
+    if row == RIVERROW {
+        return format!("│ 楚 河{}汉 界 │", " ".repeat(11));
+    }
+
+    let mut result = String::new();
+    for col in 0..COLCOUNT {
+        result.push('│');
+        if col == COLCOUNT - 1 {
+            break;
+        }
+
+        let in_palace = !(2..ROWCOUNT - 3).contains(&row);
+        // 上半行的斜线自左上向右下，下半行相反
+        let is_down = row == 0 || row == ROWCOUNT - 3;
+        let diagonal = match col {
+            3 if in_palace => Some(if is_down { '╲' } else { '╱' }),
+            4 if in_palace => Some(if is_down { '╱' } else { '╲' }),
+            _ => None,
+        };
+        match diagonal {
+            Some(ch) => result.push_str(&format!(" {ch}")),
+            None => result.push_str("  "),
+        }
+    }
+
+    result
+}
+
+// 屏幕上第row行、第col列对应的棋盘坐标
+fn board_index(row: usize, col: usize, options: &TextOptions) -> (usize, usize) {
+    if options.flipped {
+        (ROWCOUNT - 1 - row, COLCOUNT - 1 - col)
+    } else {
+        (row, col)
+    }
+}
+
+pub fn render(board: &board::Board, options: &TextOptions) -> String {
+    let pieces = board.pieces();
+    let mut lines = Vec::new();
+    for row in 0..ROWCOUNT {
+        let mut line = String::new();
+        for col in 0..COLCOUNT {
+            let (board_row, board_col) = board_index(row, col, options);
+            let piece = pieces[board_row * COLCOUNT + board_col];
+            let is_last = col == COLCOUNT - 1;
+            match options.style {
+                TextStyle::Plain => {
+                    line.push_str(&piece_text(piece, options).unwrap_or(String::from("－")))
+                }
+                TextStyle::Ascii => {
+                    line.push_str(&piece_text(piece, options).unwrap_or(String::from(".")));
+                    if !is_last {
+                        line.push(' ');
+                    }
+                }
+                TextStyle::Grid => {
+                    // 每个交点占两列，其后接一列横线
+                    match piece_text(piece, options) {
+                        Some(text) => line.push_str(&text),
+                        None => {
+                            line.push(cross_char(row, col));
+                            if !is_last {
+                                line.push('─');
+                            }
+                        }
+                    }
+                    if !is_last {
+                        line.push('─');
+                    }
+                }
+            }
+        }
+
+        if options.coords {
+            let (board_row, _) = board_index(row, 0, options);
+            line = format!("{board_row} {line}");
+        }
+        lines.push(line);
+        if options.style == TextStyle::Grid && row < ROWCOUNT - 1 {
+            let between = grid_between(row);
+            lines.push(if options.coords {
+                format!("  {between}")
+            } else {
+                between
+            });
+        }
+    }
+
+    if options.coords {
+        let width = match options.style {
+            TextStyle::Plain | TextStyle::Ascii => 2,
+            TextStyle::Grid => 3,
+        };
+        let mut line = String::from("  ");
+        for col in 0..COLCOUNT {
+            let (_, board_col) = board_index(0, col, options);
+            let col_ch = char::from(b'A' + board_col as u8);
+            line.push_str(&format!("{col_ch:width$}"));
+        }
+        lines.push(line.trim_end().to_string());
+    }
+
+    let mut result = lines.join("\n");
+    result.push('\n');
+    result
+}
+
+impl board::Board {
+    pub fn to_text(&self, options: &TextOptions) -> String {
+        render(self, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::ChangeType;
+
+    #[test]
+    fn test_board_text() {
+        let board = board::Board::new();
+        assert_eq!(board.to_string(), render(&board, &TextOptions::default()));
+
+        let mut options = TextOptions::new(TextStyle::Ascii);
+        assert!(render(&board, &options)
+            .starts_with("r n b a k a b n r\n. . . . . . . . .\n. c . . . . . c .\n"));
+        options.coords = true;
+        let text = render(&board, &options);
+        assert!(text.starts_with("0 r n b a k a b n r\n"));
+        assert!(text.ends_with("9 R N B A K A B N R\n  A B C D E F G H I\n"));
+
+        // 翻转后坐标随之对调
+        options.flipped = true;
+        let text = render(&board, &options);
+        assert!(text.starts_with("9 R N B A K A B N R\n"));
+        assert!(text.ends_with("0 r n b a k a b n r\n  I H G F E D C B A\n"));
+        let mut rotated = board;
+        rotated.to_change(ChangeType::Rotate);
+        options.coords = false;
+        assert_eq!(
+            render(&rotated, &TextOptions::new(TextStyle::Ascii)),
+            render(&board, &options)
+        );
+
+        let text = render(&board, &TextOptions::new(TextStyle::Grid));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(19, lines.len());
+        assert_eq!("車─馬─象─士─将─士─象─馬─車", lines[0]);
+        assert_eq!("│  │  │  │ ╲│ ╱│  │  │  │", lines[1]);
+        assert_eq!("├──砲─┼──┼──┼──┼──┼──砲─┤", lines[4]);
+        assert_eq!("卒─┼──卒─┼──卒─┼──卒─┼──卒", lines[6]);
+        assert_eq!("├──┴──┴──┴──┴──┴──┴──┴──┤", lines[8]);
+        assert_eq!("├──┬──┬──┬──┬──┬──┬──┬──┤", lines[10]);
+        assert_eq!("│ 楚 河           汉 界 │", lines[9]);
+        assert_eq!("│  │  │  │ ╱│ ╲│  │  │  │", lines[17]);
+        assert_eq!("车─马─相─仕─帅─仕─相─马─车", lines[18]);
+
+        let mut options = TextOptions::new(TextStyle::Plain);
+        options.color = true;
+        let text = render(&board, &options);
+        assert!(text.starts_with("\x1b[1;32m車\x1b[0m"));
+        assert!(text.contains("\x1b[1;31m帅\x1b[0m"));
+    }
+}
@@ -1,39 +1,197 @@
 #![allow(dead_code)]
 
+use crate::coord;
 use std::error;
 use std::fmt;
+use std::io;
 
-pub type Result<T> = std::result::Result<T, ParseError>;
+pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Clone, Debug)]
-pub enum ParseError {
+// 将死局面的分值，减去步数使较快的杀着得分更高
+pub const MATESCORE: i32 = 30000;
+
+// 分值是否表示可以成杀(正)或被杀(负)
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() > MATESCORE - 1000
+}
+
+// 杀棋分值对应的着数，成杀为正，被杀为负
+pub fn mate_moves(score: i32) -> Option<i32> {
+    if !is_mate_score(score) {
+        return None;
+    }
+
+    let moves = (MATESCORE - score.abs() + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+// mate_moves的逆运算
+pub fn mate_score(moves: i32) -> i32 {
+    let score = MATESCORE - (moves.abs() * 2 - 1);
+    if moves > 0 {
+        score
+    } else {
+        -score
+    }
+}
+
+// 出错位置：文件名、行号(文本格式)、字节偏移(二进制格式)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Location {
+    pub file_name: Option<String>,
+    pub line: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum Error {
     RowOut,
     ColOut,
     IndexOut,
     StringParse,
-    RecordTypeError,
+    UnknownRecordType,
+    BoardNotMatch,
+    Unsupported(coord::RecordType),
+    Io(io::Error),
+    Xqf(String),
+    Bin(String),
+    Text(String),
+    Json(serde_json::Error),
+    DhtmlXq(String),
+    Tablebase(String),
+    Info(String),
+    Context(Location, Box<Error>),
 }
 
-impl fmt::Display for ParseError {
+impl Error {
+    fn locate(self, update: impl FnOnce(&mut Location)) -> Self {
+        let (mut location, source) = match self {
+            Error::Context(location, source) => (location, source),
+            error => (Location::default(), Box::new(error)),
+        };
+        update(&mut location);
+
+        Error::Context(location, source)
+    }
+
+    pub fn in_file(self, file_name: &str) -> Self {
+        self.locate(|location| location.file_name = Some(file_name.to_string()))
+    }
+
+    pub fn at_line(self, line: usize) -> Self {
+        self.locate(|location| location.line = Some(line))
+    }
+
+    pub fn at_offset(self, offset: usize) -> Self {
+        self.locate(|location| location.offset = Some(offset))
+    }
+
+    // 片段内的行号换算为全文行号
+    pub fn shift_line(self, lines: usize) -> Self {
+        match self {
+            Error::Context(mut location, source) => {
+                location.line = location.line.map(|line| line + lines);
+                Error::Context(location, source)
+            }
+            error => error,
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Error::Context(location, _) => Some(location),
+            _ => None,
+        }
+    }
+
+    // 去除位置信息后的错误
+    pub fn kind(&self) -> &Error {
+        match self {
+            Error::Context(_, source) => source.kind(),
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for Location {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "invalid value(kind: {:?}) to coord.", self)
+        let mut parts = Vec::new();
+        if let Some(file_name) = &self.file_name {
+            parts.push(file_name.clone());
+        }
+        if let Some(line) = self.line {
+            parts.push(format!("line {line}"));
+        }
+        if let Some(offset) = self.offset {
+            parts.push(format!("byte {offset}"));
+        }
+
+        write!(f, "{}", parts.join(", "))
     }
 }
 
-impl error::Error for ParseError {
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::RowOut => write!(f, "row out of range"),
+            Error::ColOut => write!(f, "column out of range"),
+            Error::IndexOut => write!(f, "index out of range"),
+            Error::StringParse => write!(f, "invalid coordinate string"),
+            Error::UnknownRecordType => write!(f, "unknown record type"),
+            Error::BoardNotMatch => write!(f, "starting positions do not match"),
+            Error::Unsupported(record_type) => {
+                write!(f, "{:?} format is not supported here", record_type)
+            }
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Xqf(message) => write!(f, "invalid XQF data: {message}"),
+            Error::Bin(message) => write!(f, "invalid binary data: {message}"),
+            Error::Text(message) => write!(f, "invalid text record: {message}"),
+            Error::Json(err) => write!(f, "invalid JSON: {err}"),
+            Error::DhtmlXq(message) => write!(f, "invalid DhtmlXQ data: {message}"),
+            Error::Tablebase(message) => write!(f, "tablebase error: {message}"),
+            Error::Info(message) => write!(f, "invalid info: {message}"),
+            Error::Context(location, source) => write!(f, "{location}: {source}"),
+        }
+    }
+}
+
+impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        None
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Json(err) => Some(err),
+            Error::Context(_, source) => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
     }
 }
 
 // use std::convert::TryInto;
 use crate::coord::CoordPair;
 
-fn read_bytes(input: &mut &[u8], size: usize) -> Vec<u8> {
+// 读取失败时input不变，以便调用者据其余长计算出错偏移
+pub fn read_bytes(input: &mut &[u8], size: usize) -> Result<Vec<u8>> {
+    if input.len() < size {
+        return Err(Error::Bin(format!(
+            "unexpected end of data, {size} bytes expected"
+        )));
+    }
     let (bytes, rest) = input.split_at(size);
     *input = rest;
 
-    bytes.to_vec()
+    Ok(bytes.to_vec())
 }
 
 pub fn write_coordpair(output: &mut Vec<u8>, coordpair: &CoordPair) {
@@ -50,8 +208,8 @@ pub fn write_string(output: &mut Vec<u8>, string: &str) {
     output.append(&mut string.as_bytes().to_vec());
 }
 
-pub fn read_coordpair(input: &mut &[u8]) -> CoordPair {
-    let bytes = read_bytes(input, 4);
+pub fn read_coordpair(input: &mut &[u8]) -> Result<CoordPair> {
+    let bytes = read_bytes(input, 4)?;
 
     CoordPair::from_row_col(
         bytes[0] as usize,
@@ -59,18 +217,17 @@ pub fn read_coordpair(input: &mut &[u8]) -> CoordPair {
         bytes[2] as usize,
         bytes[3] as usize,
     )
-    .unwrap()
 }
 
-pub fn read_be_u32(input: &mut &[u8]) -> u32 {
-    let bytes = read_bytes(input, std::mem::size_of::<u32>());
+pub fn read_be_u32(input: &mut &[u8]) -> Result<u32> {
+    let bytes = read_bytes(input, std::mem::size_of::<u32>())?;
 
-    u32::from_be_bytes(bytes.try_into().unwrap())
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
 }
 
-pub fn read_string(input: &mut &[u8]) -> String {
-    let size = read_be_u32(input) as usize;
-    let bytes = read_bytes(input, size);
+pub fn read_string(input: &mut &[u8]) -> Result<String> {
+    let size = read_be_u32(input)? as usize;
+    let bytes = read_bytes(input, size)?;
 
-    String::from_utf8(bytes.try_into().unwrap()).unwrap()
+    String::from_utf8(bytes).map_err(|_| Error::Bin(String::from("invalid utf-8 string")))
 }
@@ -18,6 +18,8 @@ pub enum RecordType {
     PgnIccs,
     PgnRc,
     PgnZh,
+    Json,
+    DhtmlXq,
 }
 
 impl RecordType {
@@ -26,12 +28,10 @@ impl RecordType {
     }
 
     pub fn get_record_type(file_name: &str) -> common::Result<RecordType> {
-        let ext_pos = file_name
-            .rfind('.')
-            .ok_or(common::ParseError::RecordTypeError)?;
+        let ext_pos = file_name.rfind('.').ok_or(common::Error::UnknownRecordType)?;
         let ext_name = file_name
             .get(ext_pos + 1..)
-            .ok_or(common::ParseError::RecordTypeError)?;
+            .ok_or(common::Error::UnknownRecordType)?;
 
         match ext_name {
             _ if ext_name == RecordType::Xqf.ext_name() => Ok(RecordType::Xqf),
@@ -40,7 +40,9 @@ impl RecordType {
             _ if ext_name == RecordType::PgnIccs.ext_name() => Ok(RecordType::PgnIccs),
             _ if ext_name == RecordType::PgnRc.ext_name() => Ok(RecordType::PgnRc),
             _ if ext_name == RecordType::PgnZh.ext_name() => Ok(RecordType::PgnZh),
-            _ => Err(common::ParseError::RecordTypeError),
+            _ if ext_name == RecordType::Json.ext_name() => Ok(RecordType::Json),
+            _ if ext_name == RecordType::DhtmlXq.ext_name() => Ok(RecordType::DhtmlXq),
+            _ => Err(common::Error::UnknownRecordType),
         }
     }
 }
@@ -73,9 +75,9 @@ impl Coord {
 
     pub fn from(row: usize, col: usize) -> common::Result<Self> {
         if row >= ROWCOUNT {
-            Err(common::ParseError::RowOut)
+            Err(common::Error::RowOut)
         } else if col >= COLCOUNT {
-            Err(common::ParseError::ColOut)
+            Err(common::Error::ColOut)
         } else {
             Ok(Coord { row, col })
         }
@@ -90,39 +92,38 @@ impl Coord {
             RecordType::PgnRc => {
                 let row_col = coord_str
                     .parse::<usize>()
-                    .map_err(|_| common::ParseError::StringParse)?;
+                    .map_err(|_| common::Error::StringParse)?;
 
                 Self::from(row_col / 10, row_col % 10)
             }
             RecordType::PgnIccs => {
                 let row = coord_str
                     .get(1..2)
-                    .ok_or(common::ParseError::StringParse)?
+                    .ok_or(common::Error::StringParse)?
                     .parse()
-                    .map_err(|_| common::ParseError::StringParse)?;
-                let col_ch = coord_str
-                    .chars()
-                    .next()
-                    .ok_or(common::ParseError::StringParse)?;
-                let col = col_ch as usize - 'A' as usize;
+                    .map_err(|_| common::Error::StringParse)?;
+                let col_ch = coord_str.chars().next().ok_or(common::Error::StringParse)?;
+                let col = (col_ch as usize)
+                    .checked_sub('A' as usize)
+                    .ok_or(common::Error::StringParse)?;
 
                 Self::from(row, col)
             }
             RecordType::Txt => {
                 let row = coord_str
                     .get(1..2)
-                    .ok_or(common::ParseError::StringParse)?
+                    .ok_or(common::Error::StringParse)?
                     .parse()
-                    .map_err(|_| common::ParseError::StringParse)?;
+                    .map_err(|_| common::Error::StringParse)?;
                 let col = coord_str
                     .get(3..4)
-                    .ok_or(common::ParseError::StringParse)?
+                    .ok_or(common::Error::StringParse)?
                     .parse()
-                    .map_err(|_| common::ParseError::StringParse)?;
+                    .map_err(|_| common::Error::StringParse)?;
 
                 Self::from(row, col)
             }
-            _ => Err(common::ParseError::StringParse),
+            _ => Err(common::Error::StringParse),
         }
     }
 
@@ -0,0 +1,382 @@
+#![allow(dead_code)]
+
+use crate::amove;
+use crate::board;
+use crate::coord::ChangeType;
+use crate::manual;
+use crate::piece;
+use crate::stats;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Clone, Copy, Debug)]
+pub struct DedupOptions {
+    // 主线少于此着数的棋谱不参与比较，以免短小的开局互为前缀
+    pub min_moves: usize,
+}
+
+impl Default for DedupOptions {
+    fn default() -> Self {
+        DedupOptions { min_moves: 10 }
+    }
+}
+
+// 与保留棋谱的关系
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Relation {
+    // 着法及备注均相同
+    Exact,
+    // 主线相同，备注或变着不同
+    RemarksDiffer,
+    // 主线为保留棋谱主线的前段
+    Prefix,
+}
+
+impl fmt::Display for Relation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Relation::Exact => "完全相同",
+            Relation::RemarksDiffer => "仅备注不同",
+            Relation::Prefix => "主线为前段",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+// 主线各局面的Zobrist键序列，左右对称的两种走法取较小者
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    keys: Vec<(u64, u64)>,
+}
+
+impl Fingerprint {
+    pub fn from_manual(manual: &manual::Manual) -> Self {
+        let manual_move = manual.manual_move();
+        let mut board = *manual_move.board();
+        let mut keys = Vec::new();
+        let mut mirrored_keys = Vec::new();
+        let mut push_keys = |board: &board::Board, color: piece::Color| {
+            let mut mirrored_board = *board;
+            mirrored_board.to_change(ChangeType::SymmetryH);
+            keys.push(board.bit_board().get_key_lock(color));
+            mirrored_keys.push(mirrored_board.bit_board().get_key_lock(color));
+        };
+
+        let mut color = piece::Color::Red;
+        let mut before_move = manual_move.root_move();
+        while let Some(amove) = before_move.after().first().cloned() {
+            if let piece::Piece::Some(from_color, _) =
+                board.pieces()[amove.coordpair.from_coord.index()]
+            {
+                color = from_color;
+            }
+            push_keys(&board, color);
+            board.do_move(&amove);
+            color = piece::other_color(color);
+            before_move = amove;
+        }
+        push_keys(&board, color);
+
+        Fingerprint {
+            keys: keys.min(mirrored_keys),
+        }
+    }
+
+    // 着数
+    pub fn len(&self) -> usize {
+        self.keys.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_prefix_of(&self, other: &Fingerprint) -> bool {
+        other.keys.starts_with(&self.keys)
+    }
+}
+
+// 参与比较的一局棋谱
+#[derive(Debug)]
+struct Entry {
+    file_name: String,
+    fingerprint: Fingerprint,
+    // 主线各着的备注
+    remarks: Vec<String>,
+    move_count: usize,
+    remark_len: usize,
+    info_count: usize,
+}
+
+impl Entry {
+    fn from(file_name: &str, manual: &manual::Manual) -> Self {
+        fn count_moves(amove: &Rc<amove::Move>, move_count: &mut usize, remark_len: &mut usize) {
+            *remark_len += amove.remark().chars().count();
+            for after_move in amove.after() {
+                *move_count += 1;
+                count_moves(&after_move, move_count, remark_len);
+            }
+        }
+
+        let root_move = manual.manual_move().root_move();
+        let mut remarks = vec![root_move.remark()];
+        let mut before_move = root_move.clone();
+        while let Some(amove) = before_move.after().first().cloned() {
+            remarks.push(amove.remark());
+            before_move = amove;
+        }
+        let (mut move_count, mut remark_len) = (0, 0);
+        count_moves(&root_move, &mut move_count, &mut remark_len);
+
+        Entry {
+            file_name: file_name.to_string(),
+            fingerprint: Fingerprint::from_manual(manual),
+            remarks,
+            move_count,
+            remark_len,
+            info_count: manual
+                .info()
+                .values()
+                .filter(|value| !value.trim().is_empty())
+                .count(),
+        }
+    }
+
+    // 保留着法最多、备注最详、信息最全者，其余相同时取文件名在前者
+    fn is_better_than(&self, other: &Entry) -> bool {
+        let rank = |entry: &Entry| {
+            (
+                entry.fingerprint.len(),
+                entry.move_count,
+                entry.remark_len,
+                entry.info_count,
+            )
+        };
+
+        (rank(self), &other.file_name) > (rank(other), &self.file_name)
+    }
+}
+
+// 一组重复的棋谱
+#[derive(Debug, PartialEq)]
+pub struct DuplicateGroup {
+    pub keep: String,
+    pub duplicates: Vec<(String, Relation)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Deduplicator {
+    options: DedupOptions,
+    entries: Vec<Entry>,
+    // 读取失败的文件及原因
+    pub failures: Vec<(String, String)>,
+}
+
+impl Deduplicator {
+    pub fn new(options: DedupOptions) -> Self {
+        Deduplicator {
+            options,
+            ..Default::default()
+        }
+    }
+
+    pub fn add(&mut self, file_name: &str, manual: &manual::Manual) {
+        let entry = Entry::from(file_name, manual);
+        if entry.fingerprint.len() >= self.options.min_moves.max(1) {
+            self.entries.push(entry);
+        }
+    }
+
+    pub fn add_source<S: stats::ManualSource + ?Sized>(
+        &mut self,
+        source: &S,
+    ) -> std::io::Result<()> {
+        for name in source.names()? {
+            match source.read(&name) {
+                Ok(manual) => self.add(&name, &manual),
+                Err(err) => self.failures.push((name, err.to_string())),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn add_dir(&mut self, dir: &Path) -> std::io::Result<()> {
+        self.add_source(dir)
+    }
+
+    pub fn groups(&self) -> Vec<DuplicateGroup> {
+        // 主线相同者归为一类
+        let mut class_indexes: HashMap<&Fingerprint, usize> = HashMap::new();
+        let mut classes: Vec<Vec<usize>> = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let class_index = *class_indexes.entry(&entry.fingerprint).or_insert_with(|| {
+                classes.push(Vec::new());
+                classes.len() - 1
+            });
+            classes[class_index].push(index);
+        }
+
+        // 各类归入以其主线为前段的最长主线所在的类
+        let fingerprint = |class_index: usize| &self.entries[classes[class_index][0]].fingerprint;
+        let mut last_keys: HashMap<(usize, (u64, u64)), Vec<usize>> = HashMap::new();
+        for class_index in 0..classes.len() {
+            let keys = &fingerprint(class_index).keys;
+            last_keys
+                .entry((keys.len(), keys[keys.len() - 1]))
+                .or_default()
+                .push(class_index);
+        }
+        let mut roots: Vec<usize> = (0..classes.len()).collect();
+        for class_index in 0..classes.len() {
+            let keys = &fingerprint(class_index).keys;
+            for len in 1..keys.len() {
+                let prefix_classes = last_keys.get(&(len, keys[len - 1])).into_iter().flatten();
+                for &prefix_index in prefix_classes {
+                    let root = roots[prefix_index];
+                    if fingerprint(prefix_index).is_prefix_of(fingerprint(class_index))
+                        && fingerprint(class_index).len() > fingerprint(root).len()
+                    {
+                        roots[prefix_index] = class_index;
+                    }
+                }
+            }
+        }
+
+        let mut groups = Vec::new();
+        for (root_index, root_class) in classes.iter().enumerate() {
+            if roots[root_index] != root_index {
+                continue;
+            }
+
+            let keep_index = root_class
+                .iter()
+                .copied()
+                .reduce(|best, index| {
+                    if self.entries[index].is_better_than(&self.entries[best]) {
+                        index
+                    } else {
+                        best
+                    }
+                })
+                .unwrap();
+            let keep = &self.entries[keep_index];
+            let mut duplicates = Vec::new();
+            for (class_index, class) in classes.iter().enumerate() {
+                if roots[class_index] != root_index {
+                    continue;
+                }
+
+                for &index in class {
+                    let entry = &self.entries[index];
+                    if index == keep_index {
+                        continue;
+                    }
+
+                    let relation = if class_index != root_index {
+                        Relation::Prefix
+                    } else if entry.remarks == keep.remarks && entry.move_count == keep.move_count {
+                        Relation::Exact
+                    } else {
+                        Relation::RemarksDiffer
+                    };
+                    duplicates.push((entry.file_name.clone(), relation));
+                }
+            }
+
+            if !duplicates.is_empty() {
+                duplicates.sort_by(|a, b| a.0.cmp(&b.0));
+                groups.push(DuplicateGroup {
+                    keep: keep.file_name.clone(),
+                    duplicates,
+                });
+            }
+        }
+        groups.sort_by(|a, b| a.keep.cmp(&b.keep));
+
+        groups
+    }
+}
+
+impl fmt::Display for DuplicateGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "保留: {}", self.keep)?;
+        for (file_name, relation) in &self.duplicates {
+            writeln!(f, "  重复({relation}): {file_name}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_dedup() {
+        let read = |manual_str: &str| {
+            manual::Manual::read_from(manual_str.as_bytes(), coord::RecordType::Txt).unwrap()
+        };
+        // 炮二平五 马８进７ 马二进三 车９平８
+        let main_str = "(1)\n(7,7)(7,4)(1)\n(0,7)(2,6)(1)\n(9,7)(7,6)(1)\n(0,8)(0,7)\n";
+        // 左右对称：炮八平五 马２进３ 马八进七 车１平２
+        let mirrored_str = "(1)\n(7,1)(7,4)(1)\n(0,1)(2,2)(1)\n(9,1)(7,2)(1)\n(0,0)(0,1)\n";
+        let manual = read(&format!("[Title: a]\n\n{main_str}"));
+        let mirrored = read(&format!("[Title: b]\n\n{mirrored_str}"));
+        assert_eq!(
+            Fingerprint::from_manual(&manual),
+            Fingerprint::from_manual(&mirrored)
+        );
+        assert_eq!(4, Fingerprint::from_manual(&manual).len());
+
+        let mut deduplicator = Deduplicator::new(DedupOptions { min_moves: 2 });
+        deduplicator.add("a.txt", &manual);
+        deduplicator.add("b.txt", &mirrored);
+        deduplicator.add(
+            "c.txt",
+            &read("[Title: c]\n\n(1)\n(7,7)(7,4){中炮}(1)\n(0,7)(2,6)(1)\n(9,7)(7,6)(1)\n(0,8)(0,7)\n"),
+        );
+        deduplicator.add(
+            "g.txt",
+            &read("[Title: g]\n\n(1)\n(7,7)(7,4){中炮}(1)\n(0,7)(2,6)(1)\n(9,7)(7,6)(1)\n(0,8)(0,7)\n"),
+        );
+        deduplicator.add(
+            "d.txt",
+            &read("[Title: d]\n\n(1)\n(7,7)(7,4)(1)\n(0,7)(2,6)(1)\n(9,7)(7,6)\n"),
+        );
+        // 另一局：炮二平五 马２进３
+        deduplicator.add(
+            "e.txt",
+            &read("[Title: e]\n\n(1)\n(7,7)(7,4)(1)\n(0,1)(2,2)\n"),
+        );
+        // 太短者不参与比较
+        deduplicator.add("f.txt", &read("[Title: f]\n\n(1)\n(7,7)(7,4)\n"));
+
+        let groups = deduplicator.groups();
+        assert_eq!(
+            vec![DuplicateGroup {
+                keep: String::from("c.txt"),
+                duplicates: vec![
+                    (String::from("a.txt"), Relation::RemarksDiffer),
+                    (String::from("b.txt"), Relation::RemarksDiffer),
+                    (String::from("d.txt"), Relation::Prefix),
+                    (String::from("g.txt"), Relation::Exact),
+                ],
+            }],
+            groups
+        );
+        assert_eq!(
+            "保留: c.txt\n  重复(仅备注不同): a.txt\n  重复(仅备注不同): b.txt\n  重复(主线为前段): d.txt\n  重复(完全相同): g.txt\n",
+            groups[0].to_string()
+        );
+
+        let mut deduplicator = Deduplicator::new(DedupOptions::default());
+        deduplicator.add("a.txt", &manual);
+        assert!(deduplicator.groups().is_empty());
+    }
+}
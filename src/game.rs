@@ -0,0 +1,496 @@
+#![allow(dead_code)]
+
+use crate::amove;
+use crate::board;
+use crate::coord::{self, Coord, CoordPair};
+use crate::info;
+use crate::manual;
+use crate::manual_move;
+use crate::piece::{self, Color};
+use crate::search;
+use std::collections::BTreeMap;
+use std::fmt;
+
+// 电脑接受和棋的最高分值(电脑方视角)
+const DRAWACCEPTSCORE: i32 = 50;
+
+// 对局结束的方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameOver {
+    Mate(Color),
+    Stalemate(Color),
+    PerpetualCheck(Color),
+    Repetition,
+    Resign(Color),
+    DrawAgreed,
+}
+
+impl GameOver {
+    // 胜方，和棋时为None
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            GameOver::Mate(color)
+            | GameOver::Stalemate(color)
+            | GameOver::PerpetualCheck(color)
+            | GameOver::Resign(color) => Some(*color),
+            GameOver::Repetition | GameOver::DrawAgreed => None,
+        }
+    }
+
+    pub fn result(&self) -> info::GameResult {
+        let win = match self.winner() {
+            Some(Color::Red) => info::Win::RedWin,
+            Some(Color::Black) => info::Win::BlackWin,
+            None => info::Win::Draw,
+        };
+        let termination = match self {
+            GameOver::Mate(_) => info::Termination::Mate,
+            GameOver::Stalemate(_) => info::Termination::Stalemate,
+            GameOver::PerpetualCheck(_) | GameOver::Repetition => info::Termination::Repetition,
+            GameOver::Resign(_) => info::Termination::Resign,
+            GameOver::DrawAgreed => info::Termination::Agreement,
+        };
+
+        info::GameResult::new(win, Some(termination))
+    }
+
+    // 对应InfoKey::Win的取值
+    pub fn win_string(&self) -> &'static str {
+        self.result().win.name()
+    }
+}
+
+impl fmt::Display for GameOver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameOver::Mate(_) => write!(f, "绝杀"),
+            GameOver::Stalemate(_) => write!(f, "困毙"),
+            GameOver::PerpetualCheck(_) => write!(f, "长将判负"),
+            GameOver::Repetition => write!(f, "重复局面判和"),
+            GameOver::Resign(Color::Red) => write!(f, "黑方认输"),
+            GameOver::Resign(Color::Black) => write!(f, "红方认输"),
+            GameOver::DrawAgreed => write!(f, "议和"),
+        }
+    }
+}
+
+// 人机对局：记录着法、局面及结果
+pub struct Game {
+    start_board: board::Board,
+    first_color: Color,
+    board: board::Board,
+    // 着法及是否将军
+    moves: Vec<(CoordPair, bool)>,
+    // 开局及每着之后的局面
+    key_locks: Vec<(u64, u64)>,
+    result: Option<GameOver>,
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Self::from_fen(board::FEN, Color::Red)
+    }
+
+    pub fn from_fen(fen: &str, first_color: Color) -> Self {
+        let start_board = board::Board::from(fen);
+        Game {
+            start_board,
+            first_color,
+            board: start_board,
+            moves: Vec::new(),
+            key_locks: vec![start_board.bit_board().get_key_lock(first_color)],
+            result: None,
+        }
+    }
+
+    pub fn board(&self) -> &board::Board {
+        &self.board
+    }
+
+    pub fn to_move(&self) -> Color {
+        if self.moves.len().is_multiple_of(2) {
+            self.first_color
+        } else {
+            piece::other_color(self.first_color)
+        }
+    }
+
+    pub fn result(&self) -> Option<GameOver> {
+        self.result
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn legal_moves(&self) -> Vec<CoordPair> {
+        self.board
+            .bit_board()
+            .get_legal_moves(self.to_move())
+            .into_iter()
+            .map(|(from_index, to_index)| {
+                CoordPair::from(
+                    Coord::from_index(from_index).unwrap(),
+                    Coord::from_index(to_index).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    pub fn zhstr(&self, coordpair: &CoordPair) -> String {
+        self.board.get_zhstr_from_coordpair(coordpair)
+    }
+
+    // 解析中文纵线、ICCS或WXF着法，限于当前的合法着法
+    pub fn parse_move(&self, move_str: &str) -> Option<CoordPair> {
+        let move_str: String = move_str.split_whitespace().collect();
+        let legal_moves = self.legal_moves();
+        let zh_coordpair = board_zhstr(&move_str, self.to_move())
+            .and_then(|zhstr| self.board.try_get_coordpair_from_zhstr(&zhstr))
+            .filter(|coordpair| legal_moves.contains(coordpair));
+        if zh_coordpair.is_some() {
+            return zh_coordpair;
+        }
+
+        let ascii_input = move_str.to_uppercase().replace('-', "");
+        let wxf_input = normalize_wxf(&move_str);
+        legal_moves.into_iter().find(|coordpair| {
+            coordpair.to_string(coord::RecordType::PgnIccs) == ascii_input
+                || zh_to_wxf(&self.zhstr(coordpair)).is_some_and(|wxf| wxf == wxf_input)
+        })
+    }
+
+    // 走一着并判定胜负，非法着法或对局已结束时返回false
+    pub fn play(&mut self, coordpair: &CoordPair) -> bool {
+        if self.result.is_some() || !self.legal_moves().contains(coordpair) {
+            return false;
+        }
+
+        let color = self.to_move();
+        let other_color = piece::other_color(color);
+        let amove = amove::Move::root().append(*coordpair, String::new());
+        self.board.do_move(&amove);
+        let mut bit_board = self.board.bit_board();
+        self.moves
+            .push((*coordpair, bit_board.is_checked(other_color)));
+        self.key_locks.push(bit_board.get_key_lock(other_color));
+
+        if bit_board.get_legal_moves(other_color).is_empty() {
+            self.result = Some(if bit_board.is_checked(other_color) {
+                GameOver::Mate(color)
+            } else {
+                GameOver::Stalemate(color)
+            });
+        } else {
+            self.result = self.get_repetition_result();
+        }
+
+        true
+    }
+
+    // 局面第三次出现时判定：一方着着将军判负，否则判和
+    fn get_repetition_result(&self) -> Option<GameOver> {
+        let key_lock = self.key_locks.last()?;
+        let indexs: Vec<usize> = self
+            .key_locks
+            .iter()
+            .enumerate()
+            .filter(|(_, other_key_lock)| *other_key_lock == key_lock)
+            .map(|(index, _)| index)
+            .collect();
+        if indexs.len() < 3 {
+            return None;
+        }
+
+        let start = indexs[indexs.len() - 2];
+        let mut all_checks = [true; piece::COLORCOUNT];
+        for index in start..self.moves.len() {
+            let color = if index.is_multiple_of(2) {
+                self.first_color
+            } else {
+                piece::other_color(self.first_color)
+            };
+            all_checks[color as usize] &= self.moves[index].1;
+        }
+
+        Some(match all_checks {
+            [true, false] => GameOver::PerpetualCheck(Color::Black),
+            [false, true] => GameOver::PerpetualCheck(Color::Red),
+            _ => GameOver::Repetition,
+        })
+    }
+
+    // 悔棋count着，返回实际悔棋的着数
+    pub fn undo(&mut self, count: usize) -> usize {
+        let count = count.min(self.moves.len());
+        self.moves.truncate(self.moves.len() - count);
+        self.key_locks.truncate(self.moves.len() + 1);
+        self.board = self.start_board;
+        for (coordpair, _) in &self.moves {
+            self.board
+                .do_move(&amove::Move::root().append(*coordpair, String::new()));
+        }
+        self.result = None;
+
+        count
+    }
+
+    pub fn hint(&self, depth: usize) -> Option<CoordPair> {
+        if self.result.is_some() {
+            return None;
+        }
+
+        search::search(&self.board, self.to_move(), depth)
+            .pv
+            .first()
+            .copied()
+    }
+
+    // 电脑走一着，返回所走着法的中文表示
+    pub fn engine_move(&mut self, depth: usize) -> Option<String> {
+        let coordpair = self.hint(depth)?;
+        let zhstr = self.zhstr(&coordpair);
+        self.play(&coordpair).then_some(zhstr)
+    }
+
+    pub fn resign(&mut self) {
+        if self.result.is_none() {
+            self.result = Some(GameOver::Resign(piece::other_color(self.to_move())));
+        }
+    }
+
+    // 向engine_color方提和，电脑方局势不占优时接受
+    pub fn offer_draw(&mut self, engine_color: Color, depth: usize) -> bool {
+        if self.result.is_some() {
+            return false;
+        }
+
+        let score = search::search(&self.board, self.to_move(), depth).score;
+        let engine_score = if self.to_move() == engine_color {
+            score
+        } else {
+            -score
+        };
+        let accept = engine_score <= DRAWACCEPTSCORE;
+        if accept {
+            self.result = Some(GameOver::DrawAgreed);
+        }
+
+        accept
+    }
+
+    pub fn to_manual(&self) -> manual::Manual {
+        let root_move = amove::Move::root();
+        let mut before_move = root_move.clone();
+        for (coordpair, _) in &self.moves {
+            before_move = before_move.append(*coordpair, String::new());
+        }
+
+        let fen = self.start_board.get_fen();
+        let side = match self.first_color {
+            Color::Red => "r",
+            Color::Black => "b",
+        };
+        let mut info = BTreeMap::new();
+        info.insert(manual::InfoKey::FEN.name(), format!("{fen} {side} - - 0 1"));
+        let mut manual =
+            manual::Manual::from_info_move(info, manual_move::ManualMove::from(&fen, root_move));
+        if let Some(result) = self.result {
+            manual.set_result(result.result());
+            if !before_move.is_root() {
+                before_move.merge_remark(result.to_string());
+            }
+        }
+
+        manual
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 统一数字及棋子字形，便于比较中文着法
+fn normalize_zh(zhstr: &str) -> String {
+    zhstr
+        .chars()
+        .map(|ch| match ch {
+            '一' | '１' => '1',
+            '二' | '２' => '2',
+            '三' | '３' => '3',
+            '四' | '４' => '4',
+            '五' | '５' => '5',
+            '六' | '６' => '6',
+            '七' | '７' => '7',
+            '八' | '８' => '8',
+            '九' | '９' => '9',
+            '馬' => '马',
+            '車' => '车',
+            '砲' => '炮',
+            '將' => '将',
+            '帥' => '帅',
+            '後' => '后',
+            '進' => '进',
+            _ => ch,
+        })
+        .collect()
+}
+
+// 输入的中文纵线着法转为棋盘所用的字符：纵线及步数按走子方取中文或全角数字，
+// 多兵的序号取中文数字
+fn board_zhstr(zhstr: &str, color: Color) -> Option<String> {
+    let chs: Vec<char> = normalize_zh(zhstr).chars().collect();
+    if chs.len() != 4 {
+        return None;
+    }
+
+    let zhstr = chs
+        .iter()
+        .enumerate()
+        .map(|(index, &ch)| match ch.to_digit(10) {
+            Some(num @ 1..=9) => {
+                let num_color = if index == 0 { Color::Red } else { color };
+                board::Board::get_col_ch(num_color, num as usize - 1)
+            }
+            _ => ch,
+        })
+        .collect();
+    Some(zhstr)
+}
+
+fn normalize_wxf(wxf: &str) -> String {
+    wxf.to_uppercase()
+        .chars()
+        .map(|ch| match ch {
+            'B' => 'E',
+            'N' => 'H',
+            '=' => '.',
+            _ => ch,
+        })
+        .collect()
+}
+
+// 中文纵线着法转为WXF，如"炮二平五"为"C2.5"、"前车进一"为"R++1"；多兵以数字区分的不转换
+fn zh_to_wxf(zhstr: &str) -> Option<String> {
+    let kind_letter = |ch: char| {
+        let kind = piece::kind_from_name(ch);
+        (kind != piece::Kind::NoKind).then(|| "KAEHRCP".as_bytes()[kind as usize] as char)
+    };
+    let move_letter = |ch: char| match ch {
+        '进' => Some('+'),
+        '退' => Some('-'),
+        '平' => Some('.'),
+        _ => None,
+    };
+
+    let chs: Vec<char> = normalize_zh(zhstr).chars().collect();
+    if chs.len() != 4 {
+        return None;
+    }
+    let (letter, col_ch) = match kind_letter(chs[0]) {
+        Some(letter) => (letter, chs[1]),
+        None => {
+            let pos_ch = match chs[0] {
+                '前' => '+',
+                '后' => '-',
+                _ => return None,
+            };
+            (kind_letter(chs[1])?, pos_ch)
+        }
+    };
+
+    Some(format!(
+        "{letter}{col_ch}{}{}",
+        move_letter(chs[2])?,
+        chs[3]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game() {
+        let mut game = Game::new();
+        let coordpair = game.parse_move("炮二平五").unwrap();
+        assert_eq!(Some(coordpair), game.parse_move("h7e7"));
+        assert_eq!(Some(coordpair), game.parse_move("C2.5"));
+        assert_eq!(Some(coordpair), game.parse_move("c2=5"));
+        assert_eq!(None, game.parse_move("炮二进五"));
+        assert_eq!(None, game.parse_move("xyz"));
+        assert!(game.play(&coordpair));
+        assert_eq!(Color::Black, game.to_move());
+        assert_eq!(game.parse_move("马8进7"), game.parse_move("馬８進７"));
+        assert_eq!(game.parse_move("马８进７"), game.parse_move("H8+7"));
+        assert_eq!(game.parse_move("马８进７"), game.parse_move("马八进七"));
+        assert_eq!(None, game.parse_move("马５进７"));
+
+        // 前后区分同列棋子
+        let rook_game = Game::from_fen("3k5/9/9/9/9/9/9/R8/R8/4K4", Color::Red);
+        let rook_move = rook_game.parse_move("前车进1").unwrap();
+        assert_eq!("A7A6", rook_move.to_string(coord::RecordType::PgnIccs));
+        assert_eq!(Some(rook_move), rook_game.parse_move("前車進一"));
+        assert_eq!(Some(rook_move), rook_game.parse_move("R++1"));
+        assert_eq!(None, rook_game.parse_move("中车进一"));
+        assert_eq!(Some("R++1".to_string()), zh_to_wxf("前车进一"));
+
+        assert!(game.engine_move(1).is_some());
+        assert_eq!(2, game.move_count());
+        assert!(game.hint(1).is_some());
+        assert_eq!(2, game.undo(3));
+        assert_eq!(board::Board::new(), *game.board());
+
+        // 绝杀
+        let mut game = Game::from_fen("3k5/9/9/9/9/6R2/9/9/9/4K4", Color::Red);
+        assert!(game.play(&game.parse_move("车三平六").unwrap()));
+        assert_eq!(Some(GameOver::Mate(Color::Red)), game.result());
+        assert!(!game.play(
+            &game
+                .legal_moves()
+                .first()
+                .copied()
+                .unwrap_or(CoordPair::new())
+        ));
+        let manual = game.to_manual();
+        assert_eq!(
+            "[FEN: 3k5/9/9/9/9/6R2/9/9/9/4K4 r - - 0 1]\n[Termination: 绝杀]\n[Win: 红胜]\n\n(1)\n(5,6)(5,3){绝杀}\n",
+            manual.to_string(coord::RecordType::Txt)
+        );
+        // PGN格式以标准标签记录结果
+        let pgn_string = manual.to_string(coord::RecordType::PgnIccs);
+        assert!(pgn_string.starts_with(
+            "[FEN: 3k5/9/9/9/9/6R2/9/9/9/4K4 r - - 0 1]\n[Result: 1-0]\n[Termination: checkmate]\n"
+        ));
+        let pgn_manual =
+            manual::Manual::read_from(pgn_string.as_bytes(), coord::RecordType::PgnIccs).unwrap();
+        assert_eq!(manual.result(), pgn_manual.result());
+        assert_eq!(
+            Some(info::GameResult::new(
+                info::Win::RedWin,
+                Some(info::Termination::Mate)
+            )),
+            pgn_manual.result()
+        );
+        assert_eq!(1, game.undo(1));
+        assert_eq!(None, game.result());
+
+        // 红车长将
+        let mut game = Game::from_fen("4k4/9/9/9/9/9/9/9/4R4/3K5", Color::Black);
+        for move_str in ["将5平6", "车五平四", "将6平5", "车四平五"].repeat(2) {
+            assert!(game.play(&game.parse_move(move_str).unwrap()));
+        }
+        assert_eq!(Some(GameOver::PerpetualCheck(Color::Black)), game.result());
+        assert_eq!("黑胜", game.result().unwrap().win_string());
+
+        let mut game = Game::new();
+        game.resign();
+        assert_eq!(Some(GameOver::Resign(Color::Black)), game.result());
+        assert_eq!("红方认输", game.result().unwrap().to_string());
+        let mut game = Game::new();
+        assert!(game.offer_draw(Color::Black, 1));
+        assert_eq!("议和", game.result().unwrap().to_string());
+    }
+}
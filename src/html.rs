@@ -0,0 +1,208 @@
+#![allow(dead_code)]
+
+use crate::amove;
+use crate::board;
+use crate::manual;
+use crate::piece;
+use crate::svg;
+use std::rc::Rc;
+
+const STYLE: &str =
+    "body { font-family: serif; max-width: 48em; margin: 2em auto; line-height: 1.8; }
+table.info td { padding: 0 1em 0 0; }
+.move { white-space: nowrap; }
+.remark { color: #555; }
+.variation { margin-left: 2em; padding-left: 0.8em; border-left: 2px solid #ccc; }
+.diagram { margin: 0.5em 0; }
+";
+
+fn escape(text: &str) -> String {
+    let mut result = String::new();
+    for ch in text.chars() {
+        match ch {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\n' => result.push_str("<br>"),
+            _ => result.push(ch),
+        }
+    }
+
+    result
+}
+
+struct HtmlWriter {
+    board: board::Board,
+    options: svg::SvgOptions,
+    // 首着为黑方时序号错后一位
+    ply_offset: usize,
+    result: String,
+}
+
+impl HtmlWriter {
+    fn push_remark(&mut self, remark: &str, last_move: Option<&Rc<amove::Move>>) {
+        self.result.push_str(&format!(
+            "<span class=\"remark\">{}</span>\n",
+            escape(remark)
+        ));
+
+        self.options.last_move = last_move.map(|amove| amove.coordpair);
+        self.result.push_str(&format!(
+            "<div class=\"diagram\">\n{}</div>\n",
+            svg::to_svg(&self.board, &self.options)
+        ));
+    }
+
+    // 输出自amove起的一路着法，途中的变着以嵌套块输出
+    fn push_line(&mut self, amove: &Rc<amove::Move>, ply: usize) {
+        let mut amove = amove.clone();
+        let mut ply = ply;
+        let mut need_number = true;
+        let mut done_moves = Vec::new();
+        loop {
+            let color = self
+                .board
+                .bit_board()
+                .get_color(amove.coordpair.from_coord.index())
+                .unwrap_or(piece::Color::Red);
+            let number = (ply + self.ply_offset) / 2 + 1;
+            let zhstr = self.board.get_zhstr_from_coordpair(&amove.coordpair);
+            let number_str = match color {
+                piece::Color::Red => format!("{number}. "),
+                piece::Color::Black if need_number => format!("{number}. … "),
+                piece::Color::Black => String::new(),
+            };
+            self.result.push_str(&format!(
+                "<span class=\"move\">{number_str}{zhstr}</span>\n"
+            ));
+
+            let before_move = amove.before().unwrap();
+            self.board.do_move(&amove);
+            done_moves.push(amove.clone());
+            need_number = false;
+
+            let remark = amove.remark();
+            if !remark.is_empty() {
+                self.push_remark(&remark, Some(&amove));
+                need_number = true;
+            }
+
+            // 同一局面下的其他着法
+            let other_moves = before_move.after();
+            if other_moves.len() > 1 && Rc::ptr_eq(&other_moves[0], &amove) {
+                self.board.undo_move(&amove);
+                for other_move in &other_moves[1..] {
+                    self.result.push_str("<div class=\"variation\">\n");
+                    self.push_line(other_move, ply);
+                    self.result.push_str("</div>\n");
+                }
+                self.board.do_move(&amove);
+                need_number = true;
+            }
+
+            match amove.after().first() {
+                Some(after_move) => amove = after_move.clone(),
+                None => break,
+            }
+            ply += 1;
+        }
+
+        for amove in done_moves.iter().rev() {
+            self.board.undo_move(amove);
+        }
+    }
+}
+
+// 单一自含文件：信息表头、带序号的中文着法主线、嵌套缩进的变着、
+// 行内备注，每条备注处附局面图
+pub fn to_html(manual: &manual::Manual) -> String {
+    let info = manual.info();
+    let title = manual
+        .get_info(manual::InfoKey::Title)
+        .unwrap_or_default()
+        .to_string();
+    let board = *manual.manual_move().board();
+    let root_move = manual.manual_move().root_move();
+
+    let mut result = format!(
+        "<!DOCTYPE html>\n<html lang=\"zh\">\n<head>\n<meta charset=\"utf-8\">\n\
+<title>{0}</title>\n<style>\n{STYLE}</style>\n</head>\n<body>\n<h1>{0}</h1>\n\
+<table class=\"info\">\n",
+        escape(&title)
+    );
+    for (key, value) in info {
+        if !value.is_empty() {
+            result.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td></tr>\n",
+                escape(key),
+                escape(value)
+            ));
+        }
+    }
+    result.push_str("</table>\n<div class=\"manual\">\n");
+
+    let mut options = svg::SvgOptions::new();
+    options.bottom_color = board.bottom_color();
+    let first_color = root_move
+        .after()
+        .first()
+        .and_then(|amove| {
+            board
+                .bit_board()
+                .get_color(amove.coordpair.from_coord.index())
+        })
+        .unwrap_or(piece::Color::Red);
+    let mut writer = HtmlWriter {
+        board,
+        options,
+        ply_offset: if first_color == piece::Color::Red {
+            0
+        } else {
+            1
+        },
+        result,
+    };
+
+    let remark = root_move.remark();
+    if !remark.is_empty() {
+        writer.push_remark(&remark, None);
+    }
+    if let Some(amove) = root_move.after().first() {
+        writer.push_line(amove, 0);
+    }
+
+    writer.result.push_str("</div>\n</body>\n</html>\n");
+    writer.result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::manual_move;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_html() {
+        let mut info = BTreeMap::new();
+        info.insert(String::from("Title"), String::from("中炮<测试>"));
+        let manual_move = manual_move::ManualMove::from_string(
+            board::FEN,
+            "{开局}(1)\n(7,7)(7,4)(2)\n(0,7)(2,6){屏风马}(1)\n(0,1)(2,2)\n(9,7)(7,6)\n",
+            coord::RecordType::Txt,
+        )
+        .unwrap();
+        let html = to_html(&manual::Manual::from_info_move(info, manual_move));
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>中炮&lt;测试&gt;</title>"));
+        assert_eq!(2, html.matches("<svg ").count());
+        assert_eq!(1, html.matches("<div class=\"variation\">").count());
+        let main_pos = html.find("1. 炮二平五").unwrap();
+        let remark_pos = html.find("屏风马").unwrap();
+        let variation_pos = html.find("1. … 马２进３").unwrap();
+        let next_pos = html.find("2. 马二进三").unwrap();
+        assert!(main_pos < remark_pos && remark_pos < variation_pos && variation_pos < next_pos);
+    }
+}
@@ -0,0 +1,339 @@
+#![allow(dead_code)]
+
+use crate::piece;
+use std::fmt;
+
+// 对局结果(InfoKey::Win)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Win {
+    Unknown,
+    RedWin,
+    BlackWin,
+    Draw,
+}
+
+impl Win {
+    // 依XQF文件头中的序号
+    pub fn from_index(index: u8) -> Self {
+        match index {
+            1 => Win::RedWin,
+            2 => Win::BlackWin,
+            3 => Win::Draw,
+            _ => Win::Unknown,
+        }
+    }
+
+    pub fn index(&self) -> u8 {
+        *self as u8
+    }
+
+    // 亦接受PGN的"1-0"、"0-1"、"1/2-1/2"、"*"及常见的中文写法
+    pub fn from_string(win_str: &str) -> Option<Self> {
+        match win_str.trim() {
+            "未知" | "*" => Some(Win::Unknown),
+            "红胜" | "红方胜" | "红先胜" | "红方获胜" | "黑负" | "1-0" => {
+                Some(Win::RedWin)
+            }
+            "黑胜" | "黑方胜" | "红先负" | "黑方获胜" | "红负" | "0-1" => {
+                Some(Win::BlackWin)
+            }
+            "和棋" | "和" | "和局" | "红先和" | "1/2-1/2" | "½-½" => Some(Win::Draw),
+            _ => None,
+        }
+    }
+
+    pub fn pgn_result(&self) -> &'static str {
+        match self {
+            Win::Unknown => "*",
+            Win::RedWin => "1-0",
+            Win::BlackWin => "0-1",
+            Win::Draw => "1/2-1/2",
+        }
+    }
+
+    pub fn winner(&self) -> Option<piece::Color> {
+        match self {
+            Win::RedWin => Some(piece::Color::Red),
+            Win::BlackWin => Some(piece::Color::Black),
+            Win::Unknown | Win::Draw => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Win::Unknown => "未知",
+            Win::RedWin => "红胜",
+            Win::BlackWin => "黑胜",
+            Win::Draw => "和棋",
+        }
+    }
+}
+
+impl fmt::Display for Win {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// 终局原因(InfoKey::Termination)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Termination {
+    Mate,
+    Stalemate,
+    Resign,
+    Time,
+    Repetition,
+    Agreement,
+}
+
+impl Termination {
+    pub fn from_string(termination_str: &str) -> Option<Self> {
+        match termination_str.trim() {
+            "绝杀" | "将死" | "杀" | "checkmate" => Some(Termination::Mate),
+            "困毙" | "困死" | "stalemate" => Some(Termination::Stalemate),
+            "认输" | "投子认负" | "认负" | "resignation" => Some(Termination::Resign),
+            "超时" | "超时判负" | "time forfeit" => Some(Termination::Time),
+            "重复局面" | "长将判负" | "长打判负" | "重复局面判和" | "repetition" => {
+                Some(Termination::Repetition)
+            }
+            "议和" | "协议和棋" | "agreement" => Some(Termination::Agreement),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Termination::Mate => "绝杀",
+            Termination::Stalemate => "困毙",
+            Termination::Resign => "认输",
+            Termination::Time => "超时",
+            Termination::Repetition => "重复局面",
+            Termination::Agreement => "议和",
+        }
+    }
+
+    // PGN的Termination标签
+    pub fn pgn_name(&self) -> &'static str {
+        match self {
+            Termination::Mate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::Resign => "resignation",
+            Termination::Time => "time forfeit",
+            Termination::Repetition => "repetition",
+            Termination::Agreement => "agreement",
+        }
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// 对局结果及终局原因
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GameResult {
+    pub win: Win,
+    pub termination: Option<Termination>,
+}
+
+impl GameResult {
+    pub fn new(win: Win, termination: Option<Termination>) -> Self {
+        GameResult { win, termination }
+    }
+
+    // XQF文件头只记录胜负
+    pub fn from_xqf(index: u8) -> Self {
+        GameResult::new(Win::from_index(index), None)
+    }
+
+    pub fn xqf_index(&self) -> u8 {
+        self.win.index()
+    }
+
+    // 形如"红胜"、"红胜(认输)"、"1-0 resignation"
+    pub fn from_string(result_str: &str) -> Option<Self> {
+        let result_str = result_str.trim().trim_end_matches([')', '）']);
+        let (win_str, termination_str) = match result_str.split_once(['(', '（', ' ']) {
+            Some((win_str, termination_str)) => (win_str, Some(termination_str)),
+            None => (result_str, None),
+        };
+        let termination = match termination_str {
+            Some(termination_str) => Some(Termination::from_string(termination_str)?),
+            None => None,
+        };
+
+        Some(GameResult::new(Win::from_string(win_str)?, termination))
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.win)?;
+        if let Some(termination) = self.termination {
+            write!(f, "({termination})")?;
+        }
+
+        Ok(())
+    }
+}
+
+// 棋局类型(InfoKey::Atype)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameType {
+    Full,
+    Opening,
+    Middle,
+    Ending,
+}
+
+impl GameType {
+    pub fn from_index(index: u8) -> Self {
+        match index {
+            1 => GameType::Opening,
+            2 => GameType::Middle,
+            3 => GameType::Ending,
+            _ => GameType::Full,
+        }
+    }
+
+    pub fn from_string(type_str: &str) -> Option<Self> {
+        match type_str.trim() {
+            "全局" => Some(GameType::Full),
+            "开局" => Some(GameType::Opening),
+            "中局" => Some(GameType::Middle),
+            "残局" => Some(GameType::Ending),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameType::Full => "全局",
+            GameType::Opening => "开局",
+            GameType::Middle => "中局",
+            GameType::Ending => "残局",
+        }
+    }
+}
+
+impl fmt::Display for GameType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// 对局日期，月、日可缺省
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u16,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+}
+
+impl Date {
+    pub fn new(year: u16, month: Option<u8>, day: Option<u8>) -> Option<Self> {
+        let days = match month {
+            Some(1 | 3 | 5 | 7 | 8 | 10 | 12) => 31,
+            Some(4 | 6 | 9 | 11) => 30,
+            Some(2)
+                if year.is_multiple_of(4)
+                    && (!year.is_multiple_of(100) || year.is_multiple_of(400)) =>
+            {
+                29
+            }
+            Some(2) => 28,
+            Some(_) => return None,
+            None if day.is_some() => return None,
+            None => 0,
+        };
+        if day.is_some_and(|day| day == 0 || day > days) {
+            return None;
+        }
+
+        Some(Date { year, month, day })
+    }
+
+    // 自文本开头解析"1993.4.27"、"1993年4月27日"、"1993-04"等形式的日期，返回日期及其后的文本
+    pub fn parse_prefix(date_str: &str) -> Option<(Self, &str)> {
+        let date_re = regex::Regex::new(
+            r"^\s*(\d{4})\s*[年./-]?\s*(?:(\d{1,2})\s*[月./-]?\s*(?:(\d{1,2})\s*日?)?)?",
+        )
+        .unwrap();
+        let caps = date_re.captures(date_str)?;
+        let number = |index| {
+            caps.at(index)
+                .and_then(|num_str: &str| num_str.parse().ok())
+        };
+        let date = Date::new(caps.at(1)?.parse().ok()?, number(2), number(3))?;
+
+        let (_, end) = caps.pos(0)?;
+        Some((date, &date_str[end..]))
+    }
+
+    pub fn from_string(date_str: &str) -> Option<Self> {
+        Date::parse_prefix(date_str)
+            .filter(|(_, rest)| rest.trim().is_empty())
+            .map(|(date, _)| date)
+    }
+}
+
+// ISO格式，依精度为"1993-04-27"、"1993-04"或"1993"
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(f, "-{month:02}")?;
+        }
+        if let Some(day) = self.day {
+            write!(f, "-{day:02}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info() {
+        assert_eq!(Some(Win::Draw), Win::from_string("1/2-1/2"));
+        assert_eq!(Win::BlackWin, Win::from_index(2));
+        assert_eq!("红胜", Win::RedWin.to_string());
+        assert_eq!(None, Win::from_string("红方"));
+        assert_eq!(Some(Win::RedWin), Win::from_string("红先胜"));
+
+        let result = GameResult::new(Win::BlackWin, Some(Termination::Resign));
+        assert_eq!("黑胜(认输)", result.to_string());
+        assert_eq!(Some(result), GameResult::from_string("黑胜(认输)"));
+        assert_eq!(Some(result), GameResult::from_string("0-1 resignation"));
+        assert_eq!(
+            Some(GameResult::new(Win::Draw, None)),
+            GameResult::from_string("和棋")
+        );
+        assert_eq!(None, GameResult::from_string("红胜(不明)"));
+        assert_eq!(
+            ("0-1", "resignation"),
+            (result.win.pgn_result(), Termination::Resign.pgn_name())
+        );
+        assert_eq!(GameResult::new(Win::Draw, None), GameResult::from_xqf(3));
+        assert_eq!(2, result.xqf_index());
+        assert_eq!(Some(GameType::Ending), GameType::from_string("残局"));
+        assert_eq!(GameType::Full, GameType::from_index(9));
+
+        let date = Date::from_string("1993年4月27日").unwrap();
+        assert_eq!("1993-04-27", date.to_string());
+        assert_eq!("1985-06", Date::from_string("1985/6").unwrap().to_string());
+        assert_eq!(None, Date::from_string("1993.2.29"));
+        assert!(Date::from_string("2000.2.29").is_some());
+        assert_eq!(None, Date::from_string("1993.4.27 下午"));
+        assert_eq!(
+            Some((date, "于南京")),
+            Date::parse_prefix("1993.4.27于南京")
+        );
+        assert!(Date::from_string("1993") < Date::from_string("1993.1"));
+    }
+}
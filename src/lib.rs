@@ -1,13 +1,33 @@
 mod amove;
+pub mod annotator;
 mod bit_board;
 mod bit_constant;
 mod evaluation;
-mod board;
+pub mod game;
+pub mod html;
+pub mod info;
+pub mod board;
+pub mod board_text;
 pub mod coord;
+pub mod dedup;
 pub mod manual;
+pub mod manual_diff;
+pub mod metadata;
 mod manual_move;
-mod piece;
+pub mod navigator;
+pub mod piece;
+pub mod png;
 mod common;
+pub mod search;
+pub mod stats;
+pub mod solver;
+pub mod svg;
+pub mod tablebase;
+pub mod time_control;
+pub mod validation;
+pub mod xqf;
+
+pub use common::{Error, Location, Result};
 
 // pub use crate::piece;
 
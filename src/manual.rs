@@ -1,21 +1,25 @@
 #![allow(dead_code)]
 
 use crate::coord::{self, COLCOUNT, ROWCOUNT, SEATCOUNT};
-use encoding::all::GBK;
-use encoding::{DecoderTrap, Encoding};
 // use regex::Error;
 // use std::fs::File;
 // use std::io::prelude::*;
 // use std::io::BufReader;
 // use serde::de::value;
+use crate::amove;
 use crate::board;
 use crate::common;
+use crate::info;
 use crate::manual_move;
-use std::borrow::Borrow;
+use crate::piece;
+use crate::time_control;
+use crate::xqf;
+use serde_derive::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::io::{Read, Write};
 // use std::rc::Rc;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum InfoKey {
     Source,
     Title,
@@ -35,6 +39,243 @@ pub enum InfoKey {
     Version,
     FEN,
     MoveString,
+    RedTime,
+    BlackTime,
+    TimeControl,
+    TitleB,
+    ProductId,
+    PlayStepNo,
+    PlayNodes,
+    TreePos,
+    XqfReserved,
+    RedTeam,
+    BlackTeam,
+    Termination,
+}
+
+impl InfoKey {
+    pub const ALL: [InfoKey; 30] = [
+        InfoKey::Source,
+        InfoKey::Title,
+        InfoKey::Game,
+        InfoKey::Date,
+        InfoKey::Site,
+        InfoKey::Black,
+        InfoKey::RowCols,
+        InfoKey::Red,
+        InfoKey::EccoSn,
+        InfoKey::EccoName,
+        InfoKey::Win,
+        InfoKey::Opening,
+        InfoKey::Writer,
+        InfoKey::Author,
+        InfoKey::Atype,
+        InfoKey::Version,
+        InfoKey::FEN,
+        InfoKey::MoveString,
+        InfoKey::RedTime,
+        InfoKey::BlackTime,
+        InfoKey::TimeControl,
+        InfoKey::TitleB,
+        InfoKey::ProductId,
+        InfoKey::PlayStepNo,
+        InfoKey::PlayNodes,
+        InfoKey::TreePos,
+        InfoKey::XqfReserved,
+        InfoKey::RedTeam,
+        InfoKey::BlackTeam,
+        InfoKey::Termination,
+    ];
+
+    // 信息表中的键名
+    pub fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        InfoKey::ALL.into_iter().find(|key| key.name() == name)
+    }
+
+    // 校验取值，返回规范化后的值，空值表示未知
+    fn validate(&self, value: &str) -> common::Result<String> {
+        let invalid = || common::Error::Info(format!("{}: {value}", self.name()));
+        if value.contains(['\n', '\r']) {
+            return Err(invalid());
+        }
+
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(String::new());
+        }
+        match self {
+            InfoKey::Win => info::Win::from_string(value)
+                .map(|win| win.to_string())
+                .ok_or_else(invalid),
+            InfoKey::Date => info::Date::from_string(value)
+                .map(|date| date.to_string())
+                .ok_or_else(invalid),
+            InfoKey::Termination => info::Termination::from_string(value)
+                .map(|termination| termination.to_string())
+                .ok_or_else(invalid),
+            InfoKey::Atype => info::GameType::from_string(value)
+                .map(|game_type| game_type.to_string())
+                .ok_or_else(invalid),
+            InfoKey::TimeControl => time_control::TimeControl::from_string(value)
+                .map(|time_control| time_control.to_string())
+                .map_err(|_| invalid()),
+            InfoKey::Version
+            | InfoKey::ProductId
+            | InfoKey::PlayStepNo
+            | InfoKey::PlayNodes
+            | InfoKey::TreePos => value
+                .parse::<u32>()
+                .map(|num| num.to_string())
+                .map_err(|_| invalid()),
+            _ => Ok(value.to_string()),
+        }
+    }
+}
+
+// JSON格式棋谱：
+// {
+//   "info": { "Title": "...", "FEN": "... r - - 0 1", ... },
+//   "fen": "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR",
+//   "root": {
+//     "remark": "开局备注",
+//     "children": [
+//       { "coordpair": [7, 7, 7, 4], "iccs": "H7E7", "zh": "炮二平五",
+//         "remark": "着法备注",
+//         "annotation": { "nags": [1], "score": 30, "depth": 8, "clock": 600,
+//                         "elapsed": 20, "squares": ["GE7"], "arrows": ["RG9E9"] },
+//         "children": [ ... ] }
+//     ]
+//   }
+// }
+// coordpair为[起点行, 起点列, 终点行, 终点列]；首个子节点为主着，其余为变着；
+// 空的remark/annotation/children可省略，读入时以coordpair为准，缺失时取iccs。
+#[derive(Debug, Serialize, Deserialize)]
+struct ManualJson {
+    #[serde(default)]
+    info: BTreeMap<String, String>,
+    fen: String,
+    #[serde(default)]
+    root: amove::MoveJson,
+}
+
+// DhtmlXQ标签与信息项的对应
+const DHTMLXQINFOKEYS: [(&str, InfoKey); 11] = [
+    ("title", InfoKey::Title),
+    ("event", InfoKey::Game),
+    ("date", InfoKey::Date),
+    ("place", InfoKey::Site),
+    ("red", InfoKey::Red),
+    ("black", InfoKey::Black),
+    ("result", InfoKey::Win),
+    ("open", InfoKey::Opening),
+    ("author", InfoKey::Author),
+    ("redteam", InfoKey::RedTeam),
+    ("blackteam", InfoKey::BlackTeam),
+];
+
+// PGN格式以"Result: 1-0"、"Termination: resignation"记录对局结果
+const PGNRESULTKEY: &str = "Result";
+
+fn info_to_pgn(info: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut pgn_info = BTreeMap::new();
+    for (key, value) in info {
+        let win = info::Win::from_string(value).filter(|_| *key == InfoKey::Win.name());
+        let termination =
+            info::Termination::from_string(value).filter(|_| *key == InfoKey::Termination.name());
+        match (win, termination) {
+            (Some(win), _) => {
+                pgn_info.insert(PGNRESULTKEY.to_string(), win.pgn_result().to_string())
+            }
+            (_, Some(termination)) => {
+                pgn_info.insert(key.clone(), termination.pgn_name().to_string())
+            }
+            _ => pgn_info.insert(key.clone(), value.clone()),
+        };
+    }
+
+    pgn_info
+}
+
+fn info_from_pgn(info: &mut BTreeMap<String, String>) {
+    if let Some(win) = info
+        .get(PGNRESULTKEY)
+        .and_then(|value| info::Win::from_string(value))
+    {
+        info.remove(PGNRESULTKEY);
+        info.insert(InfoKey::Win.name(), win.to_string());
+    }
+    if let Some(termination) = info
+        .get(&InfoKey::Termination.name())
+        .and_then(|value| info::Termination::from_string(value))
+    {
+        info.insert(InfoKey::Termination.name(), termination.to_string());
+    }
+}
+
+fn is_pgn(record_type: coord::RecordType) -> bool {
+    matches!(
+        record_type,
+        coord::RecordType::PgnIccs | coord::RecordType::PgnRc | coord::RecordType::PgnZh
+    )
+}
+
+// 二进制格式的标记及版本，版本2起各着带有结构化注释
+const BINMAGIC: &[u8] = b"XQMB";
+const BINVERSION: u8 = 2;
+
+// DhtmlXQ_binit及XQF的32个棋子顺序
+pub(crate) const PIECEORDERCHARS: &str = "RNBAKABNRCCPPPPPrnbakabnrccppppp";
+
+// binit：32个棋子依次以两位数字"列行"表示位置，不在棋盘上的为"99"
+fn binit_to_fen(binit: &str) -> common::Result<String> {
+    let digits: Vec<usize> = binit
+        .trim()
+        .chars()
+        .map(|ch| ch.to_digit(10).map(|digit| digit as usize))
+        .collect::<Option<Vec<usize>>>()
+        .ok_or_else(|| common::Error::DhtmlXq(format!("non-digit in binit {binit}")))?;
+    if digits.len() != PIECEORDERCHARS.len() * 2 {
+        return Err(common::Error::DhtmlXq(format!(
+            "binit should have {} digits: {binit}",
+            PIECEORDERCHARS.len() * 2
+        )));
+    }
+
+    let mut piece_chars = vec![b'_'; SEATCOUNT];
+    for (ch, xy) in PIECEORDERCHARS.bytes().zip(digits.chunks(2)) {
+        if xy[0] < COLCOUNT && xy[1] < ROWCOUNT {
+            piece_chars[xy[1] * COLCOUNT + xy[0]] = ch;
+        }
+    }
+
+    Ok(board::piece_chars_to_fen(
+        &String::from_utf8(piece_chars).unwrap(),
+    ))
+}
+
+fn board_to_binit(board: &board::Board) -> String {
+    let mut used = [false; SEATCOUNT];
+    let mut result = String::new();
+    for ch in PIECEORDERCHARS.chars() {
+        let index = board
+            .pieces()
+            .iter()
+            .enumerate()
+            .position(|(index, piece)| !used[index] && piece.ch() == ch);
+        match index {
+            Some(index) => {
+                used[index] = true;
+                result.push_str(&format!("{}{}", index % COLCOUNT, index / COLCOUNT));
+            }
+            None => result.push_str("99"),
+        }
+    }
+
+    result
 }
 
 #[derive(Debug)]
@@ -51,254 +292,479 @@ impl Manual {
         }
     }
 
-    pub fn from(file_name: &str) -> common::Result<Self> {
-        let record_type = coord::RecordType::get_record_type(file_name)?;
-        match record_type {
-            coord::RecordType::Xqf => Self::from_xqf(file_name),
-            coord::RecordType::Bin => Self::from_bin(file_name),
-            _ => Self::from_string(file_name, record_type),
+    // 读入或合成的信息项均经校验并规范化，见insert_read_info
+    pub(crate) fn from_info_move(
+        info: BTreeMap<String, String>,
+        manual_move: manual_move::ManualMove,
+    ) -> Self {
+        let mut manual = Manual {
+            info: BTreeMap::new(),
+            manual_move,
+        };
+        for (name, value) in info {
+            manual.insert_read_info(&name, &value);
+        }
+
+        manual
+    }
+
+    // 换行并为空格；InfoKey的取值无效时原样改存为自定义项"键名Raw"，
+    // 自定义项键名中的空白及"[]:"替换为"_"，键名为空的舍弃
+    fn insert_read_info(&mut self, name: &str, value: &str) {
+        let value = value.lines().map(str::trim).collect::<Vec<&str>>().join(" ");
+        let name = match InfoKey::from_name(name) {
+            Some(key) => match self.set_info(key, &value) {
+                Ok(()) => return,
+                Err(_) => format!("{name}Raw"),
+            },
+            None => name
+                .chars()
+                .map(|ch| {
+                    if ch.is_whitespace() || "[]:".contains(ch) {
+                        '_'
+                    } else {
+                        ch
+                    }
+                })
+                .collect(),
+        };
+        if !name.is_empty() {
+            self.set_custom_info(&name, &value)
+                .expect("custom info name and value are sanitized");
         }
     }
 
-    pub fn write(&self, file_name: &str) -> Result<(), std::io::ErrorKind> {
-        let record_type =
-            coord::RecordType::get_record_type(file_name).map_err(|_| std::io::ErrorKind::Other)?;
-        match record_type {
-            coord::RecordType::Xqf => Err(std::io::ErrorKind::Other),
-            coord::RecordType::Bin => {
-                std::fs::write(&file_name, self.get_bytes()).map_err(|_| std::io::ErrorKind::Other)
+    pub(crate) fn info(&self) -> &BTreeMap<String, String> {
+        &self.info
+    }
+
+    pub fn get_info(&self, key: InfoKey) -> Option<&str> {
+        self.info.get(&key.name()).map(|value| value.as_str())
+    }
+
+    // 取值经校验并规范化，FEN须与棋谱的起始局面一致
+    pub fn set_info(&mut self, key: InfoKey, value: &str) -> common::Result<()> {
+        let value = key.validate(value)?;
+        if key == InfoKey::FEN {
+            let mut fields = value.split(' ');
+            let is_same = fields.next() == Some(&self.manual_move.board().get_fen());
+            if !is_same || !matches!(fields.next(), None | Some("r" | "w" | "b")) {
+                return Err(common::Error::Info(format!("FEN: {value}")));
             }
-            _ => std::fs::write(&file_name, self.to_string(record_type))
-                .map_err(|_| std::io::ErrorKind::Other),
         }
+
+        self.info.insert(key.name(), value);
+        Ok(())
     }
 
-    fn from_xqf(file_name: &str) -> common::Result<Self> {
-        let mut info = BTreeMap::new();
-        let mut manual_move = manual_move::ManualMove::new();
-        if let Ok(input) = std::fs::read(file_name) {
-            //文件标记'XQ'=$5158/版本/加密掩码/ProductId[4], 产品(厂商的产品号)
-            // 棋谱评论员/文件的作者
-            // 32个棋子的原始位置
-            // 加密的钥匙和/棋子布局位置钥匙/棋谱起点钥匙/棋谱终点钥匙
-            // 用单字节坐标表示, 将字节变为十进制, 十位数为X(0-8)个位数为Y(0-9),
-            // 棋盘的左下角为原点(0, 0). 32个棋子的位置从1到32依次为:
-            // 红: 车马相士帅士相马车炮炮兵兵兵兵兵 (位置从右到左, 从下到上)
-            // 黑: 车马象士将士象马车炮炮卒卒卒卒卒 (位置从右到左,
-            // 该谁下 0-红先, 1-黑先/最终结果 0-未知, 1-红胜 2-黑胜, 3-和棋
-            // 从下到上)PlayStepNo[2],
-            // 对局类型(开,中,残等)
-            const PIECENUM: usize = 32;
-            let signature = &input[0..2];
-            // let productid = &byte_vec[4..8];
-            let headqizixy = &input[16..48];
-            // let playstepno = &byte_vec[48..50];
-            // let playnodes = &byte_vec[52..56];
-            // let ptreepos = &byte_vec[56..60];
-            // let reserved1 = &byte_vec[60..64];
-            let headcodea_h = &input[64..80];
-            let titlea = &input[80..144];
-            // let titleb = &byte_vec[144..208];
-            let event = &input[208..272];
-            let date = &input[272..288];
-            let site = &input[288..304];
-            let red = &input[304..320];
-            let black = &input[320..336];
-            let opening = &input[336..400];
-            // let redtime = &byte_vec[400..416];
-            // let blktime = &byte_vec[416..432];
-            // let reservedh = &byte_vec[432..464];
-            let rmkwriter = &input[464..480];
-            let author = &input[480..496]; //, Other[528]{};
-            let version = input[2];
-            let headkeymask = input[3];
-            let headkeyora = input[8];
-            let headkeyorb = input[9];
-            let headkeyorc = input[10];
-            let headkeyord = input[11];
-            let headkeyssum = input[12] as usize;
-            let headkeyxy = input[13] as usize;
-            let headkeyxyf = input[14] as usize;
-            let headkeyxyt = input[15] as usize;
-            // let headwhoplay = byte_vec[50];
-            let headplayresult = input[51] as usize;
-
-            if signature[0] != 0x58 || signature[1] != 0x51 {
-                assert!(false, "文件标记不符。");
-            }
-            if (headkeyssum + headkeyxy + headkeyxyf + headkeyxyt) % 256 != 0 {
-                assert!(false, "检查密码校验和不对，不等于0。");
+    pub fn remove_info(&mut self, key: InfoKey) -> Option<String> {
+        self.info.remove(&key.name())
+    }
+
+    // 自定义信息项，键名不得与InfoKey重名，且不含空白及"[]:"
+    pub fn get_custom_info(&self, name: &str) -> Option<&str> {
+        if InfoKey::from_name(name).is_some() {
+            return None;
+        }
+
+        self.info.get(name).map(|value| value.as_str())
+    }
+
+    pub fn set_custom_info(&mut self, name: &str, value: &str) -> common::Result<()> {
+        let is_valid_name = !name.is_empty()
+            && !name.contains(|ch: char| ch.is_whitespace() || "[]:".contains(ch))
+            && InfoKey::from_name(name).is_none();
+        if !is_valid_name || value.contains(['\n', '\r']) {
+            return Err(common::Error::Info(format!("{name}: {value}")));
+        }
+
+        self.info.insert(name.to_string(), value.trim().to_string());
+        Ok(())
+    }
+
+    pub fn remove_custom_info(&mut self, name: &str) -> Option<String> {
+        if InfoKey::from_name(name).is_some() {
+            return None;
+        }
+
+        self.info.remove(name)
+    }
+
+    pub fn custom_info(&self) -> Vec<(&str, &str)> {
+        self.info
+            .iter()
+            .filter(|(key, _)| InfoKey::from_name(key).is_none())
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
+
+    pub fn win(&self) -> Option<info::Win> {
+        self.get_info(InfoKey::Win).and_then(info::Win::from_string)
+    }
+
+    pub fn set_win(&mut self, win: info::Win) {
+        self.info.insert(InfoKey::Win.name(), win.to_string());
+    }
+
+    // 对局结果，Win信息缺失或无法识别时为None
+    pub fn result(&self) -> Option<info::GameResult> {
+        let termination = self
+            .get_info(InfoKey::Termination)
+            .and_then(info::Termination::from_string);
+
+        Some(info::GameResult::new(self.win()?, termination))
+    }
+
+    pub fn set_result(&mut self, result: info::GameResult) {
+        self.set_win(result.win);
+        match result.termination {
+            Some(termination) => {
+                self.info
+                    .insert(InfoKey::Termination.name(), termination.to_string());
             }
-            if version > 18 {
-                assert!(
-                    false,
-                    "这是一个高版本的XQF文件，您需要更高版本的XQStudio来读取这个文件。"
-                );
+            None => {
+                self.remove_info(InfoKey::Termination);
             }
+        }
+    }
 
-            let keyxyf: usize;
-            let keyxyt: usize;
-            let keyrmksize: usize;
-            let mut f32keys = [0; PIECENUM];
-
-            let mut head_qizixy = headqizixy.to_vec();
-            // version <= 10 兼容1.0以前的版本
-            if version <= 10 {
-                keyrmksize = 0;
-                keyxyf = 0;
-                keyxyt = 0;
-            } else {
-                let keyxy;
-                let calkey = |bkey, ckey| {
-                    // % 256; // 保持为<256
-                    ((((((bkey * bkey) * 3 + 9) * 3 + 8) * 2 + 1) * 3 + 8) * ckey) as u8 as usize
-                };
-
-                keyxy = calkey(headkeyxy, headkeyxy);
-                keyxyf = calkey(headkeyxyf, keyxy);
-                keyxyt = calkey(headkeyxyt, keyxyf);
-                // % 65536
-                keyrmksize = ((headkeyssum * 256 + headkeyxy) % 32000) + 767;
-                // 棋子位置循环移动
-                if version >= 12 {
-                    for (index, qizixy) in headqizixy.iter().enumerate() {
-                        head_qizixy[(index + keyxy + 1) % PIECENUM] = *qizixy;
-                    }
-                }
-                for qizixy in &mut head_qizixy {
-                    // 保持为8位无符号整数，<256
-                    *qizixy = (*qizixy as isize - keyxy as isize) as u8;
-                }
-            }
+    pub fn date(&self) -> Option<info::Date> {
+        self.get_info(InfoKey::Date)
+            .and_then(info::Date::from_string)
+    }
+
+    pub fn set_date(&mut self, date: info::Date) {
+        self.info.insert(InfoKey::Date.name(), date.to_string());
+    }
+
+    pub fn game_type(&self) -> Option<info::GameType> {
+        self.get_info(InfoKey::Atype)
+            .and_then(info::GameType::from_string)
+    }
+
+    pub fn set_game_type(&mut self, game_type: info::GameType) {
+        self.info
+            .insert(InfoKey::Atype.name(), game_type.to_string());
+    }
+
+    // 起始局面，即FEN信息对应的棋盘
+    pub fn start_board(&self) -> board::Board {
+        *self.manual_move.board()
+    }
 
-            let keybytes = [
-                (headkeyssum as u8 & headkeymask) | headkeyora,
-                (headkeyxy as u8 & headkeymask) | headkeyorb,
-                (headkeyxyf as u8 & headkeymask) | headkeyorc,
-                (headkeyxyt as u8 & headkeymask) | headkeyord,
-            ];
-            for (index, ch) in "[(C) Copyright Mr. Dong Shiwei.]".bytes().enumerate() {
-                f32keys[index] = ch & keybytes[index % 4];
-            } // ord(c)
-
-            // 取得棋子字符串
-            let mut piece_chars = vec![b'_'; SEATCOUNT];
-            // QiziXY设定的棋子顺序
-            for (index, ch) in "RNBAKABNRCCPPPPPrnbakabnrccppppp".bytes().enumerate() {
-                let xy = head_qizixy[index] as usize;
-                if xy < SEATCOUNT {
-                    // 用单字节坐标表示, 将字节变为十进制,
-                    // 十位数为X(0-8),个位数为Y(0-9),棋盘的左下角为原点(0, 0)
-                    piece_chars[(ROWCOUNT - 1 - xy % ROWCOUNT) * COLCOUNT + xy / ROWCOUNT] = ch;
+    // 先走的一方，取自FEN信息，缺省为红方
+    pub fn first_color(&self) -> piece::Color {
+        match self
+            .get_info(InfoKey::FEN)
+            .and_then(|fen| fen.split(' ').nth(1))
+        {
+            Some("b") => piece::Color::Black,
+            _ => piece::Color::Red,
+        }
+    }
+
+    pub(crate) fn manual_move(&self) -> &manual_move::ManualMove {
+        &self.manual_move
+    }
+
+    pub fn from(file_name: &str) -> common::Result<Self> {
+        Self::read_file(file_name, coord::RecordType::get_record_type(file_name)?)
+    }
+
+    fn read_file(file_name: &str, record_type: coord::RecordType) -> common::Result<Self> {
+        std::fs::File::open(file_name)
+            .map_err(common::Error::from)
+            .and_then(|file| Self::read_from(std::io::BufReader::new(file), record_type))
+            .map_err(|err| err.in_file(file_name))
+    }
+
+    // 自任意输入源(文件、网络缓冲、内存等)读取棋谱
+    pub fn read_from(mut input: impl Read, record_type: coord::RecordType) -> common::Result<Self> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+
+        match record_type {
+            coord::RecordType::Xqf => Self::from_xqf(&bytes),
+            coord::RecordType::Bin => Self::from_bin_bytes(&bytes),
+            _ => {
+                let manual_string = String::from_utf8(bytes).map_err(|err| {
+                    common::Error::Text(String::from("invalid utf-8 text"))
+                        .at_offset(err.utf8_error().valid_up_to())
+                })?;
+                match record_type {
+                    coord::RecordType::Json => Self::from_json_str(&manual_string),
+                    coord::RecordType::DhtmlXq => Self::from_dhtmlxq_str(&manual_string),
+                    _ => Self::from_manual_str(&manual_string, record_type),
                 }
             }
+        }
+    }
 
-            let fen = board::piece_chars_to_fen(&String::from_utf8(piece_chars).unwrap());
-            let result = ["未知", "红胜", "黑胜", "和棋"];
-            let typestr = ["全局", "开局", "中局", "残局"];
-            let bytes_to_string = |bytes| {
-                GBK.decode(bytes, DecoderTrap::Ignore)
-                    .unwrap()
-                    .replace('\0', "")
-                    .trim()
-                    .into()
-            };
+    pub fn write(&self, file_name: &str) -> common::Result<()> {
+        let write_file = || {
+            let record_type = coord::RecordType::get_record_type(file_name)?;
+            // 先写入内存，不支持的格式不致留下空文件
+            let mut output = Vec::new();
+            self.write_to(&mut output, record_type)?;
 
-            for (key, value) in [
-                (InfoKey::FEN, format!("{fen} r - - 0 1")), // 可能存在不是红棋先走的情况？
-                (InfoKey::Version, version.to_string()),
-                (InfoKey::Win, String::from(result[headplayresult as usize])),
-                (
-                    InfoKey::Atype,
-                    String::from(typestr[headcodea_h[0] as usize]),
-                ),
-                (InfoKey::Title, bytes_to_string(titlea)),
-                (InfoKey::Game, bytes_to_string(event)),
-                (InfoKey::Date, bytes_to_string(date)),
-                (InfoKey::Site, bytes_to_string(site)),
-                (InfoKey::Red, bytes_to_string(red)),
-                (InfoKey::Black, bytes_to_string(black)),
-                (InfoKey::Opening, bytes_to_string(opening)),
-                (InfoKey::Writer, bytes_to_string(rmkwriter)),
-                (InfoKey::Author, bytes_to_string(author)),
-            ] {
-                info.insert(format!("{:?}", key), value);
-            }
+            Ok(std::fs::write(file_name, output)?)
+        };
 
-            manual_move = manual_move::ManualMove::from_xqf(
-                &fen, &input, version, keyxyf, keyxyt, keyrmksize, &f32keys,
-            );
+        write_file().map_err(|err: common::Error| err.in_file(file_name))
+    }
+
+    // 将棋谱写入任意输出目标，XQF格式暂不支持写入，
+    // 其文件头只能经xqf::XqfHeader原样还原，XqfReserved等信息项不足以重建
+    pub fn write_to(
+        &self,
+        mut output: impl Write,
+        record_type: coord::RecordType,
+    ) -> common::Result<()> {
+        match record_type {
+            coord::RecordType::Xqf => return Err(common::Error::Unsupported(record_type)),
+            coord::RecordType::Bin => output.write_all(&self.get_bytes()?)?,
+            _ => output.write_all(self.to_string(record_type).as_bytes())?,
         }
 
-        Ok(Manual { info, manual_move })
+        Ok(output.flush()?)
+    }
+
+    fn from_xqf(input: &[u8]) -> common::Result<Self> {
+        let header = xqf::XqfHeader::from_bytes(input)?;
+        let info = header
+            .info()
+            .into_iter()
+            .map(|(key, value)| (key.name(), value))
+            .collect();
+
+        let manual_move = manual_move::ManualMove::from_xqf(
+            &header.fen(),
+            input,
+            header.version,
+            header.keyxyf,
+            header.keyxyt,
+            header.keyrmksize,
+            &header.f32keys,
+        )?;
+
+        Ok(Self::from_info_move(info, manual_move))
     }
 
     pub fn from_bin(file_name: &str) -> common::Result<Self> {
+        Self::read_file(file_name, coord::RecordType::Bin)
+    }
+
+    fn from_bin_bytes(bytes: &[u8]) -> common::Result<Self> {
+        let mut input = bytes;
+        Self::read_bin(&mut input).map_err(|err| err.at_offset(bytes.len() - input.len()))
+    }
+
+    fn read_bin(input: &mut &[u8]) -> common::Result<Self> {
+        // 无标记的为不带结构化注释的旧格式
+        let version = if input.starts_with(BINMAGIC) {
+            common::read_bytes(input, BINMAGIC.len())?;
+            common::read_bytes(input, 1)?[0]
+        } else {
+            1
+        };
+        if version > BINVERSION {
+            return Err(common::Error::Bin(format!("unsupported version {version}")));
+        }
+
         let mut info = BTreeMap::new();
-        let mut manual_move = manual_move::ManualMove::new();
-        if let Ok(input) = std::fs::read(file_name) {
-            let mut input = input.borrow();
-            let info_len = common::read_be_u32(&mut input);
-            for _ in 0..info_len {
-                let key = common::read_string(&mut input);
-                let value = common::read_string(&mut input);
-
-                // println!("key_value: {key} = {value}");
-                info.insert(key, value);
-            }
-            let fen = info
-                .get(&format!("{:?}", InfoKey::FEN))
-                .unwrap()
-                .split(' ')
-                .collect::<Vec<&str>>()[0];
+        let info_len = common::read_be_u32(input)?;
+        for _ in 0..info_len {
+            let key = common::read_string(input)?;
+            let value = common::read_string(input)?;
 
-            manual_move = manual_move::ManualMove::from_bin(fen, &mut input);
+            // println!("key_value: {key} = {value}");
+            info.insert(key, value);
         }
+        let fen = match info.get(&InfoKey::FEN.name()) {
+            Some(value) => value.split(' ').next().unwrap_or(board::FEN),
+            None => board::FEN,
+        };
+
+        let manual_move = manual_move::ManualMove::from_bin(fen, input, version)?;
 
-        Ok(Manual { info, manual_move })
+        Ok(Self::from_info_move(info, manual_move))
     }
 
-    pub fn get_bytes(&self) -> Vec<u8> {
-        let mut result = Vec::new();
+    pub fn get_bytes(&self) -> common::Result<Vec<u8>> {
+        let mut result = BINMAGIC.to_vec();
+        result.push(BINVERSION);
         common::write_be_u32(&mut result, self.info.len() as u32);
         for (key, value) in &self.info {
             common::write_string(&mut result, key);
             common::write_string(&mut result, value);
         }
-        result.append(&mut self.manual_move.get_bytes());
+        result.append(&mut self.manual_move.get_bytes()?);
+
+        Ok(result)
+    }
+
+    pub fn from_json(file_name: &str) -> common::Result<Self> {
+        Self::read_file(file_name, coord::RecordType::Json)
+    }
+
+    fn from_json_str(manual_string: &str) -> common::Result<Self> {
+        let manual_json: ManualJson = serde_json::from_str(manual_string)?;
+
+        let fen = manual_json.fen.split(' ').next().unwrap_or(board::FEN);
+        let manual_move = manual_move::ManualMove::from_json(fen, &manual_json.root)?;
+
+        Ok(Self::from_info_move(manual_json.info, manual_move))
+    }
+
+    pub fn to_json(&self) -> String {
+        let manual_json = ManualJson {
+            info: self.info.clone(),
+            fen: self.manual_move.board().get_fen(),
+            root: self.manual_move.to_json(),
+        };
+
+        serde_json::to_string_pretty(&manual_json).unwrap()
+    }
+
+    pub fn from_dhtmlxq(file_name: &str) -> common::Result<Self> {
+        Self::read_file(file_name, coord::RecordType::DhtmlXq)
+    }
+
+    fn from_dhtmlxq_str(manual_string: &str) -> common::Result<Self> {
+        let block_re = regex::Regex::new(r"\[DhtmlXQ_(\w+)\]([\s\S]*?)\[/DhtmlXQ_\w+\]").unwrap();
+        let mut blocks = BTreeMap::new();
+        for caps in block_re.captures_iter(manual_string) {
+            if let (Some(key), Some(value)) = (caps.at(1), caps.at(2)) {
+                blocks.insert(key.to_string(), value.to_string());
+            }
+        }
+
+        let mut info = BTreeMap::new();
+        for (key, value) in &blocks {
+            let is_move_key = ["binit", "movelist", "move_", "comment", "ver", "init"]
+                .iter()
+                .any(|move_key| key.starts_with(move_key));
+            if is_move_key {
+                continue;
+            }
+
+            let info_key = match DHTMLXQINFOKEYS.iter().find(|(dkey, _)| dkey == key) {
+                Some((_, info_key)) => info_key.name(),
+                None => key.clone(),
+            };
+            info.insert(info_key, value.clone());
+        }
+
+        let fen = match blocks.get("binit") {
+            Some(binit) => binit_to_fen(binit)?,
+            None => board::FEN.to_string(),
+        };
+        let manual_move = manual_move::ManualMove::from_dhtmlxq(&fen, &blocks)?;
+        // binit不含走子方，取自首着棋子的颜色
+        let first_color = manual_move
+            .root_move()
+            .after()
+            .first()
+            .and_then(|amove| {
+                manual_move
+                    .board()
+                    .bit_board()
+                    .get_color(amove.coordpair.from_coord.index())
+            })
+            .unwrap_or(piece::Color::Red);
+        let side = if first_color == piece::Color::Black {
+            "b"
+        } else {
+            "r"
+        };
+        info.insert(InfoKey::FEN.name(), format!("{fen} {side} - - 0 1"));
+
+        Ok(Self::from_info_move(info, manual_move))
+    }
+
+    pub fn to_dhtmlxq(&self) -> String {
+        let mut result = String::from("[DhtmlXQ]\n");
+        let mut push_block = |key: &str, value: &str| {
+            result.push_str(&format!("[DhtmlXQ_{key}]{value}[/DhtmlXQ_{key}]\n"));
+        };
+
+        for (dkey, info_key) in DHTMLXQINFOKEYS {
+            if let Some(value) = self.info.get(&info_key.name()) {
+                push_block(dkey, value);
+            }
+        }
+        for (key, value) in &self.info {
+            let is_mapped = DHTMLXQINFOKEYS
+                .iter()
+                .any(|(_, info_key)| info_key.name() == *key);
+            if !is_mapped && *key != InfoKey::FEN.name() {
+                push_block(key, value);
+            }
+        }
+
+        push_block("binit", &board_to_binit(self.manual_move.board()));
+        for (key, value) in self.manual_move.to_dhtmlxq() {
+            push_block(&key, &value);
+        }
+        result.push_str("[/DhtmlXQ]\n");
 
         result
     }
 
     pub fn from_string(file_name: &str, record_type: coord::RecordType) -> common::Result<Self> {
-        let manual_string =
-            std::fs::read_to_string(&file_name).map_err(|_| common::ParseError::StringParse)?;
-        let (info_str, manual_move_str) = manual_string
-            .split_once("\n\n")
-            .ok_or(common::ParseError::StringParse)?;
+        Self::read_file(file_name, record_type)
+    }
+
+    fn from_manual_str(
+        manual_string: &str,
+        record_type: coord::RecordType,
+    ) -> common::Result<Self> {
+        let (info_str, manual_move_str) = manual_string.split_once("\n\n").ok_or_else(|| {
+            common::Error::Text(String::from("no blank line between info and moves"))
+        })?;
 
         let mut info = BTreeMap::new();
-        let info_re = regex::Regex::new(r"\[(\S+): ([\s\S]*)\]").unwrap();
+        let info_re = regex::Regex::new(r"(?m)^\[(\S+): ([\s\S]*?)\]$").unwrap();
         for caps in info_re.captures_iter(info_str) {
-            let key = caps.at(1).unwrap().to_string();
-            let value = caps.at(2).unwrap().to_string();
-
-            info.insert(key, value);
+            if let (Some(key), Some(value)) = (caps.at(1), caps.at(2)) {
+                info.insert(key.to_string(), value.to_string());
+            }
+        }
+        if is_pgn(record_type) {
+            info_from_pgn(&mut info);
         }
         // println!("{:?}", info);
 
-        let fen = match info.get(&format!("{:?}", InfoKey::FEN)) {
-            Some(value) => value.split_once(" ").unwrap().0,
+        let fen = match info.get(&InfoKey::FEN.name()) {
+            Some(value) => value.split(' ').next().unwrap_or(board::FEN),
             None => board::FEN,
         };
-        let manual_move = manual_move::ManualMove::from_string(fen, manual_move_str, record_type)?;
+        // 着法部分之前为信息各行及一空行
+        let manual_move = manual_move::ManualMove::from_string(fen, manual_move_str, record_type)
+            .map_err(|err| err.shift_line(info_str.matches('\n').count() + 2))?;
 
-        Ok(Manual { info, manual_move })
+        Ok(Self::from_info_move(info, manual_move))
+    }
+
+    // 合并起始局面相同的棋谱，本棋谱的信息保持不变
+    pub fn merge(&self, other: &Manual, by_zobrist: bool) -> common::Result<()> {
+        self.manual_move.merge(&other.manual_move, by_zobrist)
     }
 
     pub fn to_string(&self, record_type: coord::RecordType) -> String {
+        match record_type {
+            coord::RecordType::Json => return self.to_json(),
+            coord::RecordType::DhtmlXq => return self.to_dhtmlxq(),
+            _ => (),
+        }
+
+        let info = if is_pgn(record_type) {
+            info_to_pgn(&self.info)
+        } else {
+            self.info.clone()
+        };
         let mut remark = String::new();
-        for (key, value) in &self.info {
+        for (key, value) in &info {
             remark.push_str(&format!("[{key}: {value}]\n"));
         }
 
@@ -316,6 +782,139 @@ mod tests {
     fn test_manual() {
         let manual = Manual::new();
         assert_eq!("\n\n", manual.to_string(coord::RecordType::Txt));
+        let err = Manual::from("tests/xqf/不存在.xqf").unwrap_err();
+        assert!(matches!(err.kind(), common::Error::Io(_)));
+        assert!(std::error::Error::source(&err).is_some());
+        assert!(err
+            .to_string()
+            .starts_with("tests/xqf/不存在.xqf: I/O error"));
+        assert!(matches!(
+            Manual::read_from(&b"XQ"[..], coord::RecordType::Xqf),
+            Err(common::Error::Xqf(_))
+        ));
+        assert!(matches!(
+            manual.write_to(Vec::new(), coord::RecordType::Xqf),
+            Err(common::Error::Unsupported(coord::RecordType::Xqf))
+        ));
+
+        // 出错位置：文本格式为行号，二进制格式为字节偏移
+        let err = Manual::read_from(
+            &b"[Title: test]\n\n(1)\n(7,7)(7,4)(1)\n(0,7)(2,9)\n"[..],
+            coord::RecordType::Txt,
+        )
+        .unwrap_err();
+        assert_eq!(Some(5), err.location().unwrap().line);
+        assert!(matches!(err.kind(), common::Error::ColOut));
+        assert_eq!("line 5: column out of range", err.to_string());
+        let err = Manual::read_from(&[0, 0, 0, 1, 0, 0][..], coord::RecordType::Bin).unwrap_err();
+        assert_eq!(Some(4), err.location().unwrap().offset);
+        let err = Manual::read_from(&b"{\"fen\": 1}"[..], coord::RecordType::Json).unwrap_err();
+        assert!(matches!(err, common::Error::Json(_)));
+
+        // DhtmlXQ的走子方取自首着
+        let dhtmlxq_string = "[DhtmlXQ][DhtmlXQ_binit]9999999949999999999999999999999999999999409999999999999999999999[/DhtmlXQ_binit][DhtmlXQ_movelist]4041[/DhtmlXQ_movelist][/DhtmlXQ]";
+        let manual =
+            Manual::read_from(dhtmlxq_string.as_bytes(), coord::RecordType::DhtmlXq).unwrap();
+        assert_eq!(
+            Some("4k4/9/9/9/9/9/9/9/9/4K4 b - - 0 1"),
+            manual.get_info(InfoKey::FEN)
+        );
+        assert_eq!(piece::Color::Black, manual.first_color());
+
+        // 结构化注释在二进制、文本及JSON格式中保留
+        let manual_string = "[Title: test]\n\n{[%clk 0:10:00] 开局}(1)\n(7,7)(7,4) $1{[%eval 0.30,8][%cal RG9E9]}(1)\n(0,7)(2,6) $2 $6{[%csl GE7] 马８进７}\n";
+        let manual = Manual::read_from(manual_string.as_bytes(), coord::RecordType::Txt).unwrap();
+        assert_eq!(manual_string, manual.to_string(coord::RecordType::Txt));
+        let first_move = manual.manual_move.root_move().after()[0].clone();
+        assert_eq!(Some(30), first_move.annotation().score);
+        for record_type in [
+            coord::RecordType::Bin,
+            coord::RecordType::PgnIccs,
+            coord::RecordType::Json,
+        ] {
+            let mut output = Vec::new();
+            manual.write_to(&mut output, record_type).unwrap();
+            let manual = Manual::read_from(output.as_slice(), record_type).unwrap();
+            assert_eq!(manual_string, manual.to_string(coord::RecordType::Txt));
+        }
+        let err = Manual::read_from(&b"XQMB\x09"[..], coord::RecordType::Bin).unwrap_err();
+        assert_eq!(
+            "byte 5: invalid binary data: unsupported version 9",
+            err.to_string()
+        );
+
+        // 按InfoKey取值、设值，取值经校验并规范化
+        let mut manual = Manual::read_from(
+            &b"[FEN: 4k4/9/9/9/9/9/9/9/9/4K4 b - - 0 1]\n[Win: 1-0]\n[Date: 1993.4.27]\n\n(0)\n"[..],
+            coord::RecordType::Txt,
+        )
+        .unwrap();
+        assert_eq!(Some(info::Win::RedWin), manual.win());
+        assert_eq!(Some("红胜"), manual.get_info(InfoKey::Win));
+        assert_eq!(Some("1993-04-27"), manual.get_info(InfoKey::Date));
+        assert_eq!("1993-04-27", manual.date().unwrap().to_string());
+        assert_eq!(piece::Color::Black, manual.first_color());
+        assert_eq!("4k4/9/9/9/9/9/9/9/9/4K4", manual.start_board().get_fen());
+        manual.set_info(InfoKey::Win, "和棋").unwrap();
+        assert_eq!(Some(info::Win::Draw), manual.win());
+        manual.set_info(InfoKey::TimeControl, " 600+5").unwrap();
+        assert_eq!(Some("600+5"), manual.get_info(InfoKey::TimeControl));
+        manual.set_game_type(info::GameType::Ending);
+        assert_eq!(Some("残局"), manual.get_info(InfoKey::Atype));
+        for (key, value) in [
+            (InfoKey::Win, "红方"),
+            (InfoKey::Date, "1993.13.1"),
+            (InfoKey::Atype, "快棋"),
+            (InfoKey::Version, "v1"),
+            (InfoKey::Title, "a\nb"),
+            (InfoKey::FEN, "9/9/9/9/9/9/9/9/9/9 r - - 0 1"),
+        ] {
+            let err = manual.set_info(key, value).unwrap_err();
+            assert!(matches!(err, common::Error::Info(_)));
+        }
+        manual
+            .set_info(InfoKey::FEN, "4k4/9/9/9/9/9/9/9/9/4K4 r - - 0 1")
+            .unwrap();
+        assert_eq!(piece::Color::Red, manual.first_color());
+
+        manual.set_custom_info("Round", "第3轮").unwrap();
+        assert!(manual.set_custom_info("Title", "x").is_err());
+        assert!(manual.set_custom_info("My Key", "x").is_err());
+        assert_eq!(Some("第3轮"), manual.get_custom_info("Round"));
+        assert_eq!(None, manual.get_custom_info("Win"));
+        assert_eq!(vec![("Round", "第3轮")], manual.custom_info());
+        assert_eq!(
+            Some(String::from("第3轮")),
+            manual.remove_custom_info("Round")
+        );
+        assert_eq!(Some(InfoKey::RedTeam), InfoKey::from_name("RedTeam"));
+        assert!(manual
+            .to_string(coord::RecordType::Txt)
+            .starts_with("[Atype: 残局]\n[Date: 1993-04-27]\n[FEN: 4k4/9/9/9/9/9/9/9/9/4K4 r - - 0 1]\n[TimeControl: 600+5]\n[Win: 和棋]\n"));
+
+        // 读入时无效的取值改存为自定义项，换行并为空格
+        let manual_string = "[Date: 1993.13.1]\n[Title: 第一局\n续]\n\n(0)\n";
+        for record_type in [coord::RecordType::Txt, coord::RecordType::Json] {
+            let manual = Manual::read_from(manual_string.as_bytes(), coord::RecordType::Txt)
+                .and_then(|manual| {
+                    let mut output = Vec::new();
+                    manual.write_to(&mut output, record_type)?;
+                    Manual::read_from(output.as_slice(), record_type)
+                })
+                .unwrap();
+            assert_eq!(None, manual.get_info(InfoKey::Date));
+            assert_eq!(None, manual.date());
+            assert_eq!(Some("1993.13.1"), manual.get_custom_info("DateRaw"));
+            assert_eq!(Some("第一局 续"), manual.get_info(InfoKey::Title));
+        }
+        let json_string = format!(
+            "{{\"info\": {{\"Win\": \"1-0\", \"Version\": \"v1\", \"My Key\": \"x\"}}, \"fen\": \"{}\"}}",
+            board::FEN
+        );
+        let manual = Manual::read_from(json_string.as_bytes(), coord::RecordType::Json).unwrap();
+        assert_eq!(Some("红胜"), manual.get_info(InfoKey::Win));
+        assert_eq!(Some("v1"), manual.get_custom_info("VersionRaw"));
+        assert_eq!(Some("x"), manual.get_custom_info("My_Key"));
 
         let file_name_manual_strings = [
             ("01","[Atype: 残局]
@@ -327,7 +926,7 @@ mod tests {
 [Opening: ]
 [Red: ]
 [Site: ]
-[Title: 第01局]
+[Title: 第01局]
 [Version: 18]
 [Win: 红胜]
 [Writer: ]
@@ -379,7 +978,7 @@ mod tests {
 (0,4)(0,5){和棋。}
 "),
             ("4四量拨千斤","[Atype: 全局]
-[Author: 橘子黄了]
+[Author: 橘子黄了]
 [Black: ]
 [Date: ]
 [FEN: rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR r - - 0 1]
@@ -455,7 +1054,7 @@ mod tests {
 [Opening: ]
 [Red: ]
 [Site: ]
-[Title: 第09局]
+[Title: 第09局]
 [Version: 18]
 [Win: 红胜]
 [Writer: ]
@@ -541,11 +1140,11 @@ mod tests {
 [Black: ]
 [Date: ]
 [FEN: rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR r - - 0 1]
-[Game: 布局陷阱--飞相局对金钩炮]
+[Game: 布局陷阱--飞相局对金钩炮]
 [Opening: ]
 [Red: ]
 [Site: ]
-[Title: 布局陷阱--飞相局对金钩炮]
+[Title: 布局陷阱--飞相局对金钩炮]
 [Version: 12]
 [Win: 红胜]
 [Writer: ]
@@ -584,12 +1183,12 @@ mod tests {
             ("- 北京张强 (和) 上海胡荣华 (1993.4.27于南京)","[Atype: 全局]
 [Author: ]
 [Black: 上海胡荣华]
-[Date: 1993.4.27]
+[Date: 1993-04-27]
 [FEN: rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR r - - 0 1]
-[Game: 93全国象棋锦标赛]
+[Game: 93全国象棋锦标赛]
 [Opening: ]
-[Red: 北京张强]
-[Site: 南京]
+[Red: 北京张强]
+[Site: 南京]
 [Title: 挺兵对卒底炮]
 [Version: 13]
 [Win: 和棋]
@@ -676,6 +1275,8 @@ mod tests {
                     coord::RecordType::PgnIccs,
                     coord::RecordType::PgnRc,
                     coord::RecordType::PgnZh,
+                    coord::RecordType::Json,
+                    coord::RecordType::DhtmlXq,
                 ] {
                     let file_path = get_file_path(file_name, record_type);
                     if std::fs::File::open(&file_path).is_err() {
@@ -687,6 +1288,14 @@ mod tests {
                     if let Ok(manual) = Manual::from(&file_path) {
                         assert_eq!(manual_string, manual.to_string(coord::RecordType::Txt));
                     }
+
+                    // 内存中读写(中文着法尚不能读入)
+                    if record_type != coord::RecordType::PgnZh {
+                        let mut output = Vec::new();
+                        manual.write_to(&mut output, record_type).unwrap();
+                        let manual = Manual::read_from(output.as_slice(), record_type).unwrap();
+                        assert_eq!(manual_string, manual.to_string(coord::RecordType::Txt));
+                    }
                 }
             }
         }
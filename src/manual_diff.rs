@@ -0,0 +1,424 @@
+#![allow(dead_code)]
+
+use crate::amove;
+use crate::board;
+use crate::common;
+use crate::manual;
+use crate::manual_move;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::rc::Rc;
+
+// 着法路径：自开局起的中文纵线着法
+pub type MovePath = Vec<String>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Difference {
+    AddedMove(MovePath),
+    RemovedMove(MovePath),
+    ChangedRemark {
+        path: MovePath,
+        old: String,
+        new: String,
+    },
+    ChangedInfo {
+        key: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Conflict {
+    // 双方修改了同一信息项
+    Info {
+        key: String,
+        ours: Option<String>,
+        theirs: Option<String>,
+    },
+    // 双方修改了同一着法的备注
+    Remark {
+        path: MovePath,
+        ours: String,
+        theirs: String,
+    },
+    // 一方删除了另一方修改过的变着
+    Move(MovePath),
+}
+
+#[derive(Debug)]
+pub struct ManualDiff {
+    pub differences: Vec<Difference>,
+}
+
+fn path_string(path: &MovePath) -> String {
+    if path.is_empty() {
+        String::from("开局")
+    } else {
+        path.join(" ")
+    }
+}
+
+fn option_string(value: &Option<String>) -> &str {
+    value.as_deref().unwrap_or("")
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difference::AddedMove(path) => writeln!(f, "+ {}", path_string(path)),
+            Difference::RemovedMove(path) => writeln!(f, "- {}", path_string(path)),
+            Difference::ChangedRemark { path, old, new } => {
+                writeln!(f, "~ {}: {{{}}} => {{{}}}", path_string(path), old, new)
+            }
+            Difference::ChangedInfo { key, old, new } => writeln!(
+                f,
+                "[{}: {} => {}]",
+                key,
+                option_string(old),
+                option_string(new)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::Info { key, ours, theirs } => writeln!(
+                f,
+                "! [{}]: {} <> {}",
+                key,
+                option_string(ours),
+                option_string(theirs)
+            ),
+            Conflict::Remark { path, ours, theirs } => {
+                writeln!(f, "! {}: {{{}}} <> {{{}}}", path_string(path), ours, theirs)
+            }
+            Conflict::Move(path) => writeln!(f, "! {}: 修改与删除冲突", path_string(path)),
+        }
+    }
+}
+
+impl ManualDiff {
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl fmt::Display for ManualDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for difference in &self.differences {
+            write!(f, "{}", difference)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn zh_path(path: &MovePath, board: &board::Board, amove: &Rc<amove::Move>) -> MovePath {
+    let mut path = path.clone();
+    path.push(board.get_zhstr_from_coordpair(&amove.coordpair));
+
+    path
+}
+
+fn check_board(manuals: &[&manual::Manual]) -> common::Result<board::Board> {
+    let board = *manuals[0].manual_move().board();
+    if manuals
+        .iter()
+        .any(|manual| *manual.manual_move().board() != board)
+    {
+        return Err(common::Error::BoardNotMatch);
+    }
+
+    Ok(board)
+}
+
+// 比较两个棋谱，列出新棋谱相对旧棋谱的变动
+pub fn diff(old: &manual::Manual, new: &manual::Manual) -> common::Result<ManualDiff> {
+    let mut board = check_board(&[old, new])?;
+    let mut differences = Vec::new();
+
+    let (old_info, new_info) = (old.info(), new.info());
+    for key in old_info
+        .keys()
+        .chain(new_info.keys())
+        .collect::<BTreeSet<_>>()
+    {
+        let (old_value, new_value) = (old_info.get(key), new_info.get(key));
+        if old_value != new_value {
+            differences.push(Difference::ChangedInfo {
+                key: key.clone(),
+                old: old_value.cloned(),
+                new: new_value.cloned(),
+            });
+        }
+    }
+
+    diff_move(
+        &mut board,
+        &mut Vec::new(),
+        &old.manual_move().root_move(),
+        &new.manual_move().root_move(),
+        &mut differences,
+    );
+
+    Ok(ManualDiff { differences })
+}
+
+fn diff_move(
+    board: &mut board::Board,
+    path: &mut MovePath,
+    old_move: &Rc<amove::Move>,
+    new_move: &Rc<amove::Move>,
+    differences: &mut Vec<Difference>,
+) {
+    if old_move.remark() != new_move.remark() {
+        differences.push(Difference::ChangedRemark {
+            path: path.clone(),
+            old: old_move.remark(),
+            new: new_move.remark(),
+        });
+    }
+
+    for old_after in old_move.after() {
+        if new_move.find_after(&old_after.coordpair).is_none() {
+            differences.push(Difference::RemovedMove(zh_path(path, board, &old_after)));
+        }
+    }
+
+    for new_after in new_move.after() {
+        let after_path = zh_path(path, board, &new_after);
+        match old_move.find_after(&new_after.coordpair) {
+            None => differences.push(Difference::AddedMove(after_path)),
+            Some(old_after) => {
+                let zhstr = after_path.last().unwrap().clone();
+                board.do_move(&new_after);
+                path.push(zhstr);
+                diff_move(board, path, &old_after, &new_after, differences);
+                path.pop();
+                board.undo_move(&new_after);
+            }
+        }
+    }
+}
+
+// 三方取值：一方未改则取另一方，双方改动不一致时冲突(返回None)
+fn merge3_value<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> Option<T> {
+    if ours == theirs || theirs == base {
+        Some(ours.clone())
+    } else if ours == base {
+        Some(theirs.clone())
+    } else {
+        None
+    }
+}
+
+fn is_same_tree(amove: &Rc<amove::Move>, bmove: &Rc<amove::Move>) -> bool {
+    amove.remark() == bmove.remark()
+        && amove.after_len() == bmove.after_len()
+        && amove.after().iter().all(|aafter| {
+            bmove
+                .find_after(&aafter.coordpair)
+                .is_some_and(|bafter| is_same_tree(aafter, &bafter))
+        })
+}
+
+fn copy_after(to_move: &Rc<amove::Move>, from_move: &Rc<amove::Move>) {
+    for from_after in from_move.after() {
+        let to_after = to_move.append(from_after.coordpair, from_after.remark());
+        copy_after(&to_after, &from_after);
+    }
+}
+
+// 三方合并：以共同祖先base为准，合并ours与theirs各自的修改；冲突时保留ours并报告
+pub fn merge3(
+    base: &manual::Manual,
+    ours: &manual::Manual,
+    theirs: &manual::Manual,
+) -> common::Result<(manual::Manual, Vec<Conflict>)> {
+    let mut board = check_board(&[base, ours, theirs])?;
+    let mut conflicts = Vec::new();
+
+    let mut info = BTreeMap::new();
+    let (base_info, ours_info, theirs_info) = (base.info(), ours.info(), theirs.info());
+    for key in ours_info
+        .keys()
+        .chain(theirs_info.keys())
+        .collect::<BTreeSet<_>>()
+    {
+        let (base_value, ours_value, theirs_value) = (
+            base_info.get(key).cloned(),
+            ours_info.get(key).cloned(),
+            theirs_info.get(key).cloned(),
+        );
+        let value = merge3_value(&base_value, &ours_value, &theirs_value).unwrap_or_else(|| {
+            conflicts.push(Conflict::Info {
+                key: key.clone(),
+                ours: ours_value.clone(),
+                theirs: theirs_value.clone(),
+            });
+            ours_value.clone()
+        });
+        if let Some(value) = value {
+            info.insert(key.clone(), value);
+        }
+    }
+
+    let root_move = amove::Move::root();
+    merge3_move(
+        &mut board,
+        &mut Vec::new(),
+        Some(base.manual_move().root_move()),
+        &ours.manual_move().root_move(),
+        &theirs.manual_move().root_move(),
+        &root_move,
+        &mut conflicts,
+    );
+
+    let manual_move = manual_move::ManualMove::from(&board.get_fen(), root_move);
+    Ok((manual::Manual::from_info_move(info, manual_move), conflicts))
+}
+
+fn merge3_move(
+    board: &mut board::Board,
+    path: &mut MovePath,
+    base_move: Option<Rc<amove::Move>>,
+    ours_move: &Rc<amove::Move>,
+    theirs_move: &Rc<amove::Move>,
+    to_move: &Rc<amove::Move>,
+    conflicts: &mut Vec<Conflict>,
+) {
+    let base_remark = base_move
+        .as_ref()
+        .map(|amove| amove.remark())
+        .unwrap_or_default();
+    let (ours_remark, theirs_remark) = (ours_move.remark(), theirs_move.remark());
+    let remark = merge3_value(&base_remark, &ours_remark, &theirs_remark).unwrap_or_else(|| {
+        conflicts.push(Conflict::Remark {
+            path: path.clone(),
+            ours: ours_remark.clone(),
+            theirs: theirs_remark.clone(),
+        });
+        ours_remark.clone()
+    });
+    to_move.set_remark(remark);
+
+    let mut after_moves = ours_move.after();
+    for theirs_after in theirs_move.after() {
+        if ours_move.find_after(&theirs_after.coordpair).is_none() {
+            after_moves.push(theirs_after);
+        }
+    }
+
+    for after_move in after_moves {
+        let coordpair = after_move.coordpair;
+        let base_after = base_move
+            .as_ref()
+            .and_then(|amove| amove.find_after(&coordpair));
+        let ours_after = ours_move.find_after(&coordpair);
+        let theirs_after = theirs_move.find_after(&coordpair);
+        let after_path = zh_path(path, board, &after_move);
+        match (ours_after, theirs_after) {
+            (Some(ours_after), Some(theirs_after)) => {
+                let to_after = to_move.append(coordpair, String::new());
+                board.do_move(&after_move);
+                path.push(after_path.last().unwrap().clone());
+                merge3_move(
+                    board,
+                    path,
+                    base_after,
+                    &ours_after,
+                    &theirs_after,
+                    &to_after,
+                    conflicts,
+                );
+                path.pop();
+                board.undo_move(&after_move);
+            }
+            (Some(one_after), None) | (None, Some(one_after)) => {
+                // 一方新增则保留；一方删除而另一方未改则删除，已改则冲突并保留
+                let keep = match base_after {
+                    None => true,
+                    Some(base_after) => {
+                        let is_changed = !is_same_tree(&base_after, &one_after);
+                        if is_changed {
+                            conflicts.push(Conflict::Move(after_path));
+                        }
+                        is_changed
+                    }
+                };
+
+                if keep {
+                    let to_after = to_move.append(coordpair, one_after.remark());
+                    copy_after(&to_after, &one_after);
+                }
+            }
+            (None, None) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_manual_diff() {
+        let to_manual = |title: &str, manual_move_str| {
+            let mut info = BTreeMap::new();
+            info.insert(String::from("Title"), String::from(title));
+            let manual_move = manual_move::ManualMove::from_string(
+                board::FEN,
+                manual_move_str,
+                coord::RecordType::Txt,
+            )
+            .unwrap();
+
+            manual::Manual::from_info_move(info, manual_move)
+        };
+
+        let base = to_manual("中炮", "(1)\n(7,7)(7,4)(2)\n(0,7)(2,6)\n(0,1)(2,2)\n");
+        let ours = to_manual(
+            "中炮对屏风马",
+            "(1)\n(7,7)(7,4){中炮}(1)\n(0,7)(2,6)(1)\n(9,7)(7,6)\n",
+        );
+        let theirs = to_manual(
+            "中炮",
+            "(2)\n(7,7)(7,4)(2)\n(7,1)(7,4)\n(0,7)(2,6)\n(0,1)(2,2)\n",
+        );
+
+        let manual_diff = diff(&base, &ours).unwrap();
+        assert_eq!(
+            "[Title: 中炮 => 中炮对屏风马]
+~ 炮二平五: {} => {中炮}
+- 炮二平五 马２进３
++ 炮二平五 马８进７ 马二进三
+",
+            manual_diff.to_string()
+        );
+        assert!(diff(&ours, &ours).unwrap().is_empty());
+
+        let (manual, conflicts) = merge3(&base, &ours, &theirs).unwrap();
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            "(2)\n(7,7)(7,4){中炮}(1)\n(7,1)(7,4)\n(0,7)(2,6)(1)\n(9,7)(7,6)\n",
+            manual.manual_move().to_string(coord::RecordType::Txt)
+        );
+        assert_eq!("中炮对屏风马", manual.info()["Title"]);
+
+        let theirs = to_manual("中炮局", "(1)\n(7,7)(7,4){炮二平五}(1)\n(0,7)(2,6)\n");
+        let (_, conflicts) = merge3(&base, &ours, &theirs).unwrap();
+        assert_eq!(
+            "! [Title]: 中炮对屏风马 <> 中炮局
+! 炮二平五: {中炮} <> {炮二平五}
+",
+            conflicts
+                .iter()
+                .map(|conflict| conflict.to_string())
+                .collect::<String>()
+        );
+    }
+}
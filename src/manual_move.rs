@@ -5,10 +5,11 @@ use crate::evaluation;
 use crate::{amove, common, coord};
 use encoding::all::GBK;
 use encoding::{DecoderTrap, Encoding};
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 // use crate::bit_constant;
 // use std::borrow::Borrow;
 use crate::board;
+use crate::piece;
 // use crate::utility;
 // use crate::manual;
 // use std::cell::RefCell;
@@ -27,27 +28,41 @@ impl ManualMove {
         ManualMove::from(board::FEN, amove::Move::root())
     }
 
-    fn from(fen: &str, root_move: Rc<amove::Move>) -> Self {
+    pub(crate) fn from(fen: &str, root_move: Rc<amove::Move>) -> Self {
         ManualMove {
             board: board::Board::from(fen),
             root_move,
         }
     }
 
+    pub(crate) fn board(&self) -> &board::Board {
+        &self.board
+    }
+
+    pub(crate) fn root_move(&self) -> Rc<amove::Move> {
+        self.root_move.clone()
+    }
+
     pub fn from_xqf(
         fen: &str,
-        input: &Vec<u8>,
+        input: &[u8],
         version: u8,
         keyxyf: usize,
         keyxyt: usize,
         keyrmksize: usize,
         f32keys: &[u8],
-    ) -> Self {
+    ) -> common::Result<Self> {
         let __sub = |a, b| (a as isize - b as isize) as u8; // 保持为<256
 
-        let read_bytes = |pos: &mut usize, size| {
+        let read_bytes = |pos: &mut usize, size| -> common::Result<Vec<u8>> {
             let new_pos = *pos + size;
-            let mut bytes = input[*pos..new_pos].to_vec();
+            let mut bytes = input
+                .get(*pos..new_pos)
+                .ok_or_else(|| {
+                    common::Error::Xqf(format!("unexpected end of data, {size} bytes expected"))
+                        .at_offset(*pos)
+                })?
+                .to_vec();
             if version > 10 {
                 // '字节解密'
                 for (index, abyte) in bytes.iter_mut().enumerate() {
@@ -56,31 +71,36 @@ impl ManualMove {
             }
 
             *pos = new_pos;
-            bytes
+            Ok(bytes)
         };
 
-        let get_remark_size = |pos: &mut usize| {
-            let data = read_bytes(pos, std::mem::size_of::<u32>());
-            u32::from_le_bytes(data.try_into().unwrap()) as usize - keyrmksize
+        let get_remark_size = |pos: &mut usize| -> common::Result<usize> {
+            let offset = *pos;
+            let data = read_bytes(pos, std::mem::size_of::<u32>())?;
+            (u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize)
+                .checked_sub(keyrmksize)
+                .ok_or_else(|| {
+                    common::Error::Xqf(String::from("invalid remark size")).at_offset(offset)
+                })
         };
 
-        let get_data_remark = |pos: &mut usize| {
+        let get_data_remark = |pos: &mut usize| -> common::Result<(Vec<u8>, String)> {
             const DATASIZE: usize = 4;
-            let mut data = read_bytes(pos, DATASIZE);
+            let mut data = read_bytes(pos, DATASIZE)?;
             let mut remark_size = 0;
             if version <= 10 {
                 data[2] = (if data[2] & 0xF0 != 0 { 0x80 } else { 0 })
                     | (if data[2] & 0x0F != 0 { 0x40 } else { 0 });
-                remark_size = get_remark_size(pos);
+                remark_size = get_remark_size(pos)?;
             } else {
                 data[2] &= 0xE0;
                 if data[2] & 0x20 != 0 {
-                    remark_size = get_remark_size(pos);
+                    remark_size = get_remark_size(pos)?;
                 }
             }
 
             let remark = if remark_size > 0 {
-                GBK.decode(&read_bytes(pos, remark_size), DecoderTrap::Ignore)
+                GBK.decode(&read_bytes(pos, remark_size)?, DecoderTrap::Ignore)
                     .unwrap()
                     .replace("\r\n", "\n")
                     .trim()
@@ -89,12 +109,12 @@ impl ManualMove {
                 String::new()
             };
 
-            (data, remark)
+            Ok((data, remark))
         };
 
         let mut pos: usize = 1024;
         let root_move = amove::Move::root();
-        let (data, remark) = get_data_remark(&mut pos);
+        let (data, remark) = get_data_remark(&mut pos)?;
         root_move.set_remark(remark);
 
         if data[2] & 0x80 != 0 {
@@ -103,24 +123,30 @@ impl ManualMove {
             let mut is_other = false;
             // 当前棋子非根，或为根尚无后续棋子/当前棋子为根，且有后继棋子时，表明深度搜索已经回退到根，已经没有后续棋子了
             while pos < input.len() && (!before_move.is_root() || before_move.after_len() == 0) {
-                let (data, remark) = get_data_remark(&mut pos);
+                let offset = pos;
+                let (data, remark) = get_data_remark(&mut pos)?;
                 //# 一步棋的起点和终点有简单的加密计算，读入时需要还原
                 let fcolrow = __sub(data[0], (0x18 + keyxyf as usize) as u8);
                 let tcolrow = __sub(data[1], (0x20 + keyxyt as usize) as u8);
                 if fcolrow > 89 || tcolrow > 89 {
-                    assert!(false, "fcolrow > 89 || tcolrow > 89 ? ");
+                    return Err(
+                        common::Error::Xqf(format!("invalid move {fcolrow} -> {tcolrow}"))
+                            .at_offset(offset),
+                    );
                 }
 
                 let frow = (10 - 1 - fcolrow % 10) as usize;
                 let fcol = (fcolrow / 10) as usize;
                 let trow = (10 - 1 - tcolrow % 10) as usize;
                 let tcol = (tcolrow / 10) as usize;
-                let coord_pair = CoordPair::from_row_col(frow, fcol, trow, tcol).unwrap();
+                let coord_pair = CoordPair::from_row_col(frow, fcol, trow, tcol)
+                    .map_err(|err| err.at_offset(offset))?;
                 let tag = data[2];
                 let has_next = (tag & 0x80) != 0;
                 let has_other = (tag & 0x40) != 0;
                 if before_move.coordpair == coord_pair {
-                    assert!(false, "Error.");
+                    return Err(common::Error::Xqf(String::from("move repeats its parent"))
+                        .at_offset(offset));
                 }
 
                 if is_other {
@@ -142,7 +168,7 @@ impl ManualMove {
             }
         }
 
-        ManualMove::from(fen, root_move)
+        Ok(ManualMove::from(fen, root_move))
         // List<Move> allMoves = RootMove.AllAfterMoves;
         // allMoves.Insert(0, RootMove);
         // allMoves.ForEach(move
@@ -150,44 +176,52 @@ impl ManualMove {
         //         => !GetBoardWith(move.Before).BitBoard.CanMove(move.CoordPair)));
     }
 
-    pub fn from_bin(fen: &str, input: &mut &[u8]) -> Self {
+    // version不小于2时各着带有结构化注释
+    pub fn from_bin(fen: &str, input: &mut &[u8], version: u8) -> common::Result<Self> {
+        let read_move_fields = |input: &mut &[u8], amove: &Rc<amove::Move>| {
+            amove.set_remark(common::read_string(input)?);
+            if version >= 2 {
+                amove.set_annotation(amove::Annotation::read_bytes(input)?);
+            }
+
+            common::read_be_u32(input).map(|after_num| after_num as usize)
+        };
+
         let root_move = amove::Move::root();
-        let remark = common::read_string(input);
-        let after_num = common::read_be_u32(input) as usize;
-        root_move.set_remark(remark);
+        let after_num = read_move_fields(input, &root_move)?;
 
         let mut move_after_num_deque: VecDeque<(Rc<amove::Move>, usize)> = VecDeque::new();
         move_after_num_deque.push_back((root_move.clone(), after_num));
         while move_after_num_deque.len() > 0 {
             let (before_move, before_after_num) = move_after_num_deque.pop_front().unwrap();
             for _ in 0..before_after_num {
-                let coordpair = common::read_coordpair(input);
-                let remark = common::read_string(input);
-                let after_num = common::read_be_u32(input) as usize;
-
-                let amove = before_move.append(coordpair, remark);
+                let coordpair = common::read_coordpair(input)?;
+                let amove = before_move.append(coordpair, String::new());
+                let after_num = read_move_fields(input, &amove)?;
                 if after_num > 0 {
                     move_after_num_deque.push_back((amove, after_num));
                 }
             }
         }
 
-        ManualMove::from(fen, root_move)
+        Ok(ManualMove::from(fen, root_move))
     }
 
-    pub fn get_bytes(&self) -> Vec<u8> {
+    pub fn get_bytes(&self) -> common::Result<Vec<u8>> {
         let mut result = Vec::new();
 
         common::write_string(&mut result, &self.root_move.remark());
+        self.root_move.annotation().write_bytes(&mut result)?;
         common::write_be_u32(&mut result, self.root_move.after_len() as u32);
         for amove in self.get_all_after_moves() {
             common::write_coordpair(&mut result, &amove.coordpair);
 
             common::write_string(&mut result, &amove.remark());
+            amove.annotation().write_bytes(&mut result)?;
             common::write_be_u32(&mut result, amove.after_len() as u32);
         }
 
-        result
+        Ok(result)
     }
 
     pub fn from_string(
@@ -203,15 +237,17 @@ impl ManualMove {
             _ => r"(?:\(\d,\d\)){2}",
         };
         let remark_num_pattern = r"(?:\{([\s\S]+?)\})?(?:\((\d+)\))?\n";
-        let amove_pattern = format!("({pgn_pattern}){remark_num_pattern}");
+        let amove_pattern = format!(r"({pgn_pattern})((?: \$\d+)*){remark_num_pattern}");
         let root_move_re = regex::Regex::new(&("^".to_string() + remark_num_pattern)).unwrap();
         let amove_re = regex::Regex::new(&amove_pattern).unwrap();
         // println!("{}\n{}", manual_move_str, remark_num_pattern);
 
+        // 行号自1起算
+        let get_line = |pos: usize| manual_move_str[..pos].matches('\n').count() + 1;
         let root_move = amove::Move::root();
         if let Some(root_caps) = root_move_re.captures(manual_move_str) {
             if let Some(remark) = root_caps.at(1) {
-                root_move.set_remark(remark.to_string());
+                set_remark_annotation(&root_move, "", remark);
             }
 
             if let Some(after_num_str) = root_caps.at(2) {
@@ -224,21 +260,32 @@ impl ManualMove {
                         let (before_move, before_after_num) =
                             move_after_num_deque.pop_front().unwrap();
                         for _ in 0..before_after_num {
-                            let caps = caps_iter.next().ok_or(common::ParseError::StringParse)?;
-                            let coordpair =
-                                CoordPair::from_string(caps.at(1).unwrap(), record_type)?;
-                            let remark = if let Some(remark) = caps.at(2) {
-                                remark.to_string()
-                            } else {
-                                String::new()
-                            };
-                            let after_num: usize = if let Some(after_num_str) = caps.at(3) {
+                            let caps = caps_iter.next().ok_or_else(|| {
+                                common::Error::Text(format!(
+                                    "{before_after_num} moves expected after a move"
+                                ))
+                                .at_line(get_line(manual_move_str.len()))
+                            })?;
+                            let (start, _) = caps.pos(0).unwrap_or_default();
+                            let coordpair = caps
+                                .at(1)
+                                .ok_or(common::Error::StringParse)
+                                .and_then(|coordpair_str| {
+                                    CoordPair::from_string(coordpair_str, record_type)
+                                })
+                                .map_err(|err| err.at_line(get_line(start)))?;
+                            let after_num: usize = if let Some(after_num_str) = caps.at(4) {
                                 after_num_str.parse().unwrap_or(0)
                             } else {
                                 0
                             };
 
-                            let amove = before_move.append(coordpair, remark);
+                            let amove = before_move.append(coordpair, String::new());
+                            set_remark_annotation(
+                                &amove,
+                                caps.at(2).unwrap_or_default(),
+                                caps.at(3).unwrap_or_default(),
+                            );
                             if after_num > 0 {
                                 move_after_num_deque.push_back((amove, after_num));
                             }
@@ -251,6 +298,226 @@ impl ManualMove {
         Ok(ManualMove::from(fen, root_move))
     }
 
+    pub fn from_json(fen: &str, root_json: &amove::MoveJson) -> common::Result<Self> {
+        fn append_json(amove: &Rc<amove::Move>, move_json: &amove::MoveJson) -> common::Result<()> {
+            for after_json in &move_json.children {
+                // 坐标缺失时以ICCS着法替代
+                let coordpair = match after_json.coordpair {
+                    Some([frow, fcol, trow, tcol]) => {
+                        CoordPair::from_row_col(frow, fcol, trow, tcol)?
+                    }
+                    None => CoordPair::from_string(&after_json.iccs, coord::RecordType::PgnIccs)?,
+                };
+
+                let after_move = amove.append(coordpair, after_json.remark.clone());
+                if let Some(annotation_json) = &after_json.annotation {
+                    after_move.set_annotation(amove::Annotation::from_json(annotation_json)?);
+                }
+                append_json(&after_move, after_json)?;
+            }
+
+            Ok(())
+        }
+
+        let root_move = amove::Move::root();
+        root_move.set_remark(root_json.remark.clone());
+        if let Some(annotation_json) = &root_json.annotation {
+            root_move.set_annotation(amove::Annotation::from_json(annotation_json)?);
+        }
+        append_json(&root_move, root_json)?;
+
+        Ok(ManualMove::from(fen, root_move))
+    }
+
+    pub fn to_json(&self) -> amove::MoveJson {
+        fn to_move_json(board: &mut board::Board, amove: &Rc<amove::Move>) -> amove::MoveJson {
+            let mut children = Vec::new();
+            for after_move in amove.after() {
+                let (frow, fcol, trow, tcol) = after_move.coordpair.row_col();
+                let zh = board.get_zhstr_from_coordpair(&after_move.coordpair);
+                board.do_move(&after_move);
+                let mut after_json = to_move_json(board, &after_move);
+                board.undo_move(&after_move);
+
+                after_json.coordpair = Some([frow, fcol, trow, tcol]);
+                after_json.iccs = after_move.coordpair.to_string(coord::RecordType::PgnIccs);
+                after_json.zh = zh;
+                children.push(after_json);
+            }
+
+            amove::MoveJson {
+                remark: amove.remark(),
+                annotation: amove.annotation().to_json(),
+                children,
+                ..Default::default()
+            }
+        }
+
+        to_move_json(&mut self.board.clone(), &self.root_move)
+    }
+
+    // DhtmlXQ着法：每步四位数字"起点列起点行终点列终点行"
+    fn coordpairs_from_dhtmlxq(movelist: &str) -> common::Result<Vec<CoordPair>> {
+        let digits: Vec<usize> = movelist
+            .trim()
+            .chars()
+            .map(|ch| ch.to_digit(10).map(|digit| digit as usize))
+            .collect::<Option<Vec<usize>>>()
+            .ok_or_else(|| common::Error::DhtmlXq(format!("non-digit in movelist {movelist}")))?;
+        if !digits.len().is_multiple_of(4) {
+            return Err(common::Error::DhtmlXq(format!(
+                "movelist length not a multiple of 4: {movelist}"
+            )));
+        }
+
+        digits
+            .chunks(4)
+            .map(|xys| CoordPair::from_row_col(xys[1], xys[0], xys[3], xys[2]))
+            .collect()
+    }
+
+    fn coordpair_to_dhtmlxq(coordpair: &CoordPair) -> String {
+        let (frow, fcol, trow, tcol) = coordpair.row_col();
+        format!("{fcol}{frow}{tcol}{trow}")
+    }
+
+    // blocks: DhtmlXQ_之后的标签名->内容，如"movelist"、"move_0_3_1"、"comment1_4"
+    pub fn from_dhtmlxq(fen: &str, blocks: &BTreeMap<String, String>) -> common::Result<Self> {
+        let get_remark = |key: String| {
+            blocks
+                .get(&key)
+                .map(|remark| remark.replace("||", "\n").trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let root_move = amove::Move::root();
+        root_move.set_remark(get_remark(String::from("comment0")));
+
+        // 分支号->(父分支号, 起始步数, 各步着法)，步数自1起算
+        let mut branchs: BTreeMap<usize, (usize, usize, Vec<Rc<amove::Move>>)> = BTreeMap::new();
+        branchs.insert(0, (0, 1, Vec::new()));
+        let mut branch_movelists = vec![(0, blocks.get("movelist").cloned().unwrap_or_default())];
+        for (key, movelist) in blocks {
+            let nums: Vec<usize> = key
+                .strip_prefix("move_")
+                .unwrap_or("")
+                .split('_')
+                .filter_map(|num| num.parse().ok())
+                .collect();
+            if let [parent, step, branch] = nums[..] {
+                if branch > 0 && step > 0 {
+                    branchs.insert(branch, (parent, step, Vec::new()));
+                    branch_movelists.push((branch, movelist.clone()));
+                }
+            }
+        }
+        branch_movelists.sort();
+
+        fn get_before_move(
+            branchs: &BTreeMap<usize, (usize, usize, Vec<Rc<amove::Move>>)>,
+            root_move: &Rc<amove::Move>,
+            branch: usize,
+            step: usize,
+        ) -> Option<Rc<amove::Move>> {
+            if step == 0 {
+                return Some(root_move.clone());
+            }
+
+            let (parent, start_step, moves) = branchs.get(&branch)?;
+            if step >= *start_step {
+                moves.get(step - start_step).cloned()
+            } else if branch == 0 {
+                None
+            } else {
+                get_before_move(branchs, root_move, *parent, step)
+            }
+        }
+
+        for (branch, movelist) in branch_movelists {
+            let (parent, start_step, _) = branchs[&branch];
+            let mut before_move = get_before_move(&branchs, &root_move, parent, start_step - 1)
+                .ok_or_else(|| {
+                    common::Error::DhtmlXq(format!("branch {branch} has no move to follow"))
+                })?;
+            let mut moves = Vec::new();
+            for (index, coordpair) in Self::coordpairs_from_dhtmlxq(&movelist)?
+                .into_iter()
+                .enumerate()
+            {
+                let step = start_step + index;
+                let remark = get_remark(if branch == 0 {
+                    format!("comment{step}")
+                } else {
+                    format!("comment{branch}_{step}")
+                });
+                before_move = before_move.append(coordpair, remark);
+                moves.push(before_move.clone());
+            }
+
+            branchs.get_mut(&branch).unwrap().2 = moves;
+        }
+
+        Ok(ManualMove::from(fen, root_move))
+    }
+
+    pub fn to_dhtmlxq(&self) -> BTreeMap<String, String> {
+        let mut blocks = BTreeMap::new();
+        let to_remark = |amove: &Rc<amove::Move>| amove.remark().replace('\n', "||");
+        if !self.root_move.remark().is_empty() {
+            blocks.insert(String::from("comment0"), to_remark(&self.root_move));
+        }
+
+        // (父分支号, 起始步数, 起始着法)，主线为0号分支
+        let mut branch_deque: VecDeque<(usize, usize, Rc<amove::Move>)> = VecDeque::new();
+        if let Some(first_move) = self.root_move.after().first() {
+            branch_deque.push_back((0, 1, first_move.clone()));
+        }
+
+        let mut branch_count = 0;
+        while let Some((parent, start_step, start_move)) = branch_deque.pop_front() {
+            let branch = branch_count;
+            branch_count += 1;
+
+            let mut movelist = String::new();
+            let mut amove = start_move.clone();
+            let mut step = start_step;
+            loop {
+                movelist.push_str(&Self::coordpair_to_dhtmlxq(&amove.coordpair));
+                if !amove.remark().is_empty() {
+                    let key = if branch == 0 {
+                        format!("comment{step}")
+                    } else {
+                        format!("comment{branch}_{step}")
+                    };
+                    blocks.insert(key, to_remark(&amove));
+                }
+
+                // 本分支着法为首着时，其余同级着法另起分支
+                let before_after = amove.before().unwrap().after();
+                if Rc::ptr_eq(&before_after[0], &amove) {
+                    for other_move in &before_after[1..] {
+                        branch_deque.push_back((branch, step, other_move.clone()));
+                    }
+                }
+
+                match amove.after().first() {
+                    Some(after_move) => amove = after_move.clone(),
+                    None => break,
+                }
+                step += 1;
+            }
+
+            let key = if branch == 0 {
+                String::from("movelist")
+            } else {
+                format!("move_{parent}_{start_step}_{branch}")
+            };
+            blocks.insert(key, movelist);
+        }
+
+        blocks
+    }
+
     fn get_all_after_moves(&self) -> Vec<Rc<amove::Move>> {
         fn enqueue_after(move_deque: &mut VecDeque<Rc<amove::Move>>, amove: &Rc<amove::Move>) {
             for bmove in amove.after() {
@@ -282,6 +549,77 @@ impl ManualMove {
         zorbist_evaluation
     }
 
+    // 着法执行后的局面哈希(轮走方为对方)
+    fn get_key_lock(&self, amove: &Rc<amove::Move>) -> (u64, u64) {
+        let mut board = self.board.to_move_before(amove);
+        let color = board
+            .bit_board()
+            .get_color(amove.coordpair.from_coord.index())
+            .unwrap();
+        board.do_move(amove);
+
+        board.bit_board().get_key_lock(piece::other_color(color))
+    }
+
+    // 合并起始局面相同的棋谱：相同着法路径合一，不同备注追加，
+    // by_zobrist为真时，到达已有局面(变着转换)的后续着法合并至已有着法之下
+    pub fn merge(&self, other: &ManualMove, by_zobrist: bool) -> common::Result<()> {
+        if self.board != other.board {
+            return Err(common::Error::BoardNotMatch);
+        }
+
+        let mut key_moves: HashMap<(u64, u64), Rc<amove::Move>> = HashMap::new();
+        if by_zobrist {
+            for amove in self.get_all_after_moves() {
+                key_moves.entry(self.get_key_lock(&amove)).or_insert(amove);
+            }
+        }
+
+        self.merge_move(&self.root_move, &other.root_move, by_zobrist, &mut key_moves);
+        Ok(())
+    }
+
+    fn merge_move(
+        &self,
+        to_move: &Rc<amove::Move>,
+        from_move: &Rc<amove::Move>,
+        by_zobrist: bool,
+        key_moves: &mut HashMap<(u64, u64), Rc<amove::Move>>,
+    ) {
+        to_move.merge_remark(from_move.remark());
+        self.merge_after_moves(to_move, from_move, by_zobrist, key_moves);
+    }
+
+    fn merge_after_moves(
+        &self,
+        to_move: &Rc<amove::Move>,
+        from_move: &Rc<amove::Move>,
+        by_zobrist: bool,
+        key_moves: &mut HashMap<(u64, u64), Rc<amove::Move>>,
+    ) {
+        for from_after in from_move.after() {
+            if let Some(to_after) = to_move.find_after(&from_after.coordpair) {
+                self.merge_move(&to_after, &from_after, by_zobrist, key_moves);
+                continue;
+            }
+
+            let to_after = to_move.append(from_after.coordpair, String::new());
+            if by_zobrist {
+                let key_lock = self.get_key_lock(&to_after);
+                if let Some(same_move) = key_moves.get(&key_lock).cloned() {
+                    // 变着转换：本着保留，后续着法并入已有局面的着法之下
+                    to_after.merge_remark(from_after.remark());
+                    self.merge_after_moves(&same_move, &from_after, by_zobrist, key_moves);
+                    continue;
+                }
+
+                key_moves.insert(key_lock, to_after.clone());
+            }
+
+            self.merge_move(&to_after, &from_after, by_zobrist, key_moves);
+        }
+    }
+
     pub fn to_string(&self, record_type: coord::RecordType) -> String {
         let mut reslut = self.root_move.to_string(record_type, &self.board);
         for amove in self.get_all_after_moves() {
@@ -292,6 +630,16 @@ impl ManualMove {
     }
 }
 
+// 文本中的$n及注释命令转为结构化注释，其余为备注
+fn set_remark_annotation(amove: &Rc<amove::Move>, nags_str: &str, remark: &str) {
+    let mut annotation = amove::Annotation {
+        nags: amove::Annotation::parse_nags(nags_str),
+        ..Default::default()
+    };
+    amove.set_remark(annotation.take_commands(remark));
+    amove.set_annotation(annotation);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -302,4 +650,75 @@ mod tests {
 
         assert_eq!("\n", manual_move.to_string(coord::RecordType::Txt));
     }
+
+    #[test]
+    fn test_json() {
+        let manual_move_str = "{开局}(1)\n(7,7)(7,4) $1{[%eval 0.30,8] 中炮}(2)\n(0,7)(2,6)\n(0,1)(2,2)\n";
+        let manual_move =
+            ManualMove::from_string(board::FEN, manual_move_str, coord::RecordType::Txt).unwrap();
+        let move_json = manual_move.to_json();
+        assert_eq!("炮二平五", move_json.children[0].zh);
+        assert_eq!("H7E7", move_json.children[0].iccs);
+        let annotation_json = move_json.children[0].annotation.as_ref().unwrap();
+        assert_eq!((vec![1], Some(30)), (annotation_json.nags.clone(), annotation_json.score));
+        assert!(move_json.children[0].children[0].annotation.is_none());
+
+        let json_string = serde_json::to_string(&move_json).unwrap();
+        let move_json: amove::MoveJson = serde_json::from_str(&json_string).unwrap();
+        let manual_move = ManualMove::from_json(board::FEN, &move_json).unwrap();
+        assert_eq!(
+            manual_move_str,
+            manual_move.to_string(coord::RecordType::Txt)
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let from_txt = |manual_move_str| {
+            ManualMove::from_string(board::FEN, manual_move_str, coord::RecordType::Txt).unwrap()
+        };
+
+        let manual_move = from_txt("(1)\n(9,1)(7,2){红马}(1)\n(0,1)(2,2)\n");
+        let other = from_txt("{开局}(1)\n(9,1)(7,2){跳马}(2)\n(0,1)(2,2){对马}\n(0,7)(2,6)\n");
+        manual_move.merge(&other, false).unwrap();
+        assert_eq!(
+            "{开局}(1)\n(9,1)(7,2){红马\n跳马}(2)\n(0,1)(2,2){对马}\n(0,7)(2,6)\n",
+            manual_move.to_string(coord::RecordType::Txt)
+        );
+
+        let manual_move = from_txt("(1)\n(9,1)(7,2)(1)\n(0,1)(2,2)(1)\n(9,7)(7,6)\n");
+        let other = from_txt("(1)\n(9,7)(7,6)(1)\n(0,1)(2,2)(1)\n(9,1)(7,2)(1)\n(0,7)(2,6)\n");
+        manual_move.merge(&other, true).unwrap();
+        assert_eq!(
+            "(2)\n(9,1)(7,2)(1)\n(9,7)(7,6)(1)\n(0,1)(2,2)(1)\n(0,1)(2,2)(1)\n(9,7)(7,6)(1)\n(9,1)(7,2)\n(0,7)(2,6)\n",
+            manual_move.to_string(coord::RecordType::Txt)
+        );
+
+        let other = ManualMove::from(
+            "5a3/4ak2r/6R2/8p/9/9/9/B4N2B/4K4/3c5",
+            amove::Move::root(),
+        );
+        assert!(manual_move.merge(&other, false).is_err());
+    }
+
+    #[test]
+    fn test_dhtmlxq() {
+        let manual_move_str =
+            "{开局}(1)\n(7,7)(7,4){中炮}(2)\n(0,7)(2,6)(1)\n(0,1)(2,2){另一路}(1)\n(9,7)(7,6)(2)\n(9,1)(7,2)\n(9,8)(9,7)\n(8,7)(8,4)\n";
+        let manual_move =
+            ManualMove::from_string(board::FEN, manual_move_str, coord::RecordType::Txt).unwrap();
+        let blocks = manual_move.to_dhtmlxq();
+        // 每着四位数字，依次为起点列、行及终点列、行
+        assert_eq!("7747706279678979", blocks["movelist"]);
+        assert_eq!("10221927", blocks["move_0_2_1"]);
+        assert_eq!("7848", blocks["move_0_4_2"]);
+        assert_eq!("另一路", blocks["comment1_2"]);
+        assert_eq!("中炮", blocks["comment1"]);
+
+        let manual_move = ManualMove::from_dhtmlxq(board::FEN, &blocks).unwrap();
+        assert_eq!(
+            manual_move_str,
+            manual_move.to_string(coord::RecordType::Txt)
+        );
+    }
 }
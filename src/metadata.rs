@@ -0,0 +1,460 @@
+#![allow(dead_code)]
+
+use crate::common;
+use crate::info;
+use crate::manual::{self, InfoKey};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// 常见的地区及单位名称，用于自棋手名称中分离出所属地区
+const REGIONS: [&str; 56] = [
+    "北京",
+    "天津",
+    "上海",
+    "重庆",
+    "河北",
+    "山西",
+    "辽宁",
+    "吉林",
+    "黑龙江",
+    "江苏",
+    "浙江",
+    "安徽",
+    "福建",
+    "江西",
+    "山东",
+    "河南",
+    "湖北",
+    "湖南",
+    "广东",
+    "海南",
+    "四川",
+    "贵州",
+    "云南",
+    "陕西",
+    "甘肃",
+    "青海",
+    "台湾",
+    "内蒙古",
+    "广西",
+    "西藏",
+    "宁夏",
+    "新疆",
+    "香港",
+    "澳门",
+    "大连",
+    "沈阳",
+    "哈尔滨",
+    "青岛",
+    "厦门",
+    "深圳",
+    "广州",
+    "杭州",
+    "南京",
+    "成都",
+    "武汉",
+    "火车头",
+    "煤矿",
+    "农协",
+    "石化",
+    "前卫",
+    "中华台北",
+    "新加坡",
+    "马来西亚",
+    "越南",
+    "菲律宾",
+    "泰国",
+];
+
+// 标题中的结果用语对应的Win信息
+fn result_from_word(word: &str) -> Option<&'static str> {
+    match word {
+        "胜" | "先胜" => Some("红胜"),
+        "负" | "先负" => Some("黑胜"),
+        "和" | "先和" => Some("和棋"),
+        _ => None,
+    }
+}
+
+// 将各种结果写法归为"红胜"、"黑胜"、"和棋"
+pub fn normalize_result(result_str: &str) -> Option<&'static str> {
+    match info::GameResult::from_string(result_str) {
+        Some(result) if result.win != info::Win::Unknown => Some(result.win.name()),
+        _ => result_from_word(result_str.trim()),
+    }
+}
+
+// 自文本开头解析日期，返回ISO格式的日期及其后的文本
+fn parse_date(date_str: &str) -> Option<(String, &str)> {
+    info::Date::parse_prefix(date_str).map(|(date, rest)| (date.to_string(), rest))
+}
+
+// 将"1993.4.27"、"1993年4月27日"、"1993/04/27"等归为ISO格式
+pub fn normalize_date(date_str: &str) -> Option<String> {
+    info::Date::from_string(date_str).map(|date| date.to_string())
+}
+
+// 分离名称前的地区，余下部分至少两个字才视为姓名
+pub fn split_region(player: &str) -> (Option<String>, String) {
+    let player = player.trim();
+    let region = REGIONS
+        .iter()
+        .filter(|region| player.starts_with(*region) && player[region.len()..].chars().count() >= 2)
+        .max_by_key(|region| region.len());
+    match region {
+        Some(region) => (
+            Some(region.to_string()),
+            player[region.len()..].trim().to_string(),
+        ),
+        None => (None, player.to_string()),
+    }
+}
+
+// 棋手别名表：别名 -> 标准名
+#[derive(Clone, Debug, Default)]
+pub struct AliasTable {
+    aliases: BTreeMap<String, String>,
+}
+
+impl AliasTable {
+    pub fn new() -> Self {
+        AliasTable::default()
+    }
+
+    // 每行格式为"标准名=别名1,别名2"，#起始的行为注释
+    pub fn from_string(table_str: &str) -> Self {
+        let mut table = AliasTable::new();
+        for line in table_str.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some((name, aliases)) = line.split_once('=') {
+                for alias in aliases.split([',', '，', '、']) {
+                    table.add(alias, name);
+                }
+            }
+        }
+
+        table
+    }
+
+    pub fn add(&mut self, alias: &str, name: &str) {
+        let (alias, name) = (alias.trim(), name.trim());
+        if !alias.is_empty() && !name.is_empty() && alias != name {
+            self.aliases.insert(alias.to_string(), name.to_string());
+        }
+    }
+
+    pub fn canonical(&self, name: &str) -> String {
+        self.aliases
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+}
+
+// 一方棋手
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Player {
+    pub name: String,
+    pub region: Option<String>,
+}
+
+// 规范化后的对局信息
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub red: Option<Player>,
+    pub black: Option<Player>,
+    pub result: Option<String>,
+    pub date: Option<String>,
+    pub venue: Option<String>,
+    pub event: Option<String>,
+}
+
+impl Metadata {
+    // 解析"北京张强 (和) 上海胡荣华 (1993.4.27于南京)"、"张强 先胜 胡荣华"等形式的标题或文件名
+    pub fn from_title(title: &str) -> Option<Self> {
+        let title_re = regex::Regex::new(concat!(
+            r"^[\s－—-]*(\S+?)\s*",
+            r"(?:[(（](先胜|先和|先负|胜|和|负|对)[)）]|(先胜|先和|先负|[Vv][Ss]\.?)|\s(胜|和|负|对)\s)",
+            r"\s*(\S+?)\s*(?:[(（]([^)）]*)[)）])?\s*$"
+        ))
+        .unwrap();
+        let caps = title_re.captures(title.trim())?;
+        let word = (2..=4).find_map(|index| caps.at(index)).unwrap_or_default();
+        let player = |index| {
+            let (region, name) = split_region(caps.at(index).unwrap_or_default());
+            Player { name, region }
+        };
+        let mut metadata = Metadata {
+            red: Some(player(1)),
+            black: Some(player(5)),
+            result: result_from_word(word).map(String::from),
+            ..Default::default()
+        };
+
+        // 括号内为日期及地点，如"1993.4.27于南京"
+        if let Some(extra) = caps.at(6) {
+            let rest = match parse_date(extra) {
+                Some((date, rest)) => {
+                    metadata.date = Some(date);
+                    Some(rest)
+                }
+                None => None,
+            };
+            let venue = rest
+                .or_else(|| extra.trim().strip_prefix('于'))
+                .map(|venue| {
+                    venue
+                        .trim()
+                        .trim_start_matches(['于', '在'])
+                        .trim_end_matches("举行")
+                })
+                .filter(|venue| !venue.is_empty());
+            metadata.venue = venue.map(String::from);
+        }
+
+        Some(metadata)
+    }
+
+    // 以other补全缺失的项
+    fn fill(&mut self, other: Metadata) {
+        self.red = self.red.take().or(other.red);
+        self.black = self.black.take().or(other.black);
+        self.result = self.result.take().or(other.result);
+        self.date = self.date.take().or(other.date);
+        self.venue = self.venue.take().or(other.venue);
+        self.event = self.event.take().or(other.event);
+    }
+}
+
+pub struct Normalizer {
+    aliases: AliasTable,
+}
+
+impl Normalizer {
+    pub fn new(aliases: AliasTable) -> Self {
+        Normalizer { aliases }
+    }
+
+    fn canonical_player(&self, player: Player) -> Player {
+        // 带地区的全称亦可列入别名表
+        let full_name = format!(
+            "{}{}",
+            player.region.clone().unwrap_or_default(),
+            player.name
+        );
+        let name = match self.aliases.canonical(&full_name) {
+            canonical if canonical != full_name => canonical,
+            _ => self.aliases.canonical(&player.name),
+        };
+
+        Player { name, ..player }
+    }
+
+    // 依次自信息项、标题及文件名解析对局信息，规范化后写回信息项，
+    // 校验不通过的项保持原值，其错误一并返回
+    pub fn normalize(
+        &self,
+        manual: &mut manual::Manual,
+        file_name: Option<&str>,
+    ) -> (Metadata, Vec<common::Error>) {
+        let get_info = |key: InfoKey| {
+            manual
+                .get_info(key)
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        };
+        let get_player = |key: InfoKey, region_key: InfoKey| {
+            get_info(key).map(|player| {
+                let (region, name) = split_region(player);
+                Player {
+                    name,
+                    region: get_info(region_key).map(String::from).or(region),
+                }
+            })
+        };
+
+        let mut metadata = Metadata {
+            red: get_player(InfoKey::Red, InfoKey::RedTeam),
+            black: get_player(InfoKey::Black, InfoKey::BlackTeam),
+            result: get_info(InfoKey::Win)
+                .and_then(normalize_result)
+                .map(String::from),
+            date: get_info(InfoKey::Date).and_then(|date| parse_date(date).map(|(date, _)| date)),
+            venue: get_info(InfoKey::Site).map(String::from),
+            event: get_info(InfoKey::Game).map(String::from),
+        };
+        if let Some(title_metadata) = get_info(InfoKey::Title).and_then(Metadata::from_title) {
+            metadata.fill(title_metadata);
+        }
+        let file_stem = file_name.map(|file_name| {
+            let file_name = file_name.rsplit(['/', '\\']).next().unwrap_or(file_name);
+            file_name
+                .rsplit_once('.')
+                .map_or(file_name, |(stem, _)| stem)
+        });
+        if let Some(file_metadata) = file_stem.and_then(Metadata::from_title) {
+            metadata.fill(file_metadata);
+        }
+        metadata.red = metadata.red.map(|player| self.canonical_player(player));
+        metadata.black = metadata.black.map(|player| self.canonical_player(player));
+
+        let mut errors = Vec::new();
+        let mut set_info = |key: InfoKey, value: &Option<String>| {
+            if let Some(value) = value {
+                if let Err(err) = manual.set_info(key, value) {
+                    errors.push(err);
+                }
+            }
+        };
+        for (player, key, region_key) in [
+            (&metadata.red, InfoKey::Red, InfoKey::RedTeam),
+            (&metadata.black, InfoKey::Black, InfoKey::BlackTeam),
+        ] {
+            if let Some(player) = player {
+                set_info(key, &Some(player.name.clone()));
+                set_info(region_key, &player.region);
+            }
+        }
+        set_info(InfoKey::Win, &metadata.result);
+        set_info(InfoKey::Date, &metadata.date);
+        set_info(InfoKey::Site, &metadata.venue);
+        set_info(InfoKey::Game, &metadata.event);
+
+        (metadata, errors)
+    }
+}
+
+// 按棋手及赛事索引棋谱
+#[derive(Debug, Default)]
+pub struct MetadataIndex {
+    pub players: BTreeMap<String, Vec<String>>,
+    pub events: BTreeMap<String, Vec<String>>,
+}
+
+impl MetadataIndex {
+    pub fn new() -> Self {
+        MetadataIndex::default()
+    }
+
+    pub fn add(&mut self, file_name: &str, metadata: &Metadata) {
+        for player in [&metadata.red, &metadata.black].into_iter().flatten() {
+            self.players
+                .entry(player.name.clone())
+                .or_default()
+                .push(file_name.to_string());
+        }
+        if let Some(event) = &metadata.event {
+            self.events
+                .entry(event.clone())
+                .or_default()
+                .push(file_name.to_string());
+        }
+    }
+}
+
+impl fmt::Display for MetadataIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (title, entries) in [("棋手", &self.players), ("赛事", &self.events)] {
+            writeln!(f, "{title}:")?;
+            for (name, file_names) in entries {
+                writeln!(f, "  {name} ({})", file_names.len())?;
+                for file_name in file_names {
+                    writeln!(f, "    {file_name}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_metadata() {
+        assert_eq!(
+            Some(String::from("1993-04-27")),
+            normalize_date("1993.4.27")
+        );
+        assert_eq!(
+            Some(String::from("2001-12-03")),
+            normalize_date("2001年12月3日")
+        );
+        assert_eq!(Some(String::from("1985-06")), normalize_date("1985/6"));
+        assert_eq!(None, normalize_date("1993.13.1"));
+        assert_eq!(Some("黑胜"), normalize_result("0-1"));
+        assert_eq!(
+            (Some(String::from("黑龙江")), String::from("赵国荣")),
+            split_region("黑龙江赵国荣")
+        );
+        assert_eq!((None, String::from("北京")), split_region("北京"));
+
+        let metadata =
+            Metadata::from_title("- 北京张强 (和) 上海胡荣华 (1993.4.27于南京)").unwrap();
+        assert_eq!(
+            Some(Player {
+                name: String::from("张强"),
+                region: Some(String::from("北京"))
+            }),
+            metadata.red
+        );
+        assert_eq!(
+            Some(String::from("胡荣华")),
+            metadata.black.map(|player| player.name)
+        );
+        assert_eq!(Some(String::from("和棋")), metadata.result);
+        assert_eq!(Some(String::from("1993-04-27")), metadata.date);
+        assert_eq!(Some(String::from("南京")), metadata.venue);
+        let metadata = Metadata::from_title("许银川 先胜 吕钦").unwrap();
+        assert_eq!(Some(String::from("红胜")), metadata.result);
+        // 开局名称不是对局标题
+        assert_eq!(None, Metadata::from_title("挺兵对卒底炮"));
+
+        let manual_str = "[Title: 挺兵对卒底炮]\n[Red: 北京张强]\n[Black: 上海胡荣华]\n[Date: 1993.4.27]\n[Game:  93全国象棋锦标赛 ]\n\n(1)\n(7,7)(7,4)\n";
+        let mut manual =
+            manual::Manual::read_from(manual_str.as_bytes(), coord::RecordType::Txt).unwrap();
+        let aliases = AliasTable::from_string("# 别名\n胡司令=胡荣华, 胡大师\n");
+        assert_eq!("胡司令", aliases.canonical("胡大师"));
+        let normalizer = Normalizer::new(aliases);
+        let (metadata, errors) = normalizer.normalize(
+            &mut manual,
+            Some("tests/xqf/- 北京张强 (和) 上海胡荣华 (1993.4.27于南京).xqf"),
+        );
+        assert!(errors.is_empty());
+        assert_eq!(Some(String::from("南京")), metadata.venue);
+        let info = manual.info();
+        assert_eq!("张强", info["Red"]);
+        assert_eq!("北京", info["RedTeam"]);
+        assert_eq!("胡司令", info["Black"]);
+        assert_eq!("1993-04-27", info["Date"]);
+        assert_eq!("和棋", info["Win"]);
+        assert_eq!("南京", info["Site"]);
+        assert_eq!("93全国象棋锦标赛", info["Game"]);
+
+        // 无法写回的项保持原值并报告错误
+        let manual_str = "[Site: ]\n\n(0)\n";
+        let mut manual =
+            manual::Manual::read_from(manual_str.as_bytes(), coord::RecordType::Txt).unwrap();
+        let (venue_metadata, errors) =
+            normalizer.normalize(&mut manual, Some("张强 和 胡荣华 (于南\n京).xqf"));
+        assert_eq!(Some(String::from("南\n京")), venue_metadata.venue);
+        assert_eq!(1, errors.len());
+        assert_eq!("invalid info: Site: 南\n京", errors[0].to_string());
+        assert_eq!(Some(""), manual.get_info(InfoKey::Site));
+        assert_eq!(Some("张强"), manual.get_info(InfoKey::Red));
+
+        let mut index = MetadataIndex::new();
+        index.add("a.xqf", &metadata);
+        assert_eq!(vec![String::from("a.xqf")], index.players["胡司令"]);
+        assert!(index
+            .to_string()
+            .contains("赛事:\n  93全国象棋锦标赛 (1)\n    a.xqf\n"));
+    }
+}
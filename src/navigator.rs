@@ -0,0 +1,300 @@
+#![allow(dead_code)]
+
+use crate::amove;
+use crate::annotator;
+use crate::board;
+use crate::board_text;
+use crate::coord;
+use crate::manual;
+use crate::piece;
+use crate::png;
+use crate::search;
+use std::rc::Rc;
+
+// 浏览棋谱：当前着法、局面及翻转状态
+pub struct Navigator {
+    manual: manual::Manual,
+    board: board::Board,
+    current: Rc<amove::Move>,
+    flipped: bool,
+}
+
+impl Navigator {
+    pub fn new(manual: manual::Manual) -> Self {
+        let board = *manual.manual_move().board();
+        let current = manual.manual_move().root_move();
+        Navigator {
+            manual,
+            board,
+            current,
+            flipped: false,
+        }
+    }
+
+    pub fn manual(&self) -> &manual::Manual {
+        &self.manual
+    }
+
+    pub fn info(&self) -> Vec<(String, String)> {
+        self.manual
+            .info()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    // 已走的着数
+    pub fn ply(&self) -> usize {
+        if self.current.is_root() {
+            0
+        } else {
+            self.current.before_moves().len() + 1
+        }
+    }
+
+    // 走下一着，variation为0时走主着，其余为变着序号
+    pub fn forward(&mut self, variation: usize) -> bool {
+        match self.current.after().get(variation) {
+            Some(amove) => {
+                self.board.do_move(amove);
+                self.current = amove.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn back(&mut self) -> bool {
+        match self.current.before() {
+            Some(before_move) => {
+                self.board.undo_move(&self.current);
+                self.current = before_move;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn to_start(&mut self) {
+        while self.back() {}
+    }
+
+    pub fn to_end(&mut self) {
+        while self.forward(0) {}
+    }
+
+    // 跳至第ply着，超出当前路径时沿主着前进；返回实际到达的着数
+    pub fn go_to(&mut self, ply: usize) -> usize {
+        while self.ply() > ply && self.back() {}
+        while self.ply() < ply && self.forward(0) {}
+
+        self.ply()
+    }
+
+    pub fn flip(&mut self) {
+        self.flipped = !self.flipped;
+    }
+
+    pub fn is_flipped(&self) -> bool {
+        self.flipped
+    }
+
+    pub fn remark(&self) -> String {
+        self.current.remark()
+    }
+
+    // 当前局面下可走的着法(中文纵线表示)，首个为主着
+    pub fn variations(&self) -> Vec<String> {
+        self.current
+            .after()
+            .iter()
+            .map(|amove| self.board.get_zhstr_from_coordpair(&amove.coordpair))
+            .collect()
+    }
+
+    // 自开局至当前的着法(中文纵线表示)
+    pub fn move_list(&self) -> Vec<String> {
+        if self.current.is_root() {
+            return Vec::new();
+        }
+
+        let mut board = *self.manual.manual_move().board();
+        let mut moves = self.current.before_moves();
+        moves.push(self.current.clone());
+        moves
+            .iter()
+            .map(|amove| {
+                let zhstr = board.get_zhstr_from_coordpair(&amove.coordpair);
+                board.do_move(amove);
+                zhstr
+            })
+            .collect()
+    }
+
+    pub fn board_string(&self) -> String {
+        let mut board = self.board;
+        if self.flipped {
+            board.to_change(coord::ChangeType::Rotate);
+        }
+
+        board.to_string()
+    }
+
+    // 以指定样式输出当前局面，翻转状态与options.flipped叠加
+    pub fn board_text(&self, options: &board_text::TextOptions) -> String {
+        let options = board_text::TextOptions {
+            flipped: options.flipped != self.flipped,
+            ..*options
+        };
+
+        board_text::render(&self.board, &options)
+    }
+
+    // 分析当前局面，候选变例插入为当前着法的后续着法
+    pub fn analyze(&self, options: &search::AnalysisOptions) -> search::Analysis {
+        let color = match self.board.pieces()[self.current.coordpair.to_coord.index()] {
+            piece::Piece::Some(color, _) if !self.current.is_root() => piece::other_color(color),
+            _ => self.manual.first_color(),
+        };
+
+        annotator::insert_analysis(&self.current, &self.board, color, options)
+    }
+
+    // 当前局面的PNG图，标记当前着法，翻转状态与options.bottom_color叠加
+    pub fn png(&self, options: &png::PngOptions) -> Vec<u8> {
+        let mut options = options.clone();
+        if !self.current.is_root() {
+            options.last_move = Some(self.current.coordpair);
+        }
+        if self.flipped {
+            options.bottom_color = piece::other_color(options.bottom_color);
+        }
+
+        png::to_png(&self.board, &options)
+    }
+
+    // 整屏内容：信息、局面、着法、备注及可选着法
+    pub fn render(&self) -> String {
+        let mut result = String::new();
+        for (key, value) in self.info() {
+            if !value.is_empty() && key != manual::InfoKey::FEN.name() {
+                result.push_str(&format!("[{key}: {value}]\n"));
+            }
+        }
+        result.push('\n');
+        result.push_str(&self.board_string());
+        result.push('\n');
+
+        // 黑方先走时首回合红方一着以省略号占位
+        let mut move_list = self.move_list();
+        if !move_list.is_empty() && self.manual.first_color() == piece::Color::Black {
+            move_list.insert(0, String::from("…"));
+        }
+        for (index, pair) in move_list.chunks(2).enumerate() {
+            result.push_str(&format!("{:3}. {}\n", index + 1, pair.join(" ")));
+        }
+        result.push_str(&format!("第{}着", self.ply()));
+        if self.flipped {
+            result.push_str("(已翻转)");
+        }
+        result.push('\n');
+
+        let remark = self.remark();
+        if !remark.is_empty() {
+            result.push_str(&format!("{{{remark}}}\n"));
+        }
+        let variations = self.variations();
+        if !variations.is_empty() {
+            let choices: Vec<String> = variations
+                .iter()
+                .enumerate()
+                .map(|(index, zhstr)| format!("{index}:{zhstr}"))
+                .collect();
+            result.push_str(&format!("下一着 {}\n", choices.join(" ")));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_navigator() {
+        let manual_string = "[Title: test]\n\n{开局}(1)\n(7,7)(7,4){中炮}(1)\n(0,7)(2,6)(2)\n(9,7)(7,6)(1)\n(9,1)(7,2)\n(0,1)(2,2)\n";
+        let manual =
+            manual::Manual::read_from(manual_string.as_bytes(), coord::RecordType::Txt).unwrap();
+        let mut navigator = Navigator::new(manual);
+        assert_eq!(0, navigator.ply());
+        assert_eq!("开局", navigator.remark());
+        assert_eq!(vec!["炮二平五"], navigator.variations());
+        assert!(!navigator.back());
+
+        assert!(navigator.forward(0));
+        assert!(navigator.forward(0));
+        assert_eq!(vec!["马二进三", "马八进七"], navigator.variations());
+        assert!(navigator.forward(1));
+        assert_eq!(
+            vec!["炮二平五", "马８进７", "马八进七"],
+            navigator.move_list()
+        );
+        assert!(!navigator.forward(0));
+        assert!(navigator.back());
+        assert_eq!(2, navigator.ply());
+
+        navigator.to_end();
+        assert_eq!(4, navigator.ply());
+        assert_eq!("马二进三", navigator.move_list()[2]);
+        assert_eq!(1, navigator.go_to(1));
+        assert_eq!("中炮", navigator.remark());
+        assert_eq!(4, navigator.go_to(9));
+        navigator.to_start();
+        assert_eq!(board::Board::new().to_string(), navigator.board_string());
+
+        navigator.flip();
+        assert!(navigator.board_string().starts_with("车马相仕帅仕相马车"));
+        let mut options = board_text::TextOptions::new(board_text::TextStyle::Ascii);
+        assert!(navigator.board_text(&options).starts_with("R N B A K"));
+        options.flipped = true;
+        assert!(navigator.board_text(&options).starts_with("r n b a k"));
+        let screen = navigator.render();
+        assert!(screen.starts_with("[Title: test]\n\n车马相仕帅仕相马车"));
+        assert!(screen.contains("第0着(已翻转)\n{开局}\n下一着 0:炮二平五\n"));
+
+        // 炮二平五之后由黑方走子，候选着法插入为变着
+        navigator.go_to(1);
+        let after_len = navigator.current.after_len();
+        let analysis = navigator.analyze(&search::AnalysisOptions::new(1, 2));
+        assert_eq!(2, analysis.lines.len());
+        for (index, line) in analysis.lines.iter().enumerate() {
+            let amove = navigator.current.find_after(&line.pv[0]).unwrap();
+            assert!(amove.remark().starts_with(&format!("候选{}", index + 1)));
+            assert!(matches!(
+                navigator.board.pieces()[line.pv[0].from_coord.index()],
+                piece::Piece::Some(piece::Color::Black, _)
+            ));
+        }
+        assert!(navigator.current.after_len() <= after_len + 2);
+
+        // 变着马八进七之后由黑方走子，候选变例插入于该变着之下
+        navigator.go_to(2);
+        assert!(navigator.forward(1));
+        let variation_move = navigator.current.clone();
+        assert_eq!(0, variation_move.after_len());
+        let analysis = navigator.analyze(&search::AnalysisOptions::new(1, 1));
+        let amove = variation_move.find_after(&analysis.lines[0].pv[0]).unwrap();
+        assert!(Rc::ptr_eq(&variation_move, &amove.before().unwrap()));
+        assert_eq!(1, variation_move.after_len());
+
+        // 黑方先走时的回合编号
+        let manual_string = "[FEN: 4k4/9/9/9/9/9/9/9/9/4K4 b - - 0 1]\n\n(1)\n(0,4)(1,4)(1)\n(9,4)(8,4)(1)\n(1,4)(0,4)\n";
+        let manual =
+            manual::Manual::read_from(manual_string.as_bytes(), coord::RecordType::Txt).unwrap();
+        let mut navigator = Navigator::new(manual);
+        navigator.to_end();
+        let screen = navigator.render();
+        assert!(screen.contains("  1. … 将５进１\n  2. 帅五进一 将５退１\n"));
+    }
+}
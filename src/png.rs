@@ -0,0 +1,788 @@
+#![allow(dead_code)]
+
+use crate::amove;
+use crate::board;
+use crate::coord::{self, Coord, CoordPair, COLCOUNT, ROWCOUNT};
+use crate::manual;
+use crate::piece;
+use crate::svg::CoordLabel;
+use std::rc::Rc;
+
+pub type Rgb = [u8; 3];
+
+// 配色
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub background: Rgb,
+    pub line: Rgb,
+    pub red: Rgb,
+    pub red_fill: Rgb,
+    pub black: Rgb,
+    pub black_fill: Rgb,
+    pub last_move: Rgb,
+    pub arrow: Rgb,
+}
+
+impl Theme {
+    // 木纹底色，同svg局面图
+    pub const WOOD: Theme = Theme {
+        background: [0xf0, 0xd9, 0xa0],
+        line: [0x5a, 0x3a, 0x1a],
+        red: [0xc8, 0x10, 0x2e],
+        red_fill: [0xfb, 0xea, 0xcb],
+        black: [0x20, 0x20, 0x20],
+        black_fill: [0xfb, 0xea, 0xcb],
+        last_move: [0x2a, 0x7f, 0xff],
+        arrow: [0x2e, 0x9c, 0x4a],
+    };
+
+    // 黑白印刷：红方白底黑字，黑方黑底白字
+    pub const PRINT: Theme = Theme {
+        background: [0xff, 0xff, 0xff],
+        line: [0x00, 0x00, 0x00],
+        red: [0x00, 0x00, 0x00],
+        red_fill: [0xff, 0xff, 0xff],
+        black: [0xff, 0xff, 0xff],
+        black_fill: [0x00, 0x00, 0x00],
+        last_move: [0x80, 0x80, 0x80],
+        arrow: [0x60, 0x60, 0x60],
+    };
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "wood" => Some(Theme::WOOD),
+            "print" => Some(Theme::PRINT),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PngOptions {
+    // 显示于棋盘下方的一方，与局面实际下方不同时翻转棋盘
+    pub bottom_color: piece::Color,
+    pub last_move: Option<CoordPair>,
+    pub arrows: Vec<CoordPair>,
+    pub coord_label: CoordLabel,
+    // 格距(像素)，图像宽为其10倍、高为其11倍
+    pub grid_size: usize,
+    pub theme: Theme,
+}
+
+const MINGRIDSIZE: usize = 16;
+
+impl PngOptions {
+    pub fn new() -> Self {
+        PngOptions {
+            bottom_color: piece::Color::Red,
+            last_move: None,
+            arrows: Vec::new(),
+            coord_label: CoordLabel::Chinese,
+            grid_size: 40,
+            theme: Theme::WOOD,
+        }
+    }
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 笔画字形：每字若干折线，坐标范围0~32
+type Glyph = &'static [&'static [(u8, u8)]];
+
+const GLYPHSIZE: f32 = 32.0;
+
+#[rustfmt::skip]
+const GLYPHS: &[(char, Glyph)] = &[
+    ('车', &[&[(4, 6), (28, 6)], &[(14, 0), (8, 16), (26, 16)], &[(4, 22), (28, 22)], &[(16, 8), (16, 32)]]),
+    ('車', &[&[(4, 2), (28, 2)], &[(6, 8), (26, 8), (26, 22), (6, 22), (6, 8)], &[(6, 15), (26, 15)], &[(4, 28), (28, 28)], &[(16, 0), (16, 32)]]),
+    ('马', &[&[(6, 4), (22, 4), (20, 14)], &[(10, 4), (8, 16), (26, 16), (24, 30), (20, 28)], &[(4, 24), (20, 22)]]),
+    ('馬', &[&[(8, 2), (8, 22)], &[(8, 2), (24, 2)], &[(8, 8), (22, 8)], &[(8, 14), (22, 14)], &[(16, 2), (16, 14)], &[(8, 22), (26, 22), (24, 30), (20, 28)], &[(8, 26), (6, 30)], &[(13, 26), (12, 30)], &[(18, 26), (18, 30)]]),
+    ('相', &[&[(2, 10), (14, 10)], &[(8, 2), (8, 32)], &[(8, 10), (2, 24)], &[(8, 12), (14, 20)], &[(18, 4), (18, 30)], &[(18, 4), (30, 4), (30, 30)], &[(18, 13), (30, 13)], &[(18, 22), (30, 22)], &[(18, 30), (30, 30)]]),
+    ('象', &[&[(12, 0), (8, 4)], &[(8, 4), (22, 4), (18, 8)], &[(8, 8), (24, 8), (24, 14), (8, 14), (8, 8)], &[(14, 14), (6, 20)], &[(8, 22), (16, 18), (18, 32), (14, 30)], &[(16, 22), (6, 30)], &[(18, 20), (28, 16)], &[(18, 22), (28, 32)]]),
+    ('仕', &[&[(10, 2), (2, 16)], &[(7, 10), (7, 32)], &[(14, 10), (30, 10)], &[(22, 2), (22, 30)], &[(16, 30), (28, 30)]]),
+    ('士', &[&[(4, 10), (28, 10)], &[(16, 2), (16, 28)], &[(8, 28), (24, 28)]]),
+    ('帅', &[&[(4, 6), (4, 20)], &[(10, 2), (10, 22), (6, 30)], &[(16, 10), (16, 26)], &[(16, 10), (30, 10), (30, 26), (28, 24)], &[(23, 2), (23, 32)]]),
+    ('将', &[&[(8, 2), (8, 32)], &[(2, 10), (4, 14)], &[(2, 24), (8, 18)], &[(18, 2), (14, 8)], &[(16, 5), (28, 5), (22, 14)], &[(18, 8), (24, 12)], &[(12, 18), (30, 18)], &[(24, 14), (24, 32), (20, 30)], &[(16, 22), (18, 26)]]),
+    ('炮', &[&[(2, 10), (4, 16)], &[(12, 8), (10, 14)], &[(8, 2), (8, 18), (2, 30)], &[(8, 18), (14, 30)], &[(20, 2), (16, 10)], &[(18, 6), (30, 6), (30, 20), (28, 22)], &[(20, 12), (26, 12), (26, 18), (20, 18)], &[(20, 12), (20, 28), (30, 28), (30, 24)]]),
+    ('砲', &[&[(2, 4), (14, 4)], &[(8, 4), (2, 18)], &[(6, 16), (14, 16), (14, 28), (6, 28), (6, 16)], &[(20, 2), (16, 10)], &[(18, 6), (30, 6), (30, 20), (28, 22)], &[(20, 12), (26, 12), (26, 18), (20, 18)], &[(20, 12), (20, 28), (30, 28), (30, 24)]]),
+    ('兵', &[&[(22, 2), (10, 6)], &[(10, 6), (10, 20)], &[(10, 12), (24, 12)], &[(18, 12), (18, 20)], &[(4, 20), (28, 20)], &[(12, 24), (6, 30)], &[(20, 24), (26, 30)]]),
+    ('卒', &[&[(16, 0), (16, 4)], &[(4, 4), (28, 4)], &[(12, 6), (6, 14)], &[(10, 10), (12, 14)], &[(22, 6), (16, 14)], &[(20, 10), (24, 14)], &[(2, 20), (30, 20)], &[(16, 16), (16, 32)]]),
+    ('楚', &[&[(2, 6), (14, 6)], &[(8, 0), (8, 14)], &[(8, 6), (2, 14)], &[(8, 6), (14, 12)], &[(18, 6), (30, 6)], &[(24, 0), (24, 14)], &[(24, 6), (18, 14)], &[(24, 6), (30, 14)], &[(10, 16), (22, 16), (22, 22), (16, 22)], &[(16, 16), (16, 28)], &[(10, 22), (8, 30), (30, 30)]]),
+    ('河', &[&[(2, 4), (6, 8)], &[(2, 14), (6, 18)], &[(2, 30), (8, 22)], &[(10, 4), (30, 4)], &[(26, 4), (26, 30), (22, 28)], &[(12, 12), (20, 12), (20, 22), (12, 22), (12, 12)]]),
+    ('汉', &[&[(2, 4), (6, 8)], &[(2, 14), (6, 18)], &[(2, 30), (8, 22)], &[(12, 6), (26, 6), (18, 20), (10, 30)], &[(16, 14), (30, 30)]]),
+    ('界', &[&[(8, 2), (24, 2), (24, 14), (8, 14), (8, 2)], &[(8, 8), (24, 8)], &[(16, 2), (16, 14)], &[(16, 16), (4, 24)], &[(16, 16), (28, 24)], &[(12, 22), (8, 32)], &[(20, 22), (20, 32)]]),
+    ('一', &[&[(4, 16), (28, 16)]]),
+    ('二', &[&[(8, 8), (24, 8)], &[(4, 24), (28, 24)]]),
+    ('三', &[&[(6, 6), (26, 6)], &[(8, 16), (24, 16)], &[(4, 26), (28, 26)]]),
+    ('四', &[&[(4, 6), (28, 6), (28, 28), (4, 28), (4, 6)], &[(12, 6), (10, 20)], &[(20, 6), (20, 18), (24, 20)]]),
+    ('五', &[&[(6, 4), (26, 4)], &[(14, 4), (10, 28)], &[(8, 16), (22, 16), (22, 28)], &[(2, 28), (30, 28)]]),
+    ('六', &[&[(16, 2), (18, 6)], &[(4, 10), (28, 10)], &[(12, 16), (6, 28)], &[(20, 16), (26, 28)]]),
+    ('七', &[&[(4, 16), (28, 12)], &[(12, 4), (12, 26), (16, 28), (28, 28), (28, 24)]]),
+    ('八', &[&[(12, 6), (4, 28)], &[(18, 6), (20, 20), (28, 28)]]),
+    ('九', &[&[(14, 2), (14, 12), (4, 30)], &[(6, 12), (22, 12), (22, 28), (26, 28), (30, 24)]]),
+    ('0', &[&[(8, 4), (24, 4), (24, 28), (8, 28), (8, 4)]]),
+    ('1', &[&[(12, 8), (18, 4), (18, 28)]]),
+    ('2', &[&[(8, 6), (12, 4), (22, 4), (24, 8), (24, 12), (8, 28), (24, 28)]]),
+    ('3', &[&[(8, 4), (24, 4), (16, 14), (24, 18), (24, 24), (20, 28), (8, 28)]]),
+    ('4', &[&[(20, 28), (20, 4), (6, 20), (26, 20)]]),
+    ('5', &[&[(24, 4), (8, 4), (8, 14), (20, 14), (24, 18), (24, 24), (20, 28), (8, 28)]]),
+    ('6', &[&[(22, 4), (12, 4), (8, 10), (8, 28), (24, 28), (24, 16), (8, 16)]]),
+    ('7', &[&[(8, 4), (24, 4), (14, 28)]]),
+    ('8', &[&[(10, 4), (22, 4), (22, 14), (10, 14), (10, 4)], &[(8, 16), (24, 16), (24, 28), (8, 28), (8, 16)]]),
+    ('9', &[&[(24, 16), (8, 16), (8, 4), (24, 4), (24, 28), (10, 28)]]),
+    ('A', &[&[(6, 28), (16, 4), (26, 28)], &[(10, 20), (22, 20)]]),
+    ('B', &[&[(8, 4), (8, 28), (20, 28), (24, 24), (24, 20), (20, 16), (8, 16)], &[(8, 4), (20, 4), (22, 8), (22, 12), (20, 16)]]),
+    ('C', &[&[(24, 6), (20, 4), (12, 4), (8, 8), (8, 24), (12, 28), (20, 28), (24, 26)]]),
+    ('D', &[&[(8, 4), (8, 28), (18, 28), (24, 22), (24, 10), (18, 4), (8, 4)]]),
+    ('E', &[&[(24, 4), (8, 4), (8, 28), (24, 28)], &[(8, 16), (20, 16)]]),
+    ('F', &[&[(24, 4), (8, 4), (8, 28)], &[(8, 16), (20, 16)]]),
+    ('G', &[&[(24, 6), (20, 4), (12, 4), (8, 8), (8, 24), (12, 28), (20, 28), (24, 24), (24, 18), (16, 18)]]),
+    ('H', &[&[(8, 4), (8, 28)], &[(24, 4), (24, 28)], &[(8, 16), (24, 16)]]),
+    ('I', &[&[(16, 4), (16, 28)], &[(10, 4), (22, 4)], &[(10, 28), (22, 28)]]),
+];
+
+fn glyph(ch: char) -> Option<Glyph> {
+    // 黑方列号为全角数字
+    let ch = match ch {
+        '０'..='９' => char::from_u32(ch as u32 - '０' as u32 + '0' as u32).unwrap_or(ch),
+        _ => ch,
+    };
+    GLYPHS
+        .iter()
+        .find(|(glyph_ch, _)| *glyph_ch == ch)
+        .map(|(_, strokes)| *strokes)
+}
+
+type Point = (f32, f32);
+
+// RGB画布，各图元以覆盖率混合实现抗锯齿
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize, color: Rgb) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: color.repeat(width * height),
+        }
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Rgb {
+        let index = (y * self.width + x) * 3;
+        [
+            self.pixels[index],
+            self.pixels[index + 1],
+            self.pixels[index + 2],
+        ]
+    }
+
+    fn blend(&mut self, x: i64, y: i64, color: Rgb, alpha: f32) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height || alpha <= 0.0 {
+            return;
+        }
+
+        let alpha = alpha.min(1.0);
+        let index = (y as usize * self.width + x as usize) * 3;
+        for (channel, value) in color.iter().enumerate() {
+            let old = self.pixels[index + channel] as f32;
+            self.pixels[index + channel] = (old + (*value as f32 - old) * alpha).round() as u8;
+        }
+    }
+
+    // 对(x0,y0)-(x1,y1)范围内各像素以其中心点求覆盖率
+    fn shade(&mut self, from: Point, to: Point, color: Rgb, coverage: impl Fn(Point) -> f32) {
+        let (x0, y0) = (
+            from.0.floor().max(0.0) as i64,
+            from.1.floor().max(0.0) as i64,
+        );
+        let (x1, y1) = (to.0.ceil() as i64, to.1.ceil() as i64);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let alpha = coverage((x as f32 + 0.5, y as f32 + 0.5));
+                self.blend(x, y, color, alpha);
+            }
+        }
+    }
+
+    fn line(&mut self, from: Point, to: Point, width: f32, color: Rgb, alpha: f32) {
+        let half = width / 2.0;
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let len2 = (dx * dx + dy * dy).max(f32::EPSILON);
+        self.shade(
+            (from.0.min(to.0) - half - 1.0, from.1.min(to.1) - half - 1.0),
+            (from.0.max(to.0) + half + 1.0, from.1.max(to.1) + half + 1.0),
+            color,
+            |(x, y)| {
+                let t = (((x - from.0) * dx + (y - from.1) * dy) / len2).clamp(0.0, 1.0);
+                let distance = (x - from.0 - t * dx).hypot(y - from.1 - t * dy);
+                (half + 0.5 - distance).clamp(0.0, 1.0) * alpha
+            },
+        );
+    }
+
+    fn polyline(&mut self, points: &[Point], width: f32, color: Rgb) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], width, color, 1.0);
+        }
+    }
+
+    fn disc(&mut self, center: Point, radius: f32, color: Rgb) {
+        self.shade(
+            (center.0 - radius - 1.0, center.1 - radius - 1.0),
+            (center.0 + radius + 1.0, center.1 + radius + 1.0),
+            color,
+            |(x, y)| (radius + 0.5 - (x - center.0).hypot(y - center.1)).clamp(0.0, 1.0),
+        );
+    }
+
+    fn ring(&mut self, center: Point, radius: f32, width: f32, color: Rgb) {
+        let outer = radius + width / 2.0;
+        self.shade(
+            (center.0 - outer - 1.0, center.1 - outer - 1.0),
+            (center.0 + outer + 1.0, center.1 + outer + 1.0),
+            color,
+            |(x, y)| {
+                let distance = ((x - center.0).hypot(y - center.1) - radius).abs();
+                (width / 2.0 + 0.5 - distance).clamp(0.0, 1.0)
+            },
+        );
+    }
+
+    // 以2x2子像素采样求覆盖率
+    fn triangle(&mut self, points: [Point; 3], color: Rgb, alpha: f32) {
+        let cross =
+            |a: Point, b: Point, p: Point| (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0);
+        let inside = |p: Point| {
+            let signs = [
+                cross(points[0], points[1], p),
+                cross(points[1], points[2], p),
+                cross(points[2], points[0], p),
+            ];
+            signs.iter().all(|sign| *sign >= 0.0) || signs.iter().all(|sign| *sign <= 0.0)
+        };
+        let min = |f: fn(&Point) -> f32| points.iter().map(f).fold(f32::MAX, f32::min);
+        let max = |f: fn(&Point) -> f32| points.iter().map(f).fold(f32::MIN, f32::max);
+        self.shade(
+            (min(|p| p.0), min(|p| p.1)),
+            (max(|p| p.0), max(|p| p.1)),
+            color,
+            |(x, y)| {
+                let count = [(-0.25, -0.25), (0.25, -0.25), (-0.25, 0.25), (0.25, 0.25)]
+                    .iter()
+                    .filter(|(dx, dy)| inside((x + dx, y + dy)))
+                    .count();
+                count as f32 / 4.0 * alpha
+            },
+        );
+    }
+
+    // 以center为中心、边长size绘制一字，无字形时返回false
+    fn text(&mut self, ch: char, center: Point, size: f32, color: Rgb) -> bool {
+        let Some(strokes) = glyph(ch) else {
+            return false;
+        };
+
+        let scale = size / GLYPHSIZE;
+        let width = (size / 12.0).max(1.0);
+        for stroke in strokes {
+            let points: Vec<Point> = stroke
+                .iter()
+                .map(|(x, y)| {
+                    (
+                        center.0 + (*x as f32 - GLYPHSIZE / 2.0) * scale,
+                        center.1 + (*y as f32 - GLYPHSIZE / 2.0) * scale,
+                    )
+                })
+                .collect();
+            self.polyline(&points, width, color);
+        }
+
+        true
+    }
+
+    fn to_png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity((self.width * 3 + 1) * self.height);
+        for row in self.pixels.chunks(self.width * 3) {
+            // 过滤方式0
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // 8位深度、RGB、默认压缩及过滤、无隔行
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut result = b"\x89PNG\r\n\x1a\n".to_vec();
+        push_chunk(&mut result, b"IHDR", &header);
+        push_chunk(&mut result, b"IDAT", &zlib(&raw, self.width * 3 + 1));
+        push_chunk(&mut result, b"IEND", &[]);
+        result
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+fn push_chunk(result: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    result.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = result.len();
+    result.extend_from_slice(chunk_type);
+    result.extend_from_slice(data);
+    let crc = crc32(&result[start..]);
+    result.extend_from_slice(&crc.to_be_bytes());
+}
+
+// 低位在先写入比特流
+struct BitWriter {
+    bytes: Vec<u8>,
+    value: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: u32) {
+        self.value |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.value as u8);
+            self.value >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // 哈夫曼码高位在先
+    fn write_code(&mut self, code: u32, count: u32) {
+        self.write(code.reverse_bits() >> (32 - count), count);
+    }
+
+    fn write_literal(&mut self, value: u32) {
+        match value {
+            0..=143 => self.write_code(0x30 + value, 8),
+            144..=255 => self.write_code(0x190 + value - 144, 9),
+            256..=279 => self.write_code(value - 256, 7),
+            _ => self.write_code(0xc0 + value - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.value as u8);
+        }
+
+        self.bytes
+    }
+}
+
+const LENGTHBASES: [u32; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTHEXTRAS: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCEBASES: [u32; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCEEXTRAS: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const MAXMATCH: usize = 258;
+const MAXDISTANCE: usize = 32768;
+
+// 固定哈夫曼编码的deflate，仅匹配左邻像素及上一行同位置，适于大片同色的局面图
+fn zlib(data: &[u8], row_len: usize) -> Vec<u8> {
+    let mut writer = BitWriter {
+        bytes: vec![0x78, 0x01],
+        value: 0,
+        count: 0,
+    };
+    // 末块，固定哈夫曼编码
+    writer.write(1, 1);
+    writer.write(1, 2);
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let (mut length, mut distance) = (0, 0);
+        for dist in [3, row_len] {
+            if dist > pos || dist > MAXDISTANCE {
+                continue;
+            }
+
+            let max_len = MAXMATCH.min(data.len() - pos);
+            let len = (0..max_len)
+                .take_while(|i| data[pos + i] == data[pos + i - dist])
+                .count();
+            if len > length {
+                (length, distance) = (len, dist);
+            }
+        }
+
+        if length < 3 {
+            writer.write_literal(data[pos] as u32);
+            pos += 1;
+            continue;
+        }
+
+        let code = LENGTHBASES
+            .iter()
+            .rposition(|base| *base as usize <= length)
+            .unwrap();
+        writer.write_literal(257 + code as u32);
+        writer.write(length as u32 - LENGTHBASES[code], LENGTHEXTRAS[code]);
+        let code = DISTANCEBASES
+            .iter()
+            .rposition(|base| *base as usize <= distance)
+            .unwrap();
+        writer.write_code(code as u32, 5);
+        writer.write(distance as u32 - DISTANCEBASES[code], DISTANCEEXTRAS[code]);
+        pos += length;
+    }
+    writer.write_literal(256);
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    let mut result = writer.finish();
+    result.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    result
+}
+
+struct Layout {
+    grid: f32,
+    margin: f32,
+    is_flip: bool,
+}
+
+impl Layout {
+    // 棋盘坐标转换为图上坐标(翻转时旋转180度)
+    fn point(&self, coord: Coord) -> Point {
+        let coord = if self.is_flip {
+            coord.to_change(coord::ChangeType::Rotate)
+        } else {
+            coord
+        };
+
+        (
+            self.margin + coord.col as f32 * self.grid,
+            self.margin + coord.row as f32 * self.grid,
+        )
+    }
+
+    fn row_col(&self, row: usize, col: usize) -> Point {
+        self.point(Coord { row, col })
+    }
+}
+
+fn draw_grid(canvas: &mut Canvas, layout: &Layout, theme: &Theme) {
+    let width = (layout.grid / 24.0).max(1.0);
+    let mut line = |from: (usize, usize), to: (usize, usize)| {
+        canvas.line(
+            layout.row_col(from.0, from.1),
+            layout.row_col(to.0, to.1),
+            width,
+            theme.line,
+            1.0,
+        );
+    };
+    for row in 0..ROWCOUNT {
+        line((row, 0), (row, COLCOUNT - 1));
+    }
+    for col in 0..COLCOUNT {
+        if col == 0 || col == COLCOUNT - 1 {
+            line((0, col), (ROWCOUNT - 1, col));
+        } else {
+            // 河界处竖线断开
+            line((0, col), (ROWCOUNT / 2 - 1, col));
+            line((ROWCOUNT / 2, col), (ROWCOUNT - 1, col));
+        }
+    }
+    // 九宫斜线
+    for (top_row, bottom_row) in [(0, 2), (7, 9)] {
+        line((top_row, 3), (bottom_row, 5));
+        line((top_row, 5), (bottom_row, 3));
+    }
+
+    let grid = layout.grid;
+    let river_y = layout.margin + grid * 4.5;
+    let size = grid * 0.6;
+    for (text, x) in [
+        ("楚河", layout.margin + grid * 2.0),
+        ("汉界", layout.margin + grid * 6.0),
+    ] {
+        for (index, ch) in text.chars().enumerate() {
+            let offset = (index as f32 - 0.5) * size * 1.6;
+            canvas.text(ch, (x + offset, river_y), size, theme.line);
+        }
+    }
+}
+
+fn draw_labels(canvas: &mut Canvas, layout: &Layout, options: &PngOptions) {
+    // 标注靠近图边，以免与边线上的棋子重叠
+    let size = layout.grid * 0.3;
+    let color = options.theme.line;
+    let half_margin = layout.margin / 4.0;
+    let bottom_y = layout.margin * 2.0 + layout.grid * (ROWCOUNT - 1) as f32 - half_margin;
+    match options.coord_label {
+        CoordLabel::NoLabel => (),
+        CoordLabel::Chinese => {
+            // 双方各自从右向左数列
+            let bottom_color = options.bottom_color;
+            let top_color = piece::other_color(bottom_color);
+            for col in 0..COLCOUNT {
+                let x = layout.margin + col as f32 * layout.grid;
+                let top_ch = board::Board::get_col_ch(top_color, col);
+                let bottom_ch = board::Board::get_col_ch(bottom_color, COLCOUNT - 1 - col);
+                canvas.text(top_ch, (x, half_margin), size, color);
+                canvas.text(bottom_ch, (x, bottom_y), size, color);
+            }
+        }
+        CoordLabel::Iccs => {
+            for col in 0..COLCOUNT {
+                let (x, _) = layout.row_col(0, col);
+                canvas.text(char::from(b'A' + col as u8), (x, bottom_y), size, color);
+            }
+            for row in 0..ROWCOUNT {
+                let (_, y) = layout.row_col(row, 0);
+                canvas.text(char::from(b'0' + row as u8), (half_margin, y), size, color);
+            }
+        }
+    }
+}
+
+fn draw_pieces(canvas: &mut Canvas, layout: &Layout, board: &board::Board, theme: &Theme) {
+    let radius = layout.grid * 0.45;
+    let size = layout.grid * 0.55;
+    let width = (layout.grid / 20.0).max(1.0);
+    for (index, piece) in board.pieces().iter().enumerate() {
+        if let piece::Piece::Some(color, _) = piece {
+            let center = layout.point(Coord::from_index(index).unwrap());
+            let (color, fill) = match color {
+                piece::Color::Red => (theme.red, theme.red_fill),
+                piece::Color::Black => (theme.black, theme.black_fill),
+            };
+            canvas.disc(center, radius, fill);
+            canvas.ring(center, radius - width * 1.5, width, color);
+            canvas.ring(center, radius, width / 2.0, theme.line);
+            canvas.text(piece.print_name(), center, size, color);
+        }
+    }
+}
+
+// 起止两点外围的虚线方框
+fn draw_last_move(canvas: &mut Canvas, layout: &Layout, coordpair: &CoordPair, theme: &Theme) {
+    let half = layout.grid / 2.0;
+    let width = (layout.grid / 20.0).max(1.0);
+    let dash = (layout.grid / 10.0).max(2.0);
+    for coord in [coordpair.from_coord, coordpair.to_coord] {
+        let (x, y) = layout.point(coord);
+        let corners = [
+            (x - half, y - half),
+            (x + half, y - half),
+            (x + half, y + half),
+            (x - half, y + half),
+        ];
+        for side in 0..4 {
+            let (from, to) = (corners[side], corners[(side + 1) % 4]);
+            let mut offset = 0.0;
+            while offset < layout.grid {
+                let end = (offset + dash).min(layout.grid);
+                let at = |t: f32| {
+                    (
+                        from.0 + (to.0 - from.0) * t / layout.grid,
+                        from.1 + (to.1 - from.1) * t / layout.grid,
+                    )
+                };
+                canvas.line(at(offset), at(end), width, theme.last_move, 1.0);
+                offset += dash * 1.75;
+            }
+        }
+    }
+}
+
+fn draw_arrows(canvas: &mut Canvas, layout: &Layout, arrows: &[CoordPair], theme: &Theme) {
+    let width = (layout.grid / 8.0).max(2.0);
+    let head = width * 2.5;
+    for coordpair in arrows {
+        let from = layout.point(coordpair.from_coord);
+        let to = layout.point(coordpair.to_coord);
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let len = dx.hypot(dy);
+        if len < head {
+            continue;
+        }
+
+        let (ux, uy) = (dx / len, dy / len);
+        let base = (to.0 - ux * head, to.1 - uy * head);
+        canvas.line(from, base, width, theme.arrow, 0.8);
+        canvas.triangle(
+            [
+                to,
+                (base.0 - uy * head * 0.6, base.1 + ux * head * 0.6),
+                (base.0 + uy * head * 0.6, base.1 - ux * head * 0.6),
+            ],
+            theme.arrow,
+            0.8,
+        );
+    }
+}
+
+fn draw(board: &board::Board, options: &PngOptions) -> Canvas {
+    let grid = options.grid_size.max(MINGRIDSIZE);
+    let layout = Layout {
+        grid: grid as f32,
+        margin: grid as f32,
+        is_flip: options.bottom_color != board.bottom_color(),
+    };
+    let theme = &options.theme;
+
+    let mut canvas = Canvas::new(
+        grid * (COLCOUNT + 1),
+        grid * (ROWCOUNT + 1),
+        theme.background,
+    );
+    draw_grid(&mut canvas, &layout, theme);
+    draw_labels(&mut canvas, &layout, options);
+    if let Some(coordpair) = &options.last_move {
+        draw_last_move(&mut canvas, &layout, coordpair, theme);
+    }
+    draw_pieces(&mut canvas, &layout, board, theme);
+    draw_arrows(&mut canvas, &layout, &options.arrows, theme);
+
+    canvas
+}
+
+// 局面图(PNG)：棋盘(河界、九宫)、棋子、上一着标记、候选着法箭头及坐标，
+// 字形为内置笔画，不依赖字体
+pub fn to_png(board: &board::Board, options: &PngOptions) -> Vec<u8> {
+    draw(board, options).to_png()
+}
+
+// 一条备注及其所在局面的图
+#[derive(Debug)]
+pub struct RemarkDiagram {
+    pub remark: String,
+    pub png: Vec<u8>,
+}
+
+// 棋谱中每条备注各出一图，标记备注所在的着法，依先主线后变着的深度优先顺序
+pub fn remark_diagrams(manual: &manual::Manual, options: &PngOptions) -> Vec<RemarkDiagram> {
+    fn push_diagrams(
+        amove: &Rc<amove::Move>,
+        board: &mut board::Board,
+        options: &mut PngOptions,
+        result: &mut Vec<RemarkDiagram>,
+    ) {
+        let remark = amove.remark();
+        if !remark.is_empty() {
+            options.last_move = if amove.before().is_some() {
+                Some(amove.coordpair)
+            } else {
+                None
+            };
+            result.push(RemarkDiagram {
+                remark,
+                png: to_png(board, options),
+            });
+        }
+
+        for after_move in amove.after() {
+            board.do_move(&after_move);
+            push_diagrams(&after_move, board, options, result);
+            board.undo_move(&after_move);
+        }
+    }
+
+    let mut board = *manual.manual_move().board();
+    let mut options = options.clone();
+    let mut result = Vec::new();
+    push_diagrams(
+        &manual.manual_move().root_move(),
+        &mut board,
+        &mut options,
+        &mut result,
+    );
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png() {
+        let board = board::Board::new();
+        let mut options = PngOptions::new();
+        let png = to_png(&board, &options);
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR"));
+        assert_eq!([0, 0, 1, 144, 0, 0, 1, 184], png[16..24]);
+        assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+        // 同色大片压缩后远小于原始数据
+        assert!(png.len() < 400 * 440 * 3 / 5);
+
+        let canvas = draw(&board, &options);
+        assert_eq!(Theme::WOOD.background, canvas.pixel(5, 5));
+        // 红帅位于下方中间
+        assert_eq!(Theme::WOOD.red_fill, canvas.pixel(200 - 13, 400));
+        assert_eq!(
+            Theme::WOOD.background,
+            canvas.pixel(200 - 12, 40 + 40 * 4 + 20)
+        );
+
+        options.theme = Theme::from_name("print").unwrap();
+        options.bottom_color = piece::Color::Black;
+        options.last_move = Some(CoordPair::from_row_col(7, 7, 7, 4).unwrap());
+        options.arrows = vec![CoordPair::from_row_col(0, 1, 2, 2).unwrap()];
+        options.coord_label = CoordLabel::Iccs;
+        options.grid_size = 20;
+        let canvas = draw(&board, &options);
+        assert_eq!((200, 220), (canvas.width, canvas.height));
+        // 翻转后黑将位于下方中间
+        assert!(canvas.pixel(106, 200)[0] < 0x10);
+        assert_ne!(Theme::PRINT.background, canvas.pixel(100, 30));
+
+        let manual = manual::Manual::read_from(
+            "[Title: test]\n\n{开局}(1)\n(7,7)(7,4){中炮}(2)\n(0,7)(2,6){屏风马}\n(0,1)(2,2)\n"
+                .as_bytes(),
+            coord::RecordType::Txt,
+        )
+        .unwrap();
+        let diagrams = remark_diagrams(&manual, &PngOptions::new());
+        assert_eq!(
+            vec!["开局", "中炮", "屏风马"],
+            diagrams
+                .iter()
+                .map(|diagram| diagram.remark.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_ne!(diagrams[0].png, diagrams[1].png);
+    }
+}
@@ -0,0 +1,432 @@
+#![allow(dead_code)]
+
+use crate::bit_board;
+use crate::board;
+use crate::coord::{Coord, CoordPair, COLCOUNT, ROWCOUNT, SEATCOUNT};
+use crate::piece::{self, Color, Kind};
+use std::time::{Duration, Instant};
+
+// 杀棋分值及其与着数的换算，与着法注释共用
+pub use crate::common::{is_mate_score, mate_moves, mate_score, MATESCORE};
+
+const INFINITESCORE: i32 = MATESCORE + 1;
+
+// 各种棋子的子力分值
+const KINDVALUES: [i32; piece::KINDCOUNT] = [0, 200, 200, 400, 900, 450, 100];
+
+// 过河兵卒的加分
+const CROSSEDPAWNVALUE: i32 = 100;
+
+// 每搜索若干节点检查一次是否超时
+const TIMECHECKNODES: u64 = 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    // 走子方视角的分值
+    pub score: i32,
+    // 主要变例，无着可走时为空
+    pub pv: Vec<CoordPair>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AnalysisOptions {
+    // 最大搜索层数
+    pub depth: usize,
+    // 返回的候选着法数
+    pub multi_pv: usize,
+    // 限时，超时后返回已完成的最深一层的结果
+    pub time_limit: Option<Duration>,
+}
+
+impl AnalysisOptions {
+    pub fn new(depth: usize, multi_pv: usize) -> Self {
+        AnalysisOptions {
+            depth,
+            multi_pv,
+            time_limit: None,
+        }
+    }
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self::new(3, 3)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    // 实际完成的搜索层数
+    pub depth: usize,
+    // 候选着法按分值由高到低排列，各变例首着即候选着法
+    pub lines: Vec<SearchResult>,
+}
+
+struct Searcher {
+    bit_board: bit_board::BitBoard,
+    bottom_color: Color,
+    // 超时后各节点立即返回，所得结果作废
+    deadline: Option<Instant>,
+    nodes: u64,
+    is_stopped: bool,
+}
+
+impl Searcher {
+    fn new(board: &board::Board) -> Self {
+        Searcher {
+            bit_board: board.bit_board(),
+            bottom_color: board.bottom_color(),
+            deadline: None,
+            nodes: 0,
+            is_stopped: false,
+        }
+    }
+
+    fn check_stopped(&mut self) -> bool {
+        if let Some(deadline) = self.deadline {
+            if !self.is_stopped && self.nodes.is_multiple_of(TIMECHECKNODES) {
+                self.is_stopped = Instant::now() >= deadline;
+            }
+            self.nodes += 1;
+        }
+
+        self.is_stopped
+    }
+
+    // color方走from_index-to_index后的分值(color方视角)及对方的应着变例，
+    // 分值不高于alpha时只是上限
+    fn search_root_move(
+        &mut self,
+        color: Color,
+        (from_index, to_index): (usize, usize),
+        depth: usize,
+        alpha: i32,
+    ) -> SearchResult {
+        let eat_kind = self.bit_board.do_move(from_index, to_index);
+        let mut pv = Vec::new();
+        let score = -self.alpha_beta(
+            piece::other_color(color),
+            depth.max(1) - 1,
+            -INFINITESCORE,
+            -alpha,
+            1,
+            &mut pv,
+        );
+        self.bit_board.undo_move(from_index, to_index, eat_kind);
+
+        SearchResult {
+            score,
+            pv: std::iter::once((from_index, to_index))
+                .chain(pv)
+                .map(|(from_index, to_index)| get_coordpair(from_index, to_index))
+                .collect(),
+        }
+    }
+
+    // 子力分值，color方视角
+    fn evaluate(&self, color: Color) -> i32 {
+        let mut score = 0;
+        for index in 0..SEATCOUNT {
+            if let Some(index_color) = self.bit_board.get_color(index) {
+                let kind = self.bit_board.get_kind(index);
+                let mut value = KINDVALUES[kind as usize];
+                if kind == Kind::Pawn {
+                    let row = index / COLCOUNT;
+                    let is_crossed = if index_color == self.bottom_color {
+                        row < ROWCOUNT / 2
+                    } else {
+                        row >= ROWCOUNT / 2
+                    };
+                    if is_crossed {
+                        value += CROSSEDPAWNVALUE;
+                    }
+                }
+
+                score += if index_color == color { value } else { -value };
+            }
+        }
+
+        score
+    }
+
+    fn get_eat_value(&self, to_index: usize) -> i32 {
+        match self.bit_board.get_color(to_index) {
+            Some(_) => KINDVALUES[self.bit_board.get_kind(to_index) as usize],
+            None => 0,
+        }
+    }
+
+    // 合法着法，吃子价值高者优先
+    fn get_ordered_moves(&mut self, color: Color) -> Vec<(usize, usize)> {
+        let mut moves = self.bit_board.get_legal_moves(color);
+        moves.sort_by_key(|(_, to_index)| -self.get_eat_value(*to_index));
+        moves
+    }
+
+    // 只搜索吃子着法，避免在交换中途评估
+    fn quiesce(&mut self, color: Color, mut alpha: i32, beta: i32, ply: i32) -> i32 {
+        if self.check_stopped() {
+            return 0;
+        }
+
+        let moves = self.get_ordered_moves(color);
+        if moves.is_empty() {
+            return -MATESCORE + ply;
+        }
+
+        let stand_score = self.evaluate(color);
+        if stand_score >= beta {
+            return stand_score;
+        }
+        alpha = alpha.max(stand_score);
+
+        let other_color = piece::other_color(color);
+        for (from_index, to_index) in moves {
+            if self.bit_board.get_color(to_index).is_none() {
+                break;
+            }
+
+            let eat_kind = self.bit_board.do_move(from_index, to_index);
+            let score = -self.quiesce(other_color, -beta, -alpha, ply + 1);
+            self.bit_board.undo_move(from_index, to_index, eat_kind);
+
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+
+    fn alpha_beta(
+        &mut self,
+        color: Color,
+        depth: usize,
+        mut alpha: i32,
+        beta: i32,
+        ply: i32,
+        pv: &mut Vec<(usize, usize)>,
+    ) -> i32 {
+        pv.clear();
+        if self.check_stopped() {
+            return 0;
+        }
+        if depth == 0 {
+            return self.quiesce(color, alpha, beta, ply);
+        }
+
+        let moves = self.get_ordered_moves(color);
+        if moves.is_empty() {
+            return -MATESCORE + ply;
+        }
+
+        let other_color = piece::other_color(color);
+        let mut best_score = -INFINITESCORE;
+        let mut after_pv = Vec::new();
+        for (from_index, to_index) in moves {
+            let eat_kind = self.bit_board.do_move(from_index, to_index);
+            let score = -self.alpha_beta(
+                other_color,
+                depth - 1,
+                -beta,
+                -alpha,
+                ply + 1,
+                &mut after_pv,
+            );
+            self.bit_board.undo_move(from_index, to_index, eat_kind);
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    pv.clear();
+                    pv.push((from_index, to_index));
+                    pv.append(&mut after_pv);
+                }
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best_score
+    }
+}
+
+fn get_coordpair(from_index: usize, to_index: usize) -> CoordPair {
+    CoordPair::from(
+        Coord::from_index(from_index).unwrap(),
+        Coord::from_index(to_index).unwrap(),
+    )
+}
+
+// color方走子，搜索depth层(另加吃子延伸)的最佳着法及分值
+pub fn search(board: &board::Board, color: Color, depth: usize) -> SearchResult {
+    let mut searcher = Searcher::new(board);
+    let mut pv = Vec::new();
+    let score = searcher.alpha_beta(
+        color,
+        depth.max(1),
+        -INFINITESCORE,
+        INFINITESCORE,
+        0,
+        &mut pv,
+    );
+
+    SearchResult {
+        score,
+        pv: pv
+            .into_iter()
+            .map(|(from_index, to_index)| get_coordpair(from_index, to_index))
+            .collect(),
+    }
+}
+
+// color方走coordpair后，按depth层搜索的分值及变例(含本着)，分值为color方视角
+pub fn search_move(
+    board: &board::Board,
+    color: Color,
+    coordpair: &CoordPair,
+    depth: usize,
+) -> SearchResult {
+    Searcher::new(board).search_root_move(color, coordpair.from_to_index(), depth, -INFINITESCORE)
+}
+
+// color方走子，局面转为BitBoard后逐层加深搜索各着法的分值及变例，返回分值最高的multi_pv个
+pub fn analyze(board: &board::Board, color: Color, options: &AnalysisOptions) -> Analysis {
+    let deadline = options
+        .time_limit
+        .map(|time_limit| Instant::now() + time_limit);
+    let is_timeout = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+    let mut searcher = Searcher::new(board);
+    let mut moves = searcher.get_ordered_moves(color);
+    let mut analysis = Analysis {
+        depth: 0,
+        lines: Vec::new(),
+    };
+    let multi_pv = options.multi_pv.max(1);
+    for depth in 1..=options.depth.max(1) {
+        // 第1层总是完成，此后搜索中途超时即放弃未完成的一层
+        if depth > 1 {
+            searcher.deadline = deadline;
+        }
+        // 只需前multi_pv个着法的确切分值，其余以第multi_pv名的分值为下限搜索即可排除
+        let mut lines: Vec<SearchResult> = Vec::new();
+        let mut is_complete = true;
+        for &(from_index, to_index) in &moves {
+            let alpha = match lines.get(multi_pv - 1) {
+                Some(line) => line.score,
+                None => -INFINITESCORE,
+            };
+            let line = searcher.search_root_move(color, (from_index, to_index), depth, alpha);
+            if searcher.is_stopped {
+                is_complete = false;
+                break;
+            }
+            if line.score > alpha {
+                // 分值相同时保持原有次序
+                let index = lines
+                    .iter()
+                    .position(|other| other.score < line.score)
+                    .unwrap_or(lines.len());
+                lines.insert(index, line);
+                lines.truncate(multi_pv);
+            }
+        }
+        if !is_complete {
+            break;
+        }
+
+        // 下一层先搜索本层的候选着法
+        let best_moves: Vec<(usize, usize)> = lines
+            .iter()
+            .map(|line| line.pv[0].from_to_index())
+            .collect();
+        moves.retain(|amove| !best_moves.contains(amove));
+        moves.splice(0..0, best_moves);
+        analysis = Analysis { depth, lines };
+        if is_timeout() {
+            break;
+        }
+    }
+    analysis
+}
+
+// 分值的文字表示，走子方视角：杀棋为着数，其余以兵为单位
+pub fn score_string(score: i32) -> String {
+    if let Some(moves) = mate_moves(score) {
+        if moves > 0 {
+            format!("{moves}着杀")
+        } else {
+            format!("{}着被杀", -moves)
+        }
+    } else {
+        format!("{:+.2}", score as f64 / 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        // 车平六绝杀
+        let board = board::Board::from("3k5/9/9/9/9/6R2/9/9/9/4K4");
+        let result = search(&board, Color::Red, 1);
+        assert!(is_mate_score(result.score));
+        assert_eq!(MATESCORE - 1, result.score);
+        assert_eq!("1着杀", score_string(result.score));
+        assert_eq!(Some(-2), mate_moves(-(MATESCORE - 3)));
+        assert_eq!(-(MATESCORE - 3), mate_score(-2));
+        assert_eq!(None, mate_moves(900));
+        assert_eq!("-1.50", score_string(-150));
+
+        // 车吃车
+        let board = board::Board::from("4k4/9/9/R7r/9/9/9/9/9/3K5");
+        let result = search(&board, Color::Red, 2);
+        assert_eq!(
+            CoordPair::from(Coord::from(3, 0).unwrap(), Coord::from(3, 8).unwrap()),
+            result.pv[0]
+        );
+        assert_eq!(KINDVALUES[Kind::Rook as usize], result.score);
+        let result = search_move(
+            &board,
+            Color::Red,
+            &CoordPair::from(Coord::from(3, 0).unwrap(), Coord::from(5, 0).unwrap()),
+            2,
+        );
+        assert_eq!(0, result.score);
+        assert_eq!(2, result.pv.len());
+
+        // 初始局面子力均等
+        let result = search(&board::Board::new(), Color::Red, 2);
+        assert_eq!(0, result.score);
+
+        // 车吃车最佳，其余着法均失车
+        let board = board::Board::from("4k4/9/9/R7r/9/9/9/9/9/3K5");
+        let analysis = analyze(&board, Color::Red, &AnalysisOptions::new(2, 3));
+        assert_eq!(2, analysis.depth);
+        assert_eq!(3, analysis.lines.len());
+        assert_eq!(search(&board, Color::Red, 2), analysis.lines[0].clone());
+        assert!(analysis.lines[1].score <= 0);
+        assert!(analysis.lines[1].score >= analysis.lines[2].score);
+
+        // 限时为零时只完成第1层
+        let mut options = AnalysisOptions::new(4, 1);
+        options.time_limit = Some(Duration::ZERO);
+        let analysis = analyze(&board, Color::Red, &options);
+        assert_eq!(1, analysis.depth);
+        assert_eq!(1, analysis.lines.len());
+
+        // 超时在搜索中途即停止，不必等一个根着法搜索完
+        let mut options = AnalysisOptions::new(20, 1);
+        options.time_limit = Some(Duration::from_millis(50));
+        let start = Instant::now();
+        let analysis = analyze(&board::Board::new(), Color::Red, &options);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(analysis.depth < 20);
+        assert!(!analysis.lines.is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+#![allow(dead_code)]
+
+use crate::amove;
+use crate::bit_board;
+use crate::board;
+use crate::coord::{Coord, CoordPair};
+use crate::info;
+use crate::manual;
+use crate::manual_move;
+use crate::piece;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SolverOptions {
+    // 攻方最多着数
+    pub max_moves: usize,
+    // 攻方每着均须将军(连将杀)
+    pub checks_only: bool,
+}
+
+impl SolverOptions {
+    pub fn new(max_moves: usize) -> Self {
+        SolverOptions {
+            max_moves,
+            checks_only: false,
+        }
+    }
+}
+
+// 已知结果：攻方最少几着成杀，或几着之内不能成杀
+#[derive(Clone, Copy, Debug)]
+enum Entry {
+    Mate(usize),
+    NoMate(usize),
+}
+
+struct Solver {
+    bit_board: bit_board::BitBoard,
+    attacker: piece::Color,
+    defender: piece::Color,
+    checks_only: bool,
+    table: HashMap<(u64, u64), Entry>,
+}
+
+impl Solver {
+    fn new(board: &board::Board, attacker: piece::Color, checks_only: bool) -> Self {
+        Solver {
+            bit_board: board.bit_board(),
+            attacker,
+            defender: piece::other_color(attacker),
+            checks_only,
+            table: HashMap::new(),
+        }
+    }
+
+    // 攻方走子的局面，depth着之内最少几着成杀
+    fn mate_in(&mut self, depth: usize) -> Option<usize> {
+        let key_lock = self.bit_board.get_key_lock(self.attacker);
+        let start = match self.table.get(&key_lock) {
+            Some(Entry::Mate(moves)) => return (*moves <= depth).then_some(*moves),
+            Some(Entry::NoMate(moves)) if *moves >= depth => return None,
+            Some(Entry::NoMate(moves)) => moves + 1,
+            None => 1,
+        };
+
+        for moves in start..=depth {
+            if self.find_mate_move(moves).is_some() {
+                self.table.insert(key_lock, Entry::Mate(moves));
+                return Some(moves);
+            }
+        }
+
+        self.table.insert(key_lock, Entry::NoMate(depth));
+        None
+    }
+
+    // 恰可在moves着之内成杀的攻方着法
+    fn find_mate_move(&mut self, moves: usize) -> Option<(usize, usize)> {
+        let mut attack_moves = self.bit_board.get_legal_moves(self.attacker);
+        // 吃子着法优先
+        attack_moves.sort_by_key(|(_, to_index)| self.bit_board.get_color(*to_index).is_none());
+        for (from_index, to_index) in attack_moves {
+            let eat_kind = self.bit_board.do_move(from_index, to_index);
+            let is_mate = self.is_defended_mate(moves);
+            self.bit_board.undo_move(from_index, to_index, eat_kind);
+
+            if is_mate {
+                return Some((from_index, to_index));
+            }
+        }
+
+        None
+    }
+
+    // 攻方已走，守方无论如何应着均在moves着之内被杀(含本着)
+    fn is_defended_mate(&mut self, moves: usize) -> bool {
+        if self.checks_only && !self.bit_board.is_checked(self.defender) {
+            return false;
+        }
+
+        let defend_moves = self.bit_board.get_legal_moves(self.defender);
+        // 困毙亦负
+        if defend_moves.is_empty() {
+            return true;
+        }
+        if moves <= 1 {
+            return false;
+        }
+
+        defend_moves.into_iter().all(|(from_index, to_index)| {
+            let eat_kind = self.bit_board.do_move(from_index, to_index);
+            let is_mate = self.mate_in(moves - 1).is_some();
+            self.bit_board.undo_move(from_index, to_index, eat_kind);
+
+            is_mate
+        })
+    }
+
+    // 攻方着法取最快成杀者，守方着法全部列出，抵抗最久者为主着
+    fn append_attack(&mut self, before_move: &Rc<amove::Move>, moves: usize) {
+        let (from_index, to_index) = match self.find_mate_move(moves) {
+            Some(from_to_index) => from_to_index,
+            None => return,
+        };
+
+        let amove = before_move.append(get_coordpair(from_index, to_index), String::new());
+        let eat_kind = self.bit_board.do_move(from_index, to_index);
+        let mut defend_moves = Vec::new();
+        for (from_index, to_index) in self.bit_board.get_legal_moves(self.defender) {
+            let eat_kind = self.bit_board.do_move(from_index, to_index);
+            if let Some(after_moves) = self.mate_in(moves - 1) {
+                defend_moves.push((after_moves, from_index, to_index));
+            }
+            self.bit_board.undo_move(from_index, to_index, eat_kind);
+        }
+        if defend_moves.is_empty() {
+            let remark = if self.bit_board.is_checked(self.defender) {
+                "绝杀"
+            } else {
+                "困毙"
+            };
+            amove.set_remark(String::from(remark));
+        }
+
+        defend_moves.sort_by(|(moves, ..), (other_moves, ..)| other_moves.cmp(moves));
+        for (after_moves, from_index, to_index) in defend_moves {
+            let defend_move = amove.append(get_coordpair(from_index, to_index), String::new());
+            let eat_kind = self.bit_board.do_move(from_index, to_index);
+            self.append_attack(&defend_move, after_moves);
+            self.bit_board.undo_move(from_index, to_index, eat_kind);
+        }
+        self.bit_board.undo_move(from_index, to_index, eat_kind);
+    }
+}
+
+fn get_coordpair(from_index: usize, to_index: usize) -> CoordPair {
+    CoordPair::from(
+        Coord::from_index(from_index).unwrap(),
+        Coord::from_index(to_index).unwrap(),
+    )
+}
+
+// color方走子，options.max_moves着之内最少几着成杀
+pub fn mate_in(
+    board: &board::Board,
+    color: piece::Color,
+    options: &SolverOptions,
+) -> Option<usize> {
+    Solver::new(board, color, options.checks_only).mate_in(options.max_moves)
+}
+
+// 求解杀局，返回全部应着组成的解答棋谱；不能成杀时返回None
+pub fn solve(
+    board: &board::Board,
+    color: piece::Color,
+    options: &SolverOptions,
+) -> Option<manual::Manual> {
+    let mut solver = Solver::new(board, color, options.checks_only);
+    let moves = solver.mate_in(options.max_moves)?;
+
+    let root_move = amove::Move::root();
+    root_move.set_remark(format!("{moves}着杀"));
+    solver.append_attack(&root_move, moves);
+
+    let fen = board.get_fen();
+    let mut info = BTreeMap::new();
+    let (side, win) = match color {
+        piece::Color::Red => ("r", info::Win::RedWin),
+        piece::Color::Black => ("b", info::Win::BlackWin),
+    };
+    info.insert(manual::InfoKey::FEN.name(), format!("{fen} {side} - - 0 1"));
+    let mut manual =
+        manual::Manual::from_info_move(info, manual_move::ManualMove::from(&fen, root_move));
+    // 末着可能为绝杀或困毙，不记终局原因
+    manual.set_result(info::GameResult::new(win, None));
+
+    Some(manual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_solver() {
+        // 车平六绝杀
+        let board = board::Board::from("3k5/9/9/9/9/6R2/9/9/9/4K4");
+        let options = SolverOptions::new(3);
+        assert_eq!(Some(1), mate_in(&board, piece::Color::Red, &options));
+        assert_eq!(None, mate_in(&board, piece::Color::Black, &options));
+        let manual = solve(&board, piece::Color::Red, &options).unwrap();
+        assert_eq!(
+            "[FEN: 3k5/9/9/9/9/6R2/9/9/9/4K4 r - - 0 1]\n[Win: 红胜]\n\n{1着杀}(1)\n(5,6)(1,6){困毙}\n",
+            manual.to_string(coord::RecordType::Txt)
+        );
+        let mut options = SolverOptions::new(3);
+        options.checks_only = true;
+        let manual = solve(&board, piece::Color::Red, &options).unwrap();
+        assert_eq!(
+            "{1着杀}(1)\n(5,6)(5,3){绝杀}\n",
+            manual
+                .to_string(coord::RecordType::Txt)
+                .split_once("\n\n")
+                .unwrap()
+                .1
+        );
+
+        // 守方应着全部列出
+        let board = board::Board::from("3ak4/4a4/9/9/9/9/9/9/4R4/3K5");
+        assert_eq!(None, mate_in(&board, piece::Color::Red, &options));
+        let manual = solve(&board, piece::Color::Red, &SolverOptions::new(3)).unwrap();
+        assert_eq!(
+            "{3着杀}(1)\n帅六平五(1)\n将５平６(1)\n车五平四(2)\n将６平５(1)\n士５进６(1)\n车四进七{困毙}\n车四进六{绝杀}\n",
+            manual.to_string(coord::RecordType::PgnZh).split_once("\n\n").unwrap().1
+        );
+    }
+}
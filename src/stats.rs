@@ -0,0 +1,439 @@
+#![allow(dead_code)]
+
+use crate::amove;
+use crate::common;
+use crate::coord;
+use crate::info;
+use crate::manual;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Clone, Copy, Debug)]
+pub struct StatsOptions {
+    // 开局按前几着归类
+    pub opening_moves: usize,
+    // 各排行榜列出的条数
+    pub top: usize,
+    // 只统计该结果的对局
+    pub result: Option<info::Win>,
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        StatsOptions {
+            opening_moves: 6,
+            top: 10,
+            result: None,
+        }
+    }
+}
+
+// 统计报告，可输出文本或JSON
+#[derive(Debug, Default, Serialize)]
+pub struct StatsReport {
+    pub game_count: usize,
+    // 读取失败的文件及原因
+    pub failures: Vec<(String, String)>,
+    // 对局结果分布，缺失或无法识别者记为"未知"
+    pub results: Vec<(String, usize)>,
+    pub terminations: Vec<(String, usize)>,
+    pub opening_moves: usize,
+    pub openings: Vec<(String, usize)>,
+    // 主线平均着数
+    pub average_length: f64,
+    pub total_moves: usize,
+    pub variation_count: usize,
+    // 最长变例(自开局起的着数)所在的棋谱
+    pub longest_variations: Vec<(String, usize)>,
+    pub remark_count: usize,
+    pub remarks: Vec<(String, usize)>,
+    // 红黑双方合计对局数最多的棋手
+    pub players: Vec<(String, usize)>,
+}
+
+// 逐局累计的统计数据
+#[derive(Debug, Default)]
+pub struct Stats {
+    options: StatsOptions,
+    game_count: usize,
+    failures: Vec<(String, String)>,
+    results: BTreeMap<String, usize>,
+    terminations: BTreeMap<String, usize>,
+    openings: BTreeMap<String, usize>,
+    main_length_sum: usize,
+    total_moves: usize,
+    variation_count: usize,
+    longest_variations: Vec<(String, usize)>,
+    remark_count: usize,
+    remarks: BTreeMap<String, usize>,
+    players: BTreeMap<String, usize>,
+}
+
+// 按次数降序(次数相同时按名称)取前top项
+fn top_counts(counts: &BTreeMap<String, usize>, top: usize) -> Vec<(String, usize)> {
+    let mut result: Vec<(String, usize)> = counts
+        .iter()
+        .map(|(name, count)| (name.clone(), *count))
+        .collect();
+    result.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    result.truncate(top);
+    result
+}
+
+impl Stats {
+    pub fn new(options: StatsOptions) -> Self {
+        Stats {
+            options,
+            ..Default::default()
+        }
+    }
+
+    pub fn add(&mut self, name: &str, manual: &manual::Manual) {
+        let result = manual.result();
+        let win = result.map_or(info::Win::Unknown, |result| result.win);
+        if self
+            .options
+            .result
+            .is_some_and(|filter_win| filter_win != win)
+        {
+            return;
+        }
+
+        self.game_count += 1;
+        let get_info = |key: manual::InfoKey| {
+            manual
+                .get_info(key)
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        *self.results.entry(win.to_string()).or_insert(0) += 1;
+        if let Some(termination) = result.and_then(|result| result.termination) {
+            *self
+                .terminations
+                .entry(termination.to_string())
+                .or_insert(0) += 1;
+        }
+        for key in [manual::InfoKey::Red, manual::InfoKey::Black] {
+            let player = get_info(key);
+            if !player.is_empty() {
+                *self.players.entry(player).or_insert(0) += 1;
+            }
+        }
+
+        let manual_move = manual.manual_move();
+        let root_move = manual_move.root_move();
+        let mut board = *manual_move.board();
+        let mut main_moves = Vec::new();
+        let mut before_move = root_move.clone();
+        while let Some(amove) = before_move.after().first().cloned() {
+            if main_moves.len() < self.options.opening_moves {
+                main_moves.push(board.get_zhstr_from_coordpair(&amove.coordpair));
+                board.do_move(&amove);
+            }
+            self.main_length_sum += 1;
+            before_move = amove;
+        }
+        if !main_moves.is_empty() {
+            *self.openings.entry(main_moves.join(" ")).or_insert(0) += 1;
+        }
+
+        let max_depth = self.add_moves(&root_move, 0);
+        self.longest_variations.push((name.to_string(), max_depth));
+        self.longest_variations
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        self.longest_variations.truncate(self.options.top);
+    }
+
+    // 累计着法、变着及备注，返回自该着起的最大深度
+    fn add_moves(&mut self, amove: &Rc<amove::Move>, depth: usize) -> usize {
+        let remark = amove.remark();
+        let remark = remark.trim();
+        if !remark.is_empty() {
+            self.remark_count += 1;
+            *self.remarks.entry(remark.to_string()).or_insert(0) += 1;
+        }
+
+        let after_moves = amove.after();
+        self.variation_count += after_moves.len().saturating_sub(1);
+        let mut max_depth = depth;
+        for after_move in after_moves {
+            self.total_moves += 1;
+            max_depth = max_depth.max(self.add_moves(&after_move, depth + 1));
+        }
+
+        max_depth
+    }
+
+    pub fn add_failure(&mut self, name: &str, error: String) {
+        self.failures.push((name.to_string(), error));
+    }
+
+    // 读取来源中的全部棋谱，读取失败者记入失败列表
+    pub fn add_source<S: ManualSource + ?Sized>(&mut self, source: &S) -> std::io::Result<()> {
+        for name in source.names()? {
+            match source.read(&name) {
+                Ok(manual) => self.add(&name, &manual),
+                Err(err) => self.add_failure(&name, err.to_string()),
+            }
+        }
+
+        Ok(())
+    }
+
+    // 读取目录(含子目录)下各种格式的棋谱
+    pub fn add_dir(&mut self, dir: &Path) -> std::io::Result<()> {
+        self.add_source(dir)
+    }
+
+    pub fn report(&self) -> StatsReport {
+        let top = self.options.top;
+        StatsReport {
+            game_count: self.game_count,
+            failures: self.failures.clone(),
+            results: top_counts(&self.results, usize::MAX),
+            terminations: top_counts(&self.terminations, usize::MAX),
+            opening_moves: self.options.opening_moves,
+            openings: top_counts(&self.openings, top),
+            average_length: if self.game_count == 0 {
+                0.0
+            } else {
+                self.main_length_sum as f64 / self.game_count as f64
+            },
+            total_moves: self.total_moves,
+            variation_count: self.variation_count,
+            longest_variations: self.longest_variations.clone(),
+            remark_count: self.remark_count,
+            remarks: top_counts(&self.remarks, top),
+            players: top_counts(&self.players, top),
+        }
+    }
+}
+
+impl StatsReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn write_counts(
+            f: &mut fmt::Formatter,
+            title: &str,
+            counts: &[(String, usize)],
+        ) -> fmt::Result {
+            writeln!(f, "\n{title}:")?;
+            for (name, count) in counts {
+                writeln!(f, "  {count:6}  {name}")?;
+            }
+
+            Ok(())
+        }
+
+        writeln!(f, "棋谱: {} 局", self.game_count)?;
+        if !self.failures.is_empty() {
+            writeln!(f, "读取失败: {} 个", self.failures.len())?;
+            for (name, error) in &self.failures {
+                writeln!(f, "  {name}: {error}")?;
+            }
+        }
+        writeln!(f, "主线平均着数: {:.1}", self.average_length)?;
+        writeln!(
+            f,
+            "着法总数: {}  变着: {}  备注: {}",
+            self.total_moves, self.variation_count, self.remark_count
+        )?;
+
+        write_counts(f, "对局结果", &self.results)?;
+        if !self.terminations.is_empty() {
+            write_counts(f, "终局原因", &self.terminations)?;
+        }
+        write_counts(
+            f,
+            &format!("常见开局(前{}着)", self.opening_moves),
+            &self.openings,
+        )?;
+        write_counts(f, "最长变例(着数)", &self.longest_variations)?;
+        write_counts(f, "常见备注", &self.remarks)?;
+        write_counts(f, "对局最多的棋手", &self.players)
+    }
+}
+
+// 棋谱来源：列出各棋谱的名称，再按名称读取。
+// 目录之外的来源(如棋谱数据库，名称可用记录编号)实现此特征后，即可用于统计、查重及规范化
+pub trait ManualSource {
+    fn names(&self) -> std::io::Result<Vec<String>>;
+
+    fn read(&self, name: &str) -> common::Result<manual::Manual>;
+}
+
+// 目录来源：名称即棋谱文件名
+impl ManualSource for Path {
+    fn names(&self) -> std::io::Result<Vec<String>> {
+        manual_files(self)
+    }
+
+    fn read(&self, name: &str) -> common::Result<manual::Manual> {
+        manual::Manual::from(name)
+    }
+}
+
+// 目录(含子目录)下各种格式的棋谱文件，扩展名不符者略过
+pub fn manual_files(dir: &Path) -> std::io::Result<Vec<String>> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+
+    let mut file_names = Vec::new();
+    for path in entries {
+        if path.is_dir() {
+            file_names.append(&mut manual_files(&path)?);
+            continue;
+        }
+
+        let file_name = path.to_string_lossy().to_string();
+        if coord::RecordType::get_record_type(&file_name).is_ok() {
+            file_names.push(file_name);
+        }
+    }
+
+    Ok(file_names)
+}
+
+// 统计来源中的棋谱
+pub fn collect<S: ManualSource + ?Sized>(
+    source: &S,
+    options: StatsOptions,
+) -> std::io::Result<StatsReport> {
+    let mut stats = Stats::new(options);
+    stats.add_source(source)?;
+
+    Ok(stats.report())
+}
+
+// 统计目录下的棋谱
+pub fn collect_dir(dir: &Path, options: StatsOptions) -> std::io::Result<StatsReport> {
+    collect(dir, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let manual_strings = [
+            "[Red: 甲]\n[Black: 乙]\n[Termination: 认输]\n[Win: 1-0]\n\n{开局}(1)\n(7,7)(7,4){中炮}(1)\n(0,7)(2,6)(2)\n(9,7)(7,6)(1)\n(9,1)(7,2)\n(0,1)(2,2)\n",
+            "[Red: 丙]\n[Black: 甲]\n[Win: 和棋]\n\n(1)\n(7,7)(7,4){中炮}(1)\n(0,1)(2,2)\n",
+            "[Red: 甲]\n\n(1)\n(9,2)(7,4)\n",
+        ];
+        let mut stats = Stats::new(StatsOptions {
+            opening_moves: 1,
+            top: 2,
+            result: None,
+        });
+        for (index, manual_string) in manual_strings.iter().enumerate() {
+            let manual =
+                manual::Manual::read_from(manual_string.as_bytes(), coord::RecordType::Txt)
+                    .unwrap();
+            stats.add(&format!("{index}.txt"), &manual);
+        }
+        stats.add_failure("bad.xqf", String::from("invalid"));
+
+        let report = stats.report();
+        assert_eq!(3, report.game_count);
+        assert_eq!(
+            vec![
+                (String::from("和棋"), 1),
+                (String::from("未知"), 1),
+                (String::from("红胜"), 1)
+            ],
+            report.results
+        );
+        assert_eq!(vec![(String::from("认输"), 1)], report.terminations);
+        assert_eq!(
+            vec![(String::from("炮二平五"), 2), (String::from("相七进五"), 1)],
+            report.openings
+        );
+        assert!((report.average_length - 7.0 / 3.0).abs() < 1e-9);
+        assert_eq!(
+            (8, 1, 3),
+            (
+                report.total_moves,
+                report.variation_count,
+                report.remark_count
+            )
+        );
+        assert_eq!(
+            vec![(String::from("0.txt"), 4), (String::from("1.txt"), 2)],
+            report.longest_variations
+        );
+        assert_eq!(
+            vec![(String::from("中炮"), 2), (String::from("开局"), 1)],
+            report.remarks
+        );
+        assert_eq!(
+            vec![(String::from("甲"), 3), (String::from("丙"), 1)],
+            report.players
+        );
+
+        let text = report.to_string();
+        assert!(
+            text.starts_with("棋谱: 3 局\n读取失败: 1 个\n  bad.xqf: invalid\n主线平均着数: 2.3\n")
+        );
+        assert!(text.contains("\n常见开局(前1着):\n       2  炮二平五\n"));
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(3, json["game_count"]);
+        assert_eq!("甲", json["players"][0][0]);
+
+        // 按结果筛选
+        let mut stats = Stats::new(StatsOptions {
+            result: Some(info::Win::Draw),
+            ..Default::default()
+        });
+        for manual_string in manual_strings {
+            let manual =
+                manual::Manual::read_from(manual_string.as_bytes(), coord::RecordType::Txt)
+                    .unwrap();
+            stats.add("", &manual);
+        }
+        let report = stats.report();
+        assert_eq!(1, report.game_count);
+        assert_eq!(
+            vec![(String::from("丙"), 1), (String::from("甲"), 1)],
+            report.players
+        );
+
+        // 目录之外的来源：按编号取记录
+        struct RecordSource<'a>(&'a [&'a str]);
+        impl ManualSource for RecordSource<'_> {
+            fn names(&self) -> std::io::Result<Vec<String>> {
+                Ok((0..=self.0.len()).map(|id| id.to_string()).collect())
+            }
+
+            fn read(&self, name: &str) -> common::Result<manual::Manual> {
+                let record = name
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|id| self.0.get(id))
+                    .ok_or(common::Error::IndexOut)?;
+                manual::Manual::read_from(record.as_bytes(), coord::RecordType::Txt)
+            }
+        }
+        let report = collect(&RecordSource(&manual_strings), StatsOptions::default()).unwrap();
+        assert_eq!(3, report.game_count);
+        assert_eq!(
+            vec![String::from("3")],
+            report
+                .failures
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,292 @@
+#![allow(dead_code)]
+
+use crate::board;
+use crate::coord::{self, Coord, CoordPair, COLCOUNT, ROWCOUNT};
+use crate::piece;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CoordLabel {
+    NoLabel,
+    Chinese,
+    Iccs,
+}
+
+#[derive(Clone, Debug)]
+pub struct SvgOptions {
+    // 显示于棋盘下方的一方，与局面实际下方不同时翻转棋盘
+    pub bottom_color: piece::Color,
+    pub last_move: Option<CoordPair>,
+    pub arrows: Vec<CoordPair>,
+    pub coord_label: CoordLabel,
+    pub grid_size: usize,
+}
+
+const BOARDCOLOR: &str = "#f0d9a0";
+const LINECOLOR: &str = "#5a3a1a";
+const PIECECOLOR: &str = "#fbeacb";
+const REDCOLOR: &str = "#c8102e";
+const BLACKCOLOR: &str = "#202020";
+const LASTMOVECOLOR: &str = "#2a7fff";
+const ARROWCOLOR: &str = "#2e9c4a";
+
+impl SvgOptions {
+    pub fn new() -> Self {
+        SvgOptions {
+            bottom_color: piece::Color::Red,
+            last_move: None,
+            arrows: Vec::new(),
+            coord_label: CoordLabel::Chinese,
+            grid_size: 40,
+        }
+    }
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Layout {
+    grid: usize,
+    margin: usize,
+    is_flip: bool,
+}
+
+impl Layout {
+    // 棋盘坐标转换为图上坐标(翻转时旋转180度)
+    fn point(&self, coord: Coord) -> (usize, usize) {
+        let coord = if self.is_flip {
+            coord.to_change(coord::ChangeType::Rotate)
+        } else {
+            coord
+        };
+
+        (
+            self.margin + coord.col * self.grid,
+            self.margin + coord.row * self.grid,
+        )
+    }
+
+    fn line(&self, result: &mut String, from: (usize, usize), to: (usize, usize)) {
+        let (x1, y1) = self.point(Coord {
+            row: from.0,
+            col: from.1,
+        });
+        let (x2, y2) = self.point(Coord {
+            row: to.0,
+            col: to.1,
+        });
+        result.push_str(&format!(
+            "<line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" />\n"
+        ));
+    }
+}
+
+fn draw_grid(result: &mut String, layout: &Layout) {
+    result.push_str(&format!(
+        "<g stroke=\"{LINECOLOR}\" stroke-width=\"1.5\">\n"
+    ));
+    for row in 0..ROWCOUNT {
+        layout.line(result, (row, 0), (row, COLCOUNT - 1));
+    }
+    for col in 0..COLCOUNT {
+        if col == 0 || col == COLCOUNT - 1 {
+            layout.line(result, (0, col), (ROWCOUNT - 1, col));
+        } else {
+            // 河界处竖线断开
+            layout.line(result, (0, col), (ROWCOUNT / 2 - 1, col));
+            layout.line(result, (ROWCOUNT / 2, col), (ROWCOUNT - 1, col));
+        }
+    }
+    // 九宫斜线
+    for (top_row, bottom_row) in [(0, 2), (7, 9)] {
+        layout.line(result, (top_row, 3), (bottom_row, 5));
+        layout.line(result, (top_row, 5), (bottom_row, 3));
+    }
+    result.push_str("</g>\n");
+
+    let grid = layout.grid;
+    let river_y = layout.margin + grid * 9 / 2;
+    let font_size = grid * 3 / 5;
+    for (text, x) in [
+        ("楚 河", layout.margin + grid * 2),
+        ("汉 界", layout.margin + grid * 6),
+    ] {
+        result.push_str(&format!(
+            "<text x=\"{x}\" y=\"{river_y}\" font-size=\"{font_size}\" fill=\"{LINECOLOR}\" \
+text-anchor=\"middle\" dominant-baseline=\"central\">{text}</text>\n"
+        ));
+    }
+}
+
+fn draw_labels(
+    result: &mut String,
+    layout: &Layout,
+    bottom_color: piece::Color,
+    label: CoordLabel,
+) {
+    let font_size = layout.grid * 2 / 5;
+    let mut push_text = |x: usize, y: usize, text: String| {
+        result.push_str(&format!(
+            "<text x=\"{x}\" y=\"{y}\" font-size=\"{font_size}\" fill=\"{LINECOLOR}\" \
+text-anchor=\"middle\" dominant-baseline=\"central\">{text}</text>\n"
+        ));
+    };
+
+    let half_margin = layout.margin / 2;
+    let bottom_y = layout.margin * 2 + layout.grid * (ROWCOUNT - 1) - half_margin;
+    match label {
+        CoordLabel::NoLabel => (),
+        CoordLabel::Chinese => {
+            // 双方各自从右向左数列
+            let top_color = piece::other_color(bottom_color);
+            for col in 0..COLCOUNT {
+                let x = layout.margin + col * layout.grid;
+                push_text(
+                    x,
+                    half_margin,
+                    board::Board::get_col_ch(top_color, col).to_string(),
+                );
+                push_text(
+                    x,
+                    bottom_y,
+                    board::Board::get_col_ch(bottom_color, COLCOUNT - 1 - col).to_string(),
+                );
+            }
+        }
+        CoordLabel::Iccs => {
+            for col in 0..COLCOUNT {
+                let (x, _) = layout.point(Coord { row: 0, col });
+                push_text(x, bottom_y, char::from(b'A' + col as u8).to_string());
+            }
+            for row in 0..ROWCOUNT {
+                let (_, y) = layout.point(Coord { row, col: 0 });
+                push_text(half_margin, y, row.to_string());
+            }
+        }
+    }
+}
+
+fn draw_pieces(result: &mut String, layout: &Layout, board: &board::Board) {
+    let radius = layout.grid * 9 / 20;
+    let font_size = layout.grid * 11 / 20;
+    for (index, piece) in board.pieces().iter().enumerate() {
+        if let piece::Piece::Some(color, _) = piece {
+            let (x, y) = layout.point(Coord::from_index(index).unwrap());
+            let color = match color {
+                piece::Color::Red => REDCOLOR,
+                piece::Color::Black => BLACKCOLOR,
+            };
+            result.push_str(&format!(
+                "<circle cx=\"{x}\" cy=\"{y}\" r=\"{radius}\" fill=\"{PIECECOLOR}\" \
+stroke=\"{color}\" stroke-width=\"2\" />\n\
+<text x=\"{x}\" y=\"{y}\" font-size=\"{font_size}\" fill=\"{color}\" \
+text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+                piece.print_name()
+            ));
+        }
+    }
+}
+
+fn draw_last_move(result: &mut String, layout: &Layout, coordpair: &CoordPair) {
+    let half = layout.grid / 2;
+    for coord in [coordpair.from_coord, coordpair.to_coord] {
+        let (x, y) = layout.point(coord);
+        result.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" \
+stroke=\"{LASTMOVECOLOR}\" stroke-width=\"2\" stroke-dasharray=\"4,3\" />\n",
+            x - half,
+            y - half,
+            layout.grid,
+            layout.grid
+        ));
+    }
+}
+
+fn draw_arrows(result: &mut String, layout: &Layout, arrows: &[CoordPair]) {
+    if arrows.is_empty() {
+        return;
+    }
+
+    result.push_str(&format!(
+        "<defs><marker id=\"arrowhead\" markerWidth=\"4\" markerHeight=\"4\" refX=\"2\" \
+refY=\"2\" orient=\"auto\"><path d=\"M0,0 L4,2 L0,4 Z\" fill=\"{ARROWCOLOR}\" /></marker></defs>\n"
+    ));
+    let width = (layout.grid / 8).max(2);
+    for coordpair in arrows {
+        let (x1, y1) = layout.point(coordpair.from_coord);
+        let (x2, y2) = layout.point(coordpair.to_coord);
+        result.push_str(&format!(
+            "<line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" stroke=\"{ARROWCOLOR}\" \
+stroke-width=\"{width}\" stroke-opacity=\"0.8\" marker-end=\"url(#arrowhead)\" />\n"
+        ));
+    }
+}
+
+// 局面图：棋盘(河界、九宫)、棋子、上一着标记、候选着法箭头及坐标
+pub fn to_svg(board: &board::Board, options: &SvgOptions) -> String {
+    let grid = options.grid_size;
+    let layout = Layout {
+        grid,
+        margin: grid,
+        is_flip: options.bottom_color != board.bottom_color(),
+    };
+    let width = grid * (COLCOUNT + 1);
+    let height = grid * (ROWCOUNT + 1);
+
+    let mut result = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+viewBox=\"0 0 {width} {height}\">\n\
+<rect width=\"{width}\" height=\"{height}\" fill=\"{BOARDCOLOR}\" />\n"
+    );
+    draw_grid(&mut result, &layout);
+    draw_labels(
+        &mut result,
+        &layout,
+        options.bottom_color,
+        options.coord_label,
+    );
+    if let Some(coordpair) = &options.last_move {
+        draw_last_move(&mut result, &layout, coordpair);
+    }
+    draw_pieces(&mut result, &layout, board);
+    draw_arrows(&mut result, &layout, &options.arrows);
+    result.push_str("</svg>\n");
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg() {
+        let board = board::Board::new();
+        let mut options = SvgOptions::new();
+        let svg = to_svg(&board, &options);
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(32, svg.matches("<circle").count());
+        assert!(svg.contains("楚 河"));
+        assert!(svg.contains(">九</text>"));
+        // 红帅位于下方中间
+        assert!(svg.contains("<circle cx=\"200\" cy=\"400\""));
+        assert!(!svg.contains("marker"));
+
+        let coordpair = CoordPair::from_row_col(7, 7, 7, 4).unwrap();
+        options.bottom_color = piece::Color::Black;
+        options.last_move = Some(coordpair);
+        options.arrows = vec![CoordPair::from_row_col(0, 1, 2, 2).unwrap()];
+        options.coord_label = CoordLabel::Iccs;
+        let svg = to_svg(&board, &options);
+        // 翻转后黑将位于下方中间
+        assert!(svg.contains(
+            "<circle cx=\"200\" cy=\"400\" r=\"18\" fill=\"#fbeacb\" stroke=\"#202020\""
+        ));
+        assert_eq!(2, svg.matches("stroke-dasharray").count());
+        assert!(svg.contains("marker-end=\"url(#arrowhead)\""));
+        assert!(svg.contains(">I</text>"));
+    }
+}
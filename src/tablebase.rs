@@ -0,0 +1,427 @@
+#![allow(dead_code)]
+
+use crate::bit_board;
+use crate::bit_constant;
+use crate::board;
+use crate::common;
+use crate::coord::{self, SEATCOUNT};
+use crate::piece::{self, Color, Kind, Piece};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::rc::Rc;
+
+// 文件格式：标记"XQTB"、版本、子力字符串、局面数、每局面一字节的结果
+const MAGIC: &[u8] = b"XQTB";
+const VERSION: u8 = 1;
+
+// 局面数上限(含双方轮走)，超出则拒绝生成
+const MAXPOSITIONCOUNT: usize = 1 << 26;
+
+// 每局面一字节：0为和棋(或未定)，255为不可能局面，其余为距杀着数(半回合)加1，
+// 距离为奇数者轮走方胜，偶数者轮走方负
+const DRAW: u8 = 0;
+const ILLEGAL: u8 = 255;
+const MAXDISTANCE: usize = 253;
+
+// 后继局面：本表局面序号，或最高位置位时为吃子后子表中的结果
+const EXTERNAL: u32 = 1 << 31;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    // 轮走方胜，及距杀着数(半回合)
+    Win(usize),
+    Loss(usize),
+    Draw,
+}
+
+fn decode_value(value: u8) -> Option<Outcome> {
+    match value {
+        DRAW => Some(Outcome::Draw),
+        ILLEGAL => None,
+        _ => {
+            let distance = value as usize - 1;
+            if distance % 2 == 1 {
+                Some(Outcome::Win(distance))
+            } else {
+                Some(Outcome::Loss(distance))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Tablebase {
+    // 棋子按红先黑后、种类顺序排列，红方固定在下
+    pieces: Vec<Piece>,
+    // 各棋子可放置的位置，及位置在其中的序号
+    seats: Vec<Vec<usize>>,
+    seat_ordinals: Vec<[Option<usize>; SEATCOUNT]>,
+    placement_count: usize,
+    values: Vec<u8>,
+}
+
+impl Tablebase {
+    fn from_pieces(pieces: Vec<Piece>) -> common::Result<Self> {
+        let mut seats = Vec::new();
+        let mut seat_ordinals = Vec::new();
+        let mut placement_count: usize = 1;
+        for piece in &pieces {
+            if let Piece::Some(color, kind) = piece {
+                let piece_seats = bit_constant::get_kind_put_indexs(*kind, *color == Color::Red);
+                let mut ordinals = [None; SEATCOUNT];
+                for (ordinal, index) in piece_seats.iter().enumerate() {
+                    ordinals[*index] = Some(ordinal);
+                }
+
+                placement_count = placement_count.saturating_mul(piece_seats.len());
+                seats.push(piece_seats);
+                seat_ordinals.push(ordinals);
+            }
+        }
+        if placement_count.saturating_mul(piece::COLORCOUNT) > MAXPOSITIONCOUNT {
+            return Err(common::Error::Tablebase(format!(
+                "too many positions for {}",
+                pieces_to_signature(&pieces)
+            )));
+        }
+
+        Ok(Tablebase {
+            pieces,
+            seats,
+            seat_ordinals,
+            placement_count,
+            values: Vec::new(),
+        })
+    }
+
+    pub fn signature(&self) -> String {
+        pieces_to_signature(&self.pieces)
+    }
+
+    // 以子力字符串生成，如"KRk"(车胜将)、"KRkaa"(车对双士)；
+    // 吃子后的子表一并生成。按无着可走为负计算，长将、长捉等判负规则未计入，循环局面均作和棋
+    pub fn generate(signature: &str) -> common::Result<Self> {
+        let pieces = signature_to_pieces(signature)?;
+        let mut sub_tables = HashMap::new();
+
+        Self::generate_pieces(pieces, &mut sub_tables)
+    }
+
+    fn generate_pieces(
+        pieces: Vec<Piece>,
+        sub_tables: &mut HashMap<String, Rc<Tablebase>>,
+    ) -> common::Result<Self> {
+        let mut tablebase = Self::from_pieces(pieces)?;
+        let position_count = tablebase.placement_count * piece::COLORCOUNT;
+        let mut values = vec![DRAW; position_count];
+        let mut offsets = Vec::with_capacity(position_count + 1);
+        let mut successors: Vec<u32> = Vec::new();
+        let mut max_external_distance = 0;
+        offsets.push(0);
+
+        for (position, value) in values.iter_mut().enumerate() {
+            let (color, seats) = tablebase.decode_position(position);
+            let board_pieces = match tablebase.get_board_pieces(&seats) {
+                Some(board_pieces) => board_pieces,
+                None => {
+                    *value = ILLEGAL;
+                    offsets.push(successors.len());
+                    continue;
+                }
+            };
+
+            let mut bit_board = bit_board::BitBoard::new(&board_pieces);
+            // 将帅照面或非轮走方被将军
+            if bit_board.is_killed(piece::other_color(color)) {
+                *value = ILLEGAL;
+                offsets.push(successors.len());
+                continue;
+            }
+
+            let legal_moves = bit_board.get_legal_moves(color);
+            if legal_moves.is_empty() {
+                *value = 1;
+            }
+            for (from_index, to_index) in legal_moves {
+                let mut after_seats = seats.clone();
+                let from_slot = seats.iter().position(|seat| *seat == from_index).unwrap();
+                after_seats[from_slot] = to_index;
+                match seats.iter().position(|seat| *seat == to_index) {
+                    None => {
+                        let after_position = tablebase
+                            .encode_position(piece::other_color(color), &after_seats)
+                            .unwrap();
+                        successors.push(after_position as u32);
+                    }
+                    Some(eat_slot) => {
+                        let mut sub_pieces = tablebase.pieces.clone();
+                        sub_pieces.remove(eat_slot);
+                        after_seats.remove(eat_slot);
+                        let signature = pieces_to_signature(&sub_pieces);
+                        let sub_table = match sub_tables.get(&signature) {
+                            Some(sub_table) => sub_table.clone(),
+                            None => {
+                                let sub_table =
+                                    Rc::new(Self::generate_pieces(sub_pieces, sub_tables)?);
+                                sub_tables.insert(signature, sub_table.clone());
+                                sub_table
+                            }
+                        };
+
+                        let sub_value = sub_table
+                            .encode_position(piece::other_color(color), &after_seats)
+                            .map_or(DRAW, |sub_position| sub_table.values[sub_position]);
+                        if sub_value != DRAW && sub_value != ILLEGAL {
+                            max_external_distance = max_external_distance.max(sub_value as usize);
+                        }
+                        successors.push(EXTERNAL | sub_value as u32);
+                    }
+                }
+            }
+            offsets.push(successors.len());
+        }
+
+        // 逐层退解：第distance层只确定距杀恰为distance的局面
+        let mut distance = 1;
+        loop {
+            let mut changed = false;
+            for position in 0..position_count {
+                if values[position] != DRAW {
+                    continue;
+                }
+
+                let mut after_values = successors[offsets[position]..offsets[position + 1]]
+                    .iter()
+                    .map(|successor| {
+                        if successor & EXTERNAL != 0 {
+                            (successor & !EXTERNAL) as u8
+                        } else {
+                            values[*successor as usize]
+                        }
+                    });
+                let is_resolved = if distance % 2 == 1 {
+                    // 有一着使对方恰负于distance-1
+                    after_values.any(|value| value as usize == distance)
+                } else {
+                    // 每着均使对方胜，且最长恰为distance-1
+                    let mut max_value = 0;
+                    after_values.all(|value| {
+                        max_value = max_value.max(value as usize);
+                        value != DRAW && value != ILLEGAL && value % 2 == 0
+                    }) && max_value == distance
+                };
+                if is_resolved {
+                    values[position] = (distance + 1) as u8;
+                    changed = true;
+                }
+            }
+
+            if (!changed && distance > max_external_distance) || distance >= MAXDISTANCE {
+                break;
+            }
+            distance += 1;
+        }
+
+        tablebase.values = values;
+        Ok(tablebase)
+    }
+
+    fn decode_position(&self, position: usize) -> (Color, Vec<usize>) {
+        let color = piece::COLORARRAY[position / self.placement_count];
+        let mut placement = position % self.placement_count;
+        let mut seats = vec![0; self.seats.len()];
+        for (slot, piece_seats) in self.seats.iter().enumerate().rev() {
+            seats[slot] = piece_seats[placement % piece_seats.len()];
+            placement /= piece_seats.len();
+        }
+
+        (color, seats)
+    }
+
+    fn encode_position(&self, color: Color, seats: &[usize]) -> Option<usize> {
+        let mut placement = 0;
+        for (slot, seat) in seats.iter().enumerate() {
+            placement = placement * self.seats[slot].len() + self.seat_ordinals[slot][*seat]?;
+        }
+
+        Some(color as usize * self.placement_count + placement)
+    }
+
+    // 棋子位置重叠时为None
+    fn get_board_pieces(&self, seats: &[usize]) -> Option<board::Pieces> {
+        let mut board_pieces = [Piece::None; SEATCOUNT];
+        for (piece, seat) in self.pieces.iter().zip(seats) {
+            if board_pieces[*seat] != Piece::None {
+                return None;
+            }
+            board_pieces[*seat] = *piece;
+        }
+
+        Some(board_pieces)
+    }
+
+    // 子力与本表不符或局面不可能时为None
+    pub fn probe(&self, board: &board::Board, color: Color) -> Option<Outcome> {
+        let mut board = *board;
+        if board.bottom_color() != Color::Red {
+            board.to_change(coord::ChangeType::Rotate);
+        }
+
+        let mut used = [false; SEATCOUNT];
+        let mut seats = Vec::new();
+        for piece in &self.pieces {
+            let seat = board
+                .pieces()
+                .iter()
+                .enumerate()
+                .position(|(index, board_piece)| !used[index] && board_piece == piece)?;
+            used[seat] = true;
+            seats.push(seat);
+        }
+        let piece_count = board
+            .pieces()
+            .iter()
+            .filter(|piece| **piece != Piece::None)
+            .count();
+        if piece_count != seats.len() {
+            return None;
+        }
+
+        decode_value(self.values[self.encode_position(color, &seats)?])
+    }
+
+    pub fn write_to(&self, mut output: impl Write) -> common::Result<()> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        common::write_string(&mut bytes, &self.signature());
+        common::write_be_u32(&mut bytes, self.values.len() as u32);
+        output.write_all(&bytes)?;
+        output.write_all(&self.values)?;
+
+        Ok(output.flush()?)
+    }
+
+    pub fn read_from(mut input: impl Read) -> common::Result<Self> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        if bytes.len() <= MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(common::Error::Tablebase(String::from(
+                "not a tablebase file",
+            )));
+        }
+        if bytes[MAGIC.len()] != VERSION {
+            return Err(common::Error::Tablebase(format!(
+                "unsupported version {}",
+                bytes[MAGIC.len()]
+            )));
+        }
+
+        let mut input = &bytes[MAGIC.len() + 1..];
+        let signature = common::read_string(&mut input)?;
+        let count = common::read_be_u32(&mut input)? as usize;
+        let mut tablebase = Self::from_pieces(signature_to_pieces(&signature)?)?;
+        if count != tablebase.placement_count * piece::COLORCOUNT || input.len() != count {
+            return Err(common::Error::Tablebase(format!(
+                "position count does not match {signature}"
+            )));
+        }
+
+        tablebase.values = input.to_vec();
+        Ok(tablebase)
+    }
+}
+
+fn signature_to_pieces(signature: &str) -> common::Result<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    for ch in signature.chars() {
+        match Piece::new(ch) {
+            Piece::Some(color, kind) if kind != Kind::NoKind => {
+                pieces.push(Piece::Some(color, kind))
+            }
+            _ => {
+                return Err(common::Error::Tablebase(format!(
+                    "invalid piece '{ch}' in {signature}"
+                )))
+            }
+        }
+    }
+
+    for color in piece::COLORARRAY {
+        let king_count = pieces
+            .iter()
+            .filter(|piece| **piece == Piece::Some(color, Kind::King))
+            .count();
+        if king_count != 1 {
+            return Err(common::Error::Tablebase(format!(
+                "{signature} should have one king each side"
+            )));
+        }
+    }
+
+    pieces.sort_by_key(|piece| match piece {
+        Piece::Some(color, kind) => (*color as usize, *kind as usize),
+        Piece::None => (piece::COLORCOUNT, piece::KINDCOUNT),
+    });
+    Ok(pieces)
+}
+
+fn pieces_to_signature(pieces: &[Piece]) -> String {
+    pieces.iter().map(|piece| piece.ch()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tablebase() {
+        assert!(Tablebase::generate("KRx").is_err());
+        assert!(Tablebase::generate("KR").is_err());
+
+        let tablebase = Tablebase::generate("RkK").unwrap();
+        assert_eq!("KRk", tablebase.signature());
+
+        // 车平六绝杀或车三进四困毙，均一着胜
+        let board = board::Board::from("3k5/9/9/9/9/6R2/9/9/9/4K4");
+        assert_eq!(Some(Outcome::Win(1)), tablebase.probe(&board, Color::Red));
+        let board = board::Board::from("3k5/9/9/9/9/3R5/9/9/9/4K4");
+        assert_eq!(
+            Some(Outcome::Loss(0)),
+            tablebase.probe(&board, Color::Black)
+        );
+        // 将吃无根车成和
+        let board = board::Board::from("3k5/3R5/9/9/9/9/9/9/9/5K3");
+        assert_eq!(Some(Outcome::Draw), tablebase.probe(&board, Color::Black));
+        // 黑方在下时翻转后查询
+        let mut rotate_board = board;
+        rotate_board.to_change(coord::ChangeType::Rotate);
+        assert_eq!(
+            Some(Outcome::Draw),
+            tablebase.probe(&rotate_board, Color::Black)
+        );
+        // 将帅照面
+        let board = board::Board::from("4k4/9/9/9/9/9/9/9/R8/4K4");
+        assert_eq!(None, tablebase.probe(&board, Color::Red));
+        assert_eq!(None, tablebase.probe(&board::Board::new(), Color::Red));
+
+        // 车胜将：除车被吃外，红先均胜
+        let mut max_distance = 0;
+        for value in &tablebase.values {
+            if let Some(Outcome::Win(distance)) = decode_value(*value) {
+                max_distance = max_distance.max(distance);
+            }
+        }
+        assert!(max_distance > 1 && max_distance % 2 == 1);
+
+        let mut output = Vec::new();
+        tablebase.write_to(&mut output).unwrap();
+        assert_eq!(
+            MAGIC.len() + 1 + 4 + 3 + 4 + tablebase.values.len(),
+            output.len()
+        );
+        let read_tablebase = Tablebase::read_from(output.as_slice()).unwrap();
+        assert_eq!(tablebase.signature(), read_tablebase.signature());
+        assert_eq!(tablebase.values, read_tablebase.values);
+        assert!(Tablebase::read_from(&output[..10]).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+#![allow(dead_code)]
+
+use crate::amove;
+use crate::common;
+use crate::manual;
+use std::fmt;
+use std::rc::Rc;
+
+// 读秒：每着限时period秒，超时则用去一次，共periods次
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Byoyomi {
+    pub period: u32,
+    pub periods: u32,
+}
+
+// 用时规则：基本用时base秒，每着加秒increment，用完后进入读秒
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeControl {
+    pub base: u32,
+    pub increment: u32,
+    pub byoyomi: Option<Byoyomi>,
+}
+
+// 一方的计时状态
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClockState {
+    // 剩余基本用时，读秒时为本次读秒的时限
+    pub remaining: u32,
+    pub periods_left: u32,
+    pub in_byoyomi: bool,
+    pub flagged: bool,
+}
+
+impl TimeControl {
+    pub fn new(base: u32, increment: u32) -> Self {
+        TimeControl {
+            base,
+            increment,
+            byoyomi: None,
+        }
+    }
+
+    pub fn with_byoyomi(base: u32, period: u32, periods: u32) -> Self {
+        TimeControl {
+            base,
+            increment: 0,
+            byoyomi: Some(Byoyomi { period, periods }),
+        }
+    }
+
+    // 格式为"基本用时+加秒"，读秒时再加"+时限x次数"，单位为秒，如"5400+30"、"1800+0+30x3"
+    pub fn from_string(time_control_str: &str) -> common::Result<Self> {
+        let parse = |num_str: &str| {
+            num_str
+                .trim()
+                .parse()
+                .map_err(|_| common::Error::StringParse)
+        };
+        let parts: Vec<&str> = time_control_str.split('+').collect();
+        let base = parse(parts[0])?;
+        let increment = match parts.get(1) {
+            Some(increment_str) => parse(increment_str)?,
+            None => 0,
+        };
+        let byoyomi = match parts.get(2) {
+            Some(byoyomi_str) => {
+                let (period_str, periods_str) = byoyomi_str
+                    .split_once('x')
+                    .ok_or(common::Error::StringParse)?;
+                Some(Byoyomi {
+                    period: parse(period_str)?,
+                    periods: parse(periods_str)?,
+                })
+            }
+            None => None,
+        };
+        if parts.len() > 3 {
+            return Err(common::Error::StringParse);
+        }
+
+        Ok(TimeControl {
+            base,
+            increment,
+            byoyomi,
+        })
+    }
+
+    pub fn start(&self) -> ClockState {
+        ClockState {
+            remaining: self.base,
+            periods_left: self.byoyomi.map_or(0, |byoyomi| byoyomi.periods),
+            in_byoyomi: false,
+            flagged: false,
+        }
+    }
+
+    // 一方走一着用时elapsed秒后的计时状态
+    pub fn spend(&self, state: &mut ClockState, elapsed: u32) {
+        if state.flagged {
+            return;
+        }
+
+        let mut elapsed = elapsed;
+        if !state.in_byoyomi {
+            if elapsed <= state.remaining {
+                state.remaining = state.remaining - elapsed + self.increment;
+                return;
+            }
+
+            elapsed -= state.remaining;
+            state.remaining = 0;
+            match self.byoyomi {
+                Some(_) => state.in_byoyomi = true,
+                None => {
+                    state.flagged = true;
+                    return;
+                }
+            }
+        }
+
+        let period = self.byoyomi.map_or(0, |byoyomi| byoyomi.period);
+        // 每超过一个时限用去一次读秒
+        let used = elapsed
+            .saturating_sub(1)
+            .checked_div(period)
+            .unwrap_or(state.periods_left);
+        if used >= state.periods_left {
+            state.periods_left = 0;
+            state.remaining = 0;
+            state.flagged = true;
+        } else {
+            state.periods_left -= used;
+            state.remaining = period;
+        }
+    }
+
+    // 依各着用时计算双方交替走子后的剩余用时，写入各着注释；返回首个超时着法的序号
+    pub fn compute_clocks(&self, moves: &[Rc<amove::Move>]) -> Option<usize> {
+        let mut states = [self.start(), self.start()];
+        let mut flagged_index = None;
+        for (index, amove) in moves.iter().enumerate() {
+            let state = &mut states[index % 2];
+            let mut annotation = amove.annotation();
+            self.spend(state, annotation.elapsed.unwrap_or(0));
+            annotation.clock = Some(state.remaining);
+            amove.set_annotation(annotation);
+
+            if state.flagged && flagged_index.is_none() {
+                flagged_index = Some(index);
+            }
+        }
+
+        flagged_index
+    }
+
+    // 计算棋谱主线的剩余用时
+    pub fn compute_main_clocks(&self, manual: &manual::Manual) -> Option<usize> {
+        let mut moves = Vec::new();
+        let mut before_move = manual.manual_move().root_move();
+        while let Some(amove) = before_move.after().first().cloned() {
+            moves.push(amove.clone());
+            before_move = amove;
+        }
+
+        self.compute_clocks(&moves)
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}+{}", self.base, self.increment)?;
+        if let Some(byoyomi) = self.byoyomi {
+            write!(f, "+{}x{}", byoyomi.period, byoyomi.periods)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+
+    #[test]
+    fn test_time_control() {
+        let time_control = TimeControl::from_string("600+5").unwrap();
+        assert_eq!(TimeControl::new(600, 5), time_control);
+        assert_eq!("600+5", time_control.to_string());
+        let time_control = TimeControl::from_string("60+0+30x2").unwrap();
+        assert_eq!(TimeControl::with_byoyomi(60, 30, 2), time_control);
+        assert_eq!("60+0+30x2", time_control.to_string());
+        assert!(TimeControl::from_string("60+x").is_err());
+
+        // 基本用时用完后进入读秒，超时用去一次
+        let mut state = time_control.start();
+        time_control.spend(&mut state, 50);
+        assert_eq!(10, state.remaining);
+        time_control.spend(&mut state, 30);
+        assert!(state.in_byoyomi);
+        assert_eq!((30, 2), (state.remaining, state.periods_left));
+        time_control.spend(&mut state, 45);
+        assert_eq!((30, 1), (state.remaining, state.periods_left));
+        time_control.spend(&mut state, 31);
+        assert!(state.flagged);
+
+        let manual_string = "[Title: test]\n\n(1)\n(7,7)(7,4){[%emt 0:00:20]}(1)\n(0,7)(2,6){[%emt 0:00:50]}(1)\n(9,7)(7,6){[%emt 0:00:45]}(1)\n(0,8)(0,7){[%emt 0:00:25]}\n";
+        let manual =
+            manual::Manual::read_from(manual_string.as_bytes(), coord::RecordType::Txt).unwrap();
+        // 黑方第二着超时
+        assert_eq!(
+            Some(3),
+            TimeControl::new(60, 10).compute_main_clocks(&manual)
+        );
+        assert!(manual
+            .to_string(coord::RecordType::Txt)
+            .ends_with("(7,7)(7,4){[%clk 0:00:50][%emt 0:00:20]}(1)\n(0,7)(2,6){[%clk 0:00:20][%emt 0:00:50]}(1)\n(9,7)(7,6){[%clk 0:00:15][%emt 0:00:45]}(1)\n(0,8)(0,7){[%clk 0:00:00][%emt 0:00:25]}\n"));
+    }
+}
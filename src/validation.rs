@@ -0,0 +1,257 @@
+#![allow(dead_code)]
+
+use crate::bit_board;
+use crate::bit_constant;
+use crate::board;
+use crate::coord::{self, Coord, COLCOUNT, ROWCOUNT, SEATCOUNT};
+use crate::piece::{self, Color, Kind, Piece};
+use std::fmt;
+
+// 各种棋子的最多数量
+const KINDMAXCOUNTS: [usize; piece::KINDCOUNT] = [1, 2, 2, 2, 2, 2, 5];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    FenSyntax(String),
+    NoKing(Color),
+    TooMany(Color, Kind, usize),
+    BadPlace(Color, Kind, Coord),
+    PawnBehind(Color, Coord),
+    KingsFacing,
+    NotMoverInCheck(Color),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |color: &Color, kind: &Kind| Piece::Some(*color, *kind).name();
+        match self {
+            Problem::FenSyntax(message) => write!(f, "FEN格式错误：{message}"),
+            Problem::NoKing(color) => write!(f, "缺少{}", name(color, &Kind::King)),
+            Problem::TooMany(color, kind, count) => write!(
+                f,
+                "{}有{count}个，最多{}个",
+                name(color, kind),
+                KINDMAXCOUNTS[*kind as usize]
+            ),
+            Problem::BadPlace(color, kind, coord) => write!(
+                f,
+                "{}不能位于{}",
+                name(color, kind),
+                coord.to_string(coord::RecordType::Txt)
+            ),
+            Problem::PawnBehind(color, coord) => write!(
+                f,
+                "{}位于初始位置之后{}",
+                name(color, &Kind::Pawn),
+                coord.to_string(coord::RecordType::Txt)
+            ),
+            Problem::KingsFacing => write!(f, "将帅照面"),
+            Problem::NotMoverInCheck(color) => {
+                write!(f, "非轮走方{}已被将军", name(color, &Kind::King))
+            }
+        }
+    }
+}
+
+// 检查FEN字符串本身(可带" r - - 0 1"等后缀)，无误时再检查局面
+pub fn validate_fen(fen: &str, to_move: Color) -> Vec<Problem> {
+    let fen = fen.split(' ').next().unwrap_or_default();
+    let rows: Vec<&str> = fen.split('/').collect();
+    if rows.len() != ROWCOUNT {
+        return vec![Problem::FenSyntax(format!(
+            "应有{ROWCOUNT}行，实有{}行",
+            rows.len()
+        ))];
+    }
+
+    let mut problems = Vec::new();
+    for (row, row_str) in rows.iter().enumerate() {
+        let mut col_count = 0;
+        for ch in row_str.chars() {
+            match ch.to_digit(10) {
+                Some(num) => col_count += num as usize,
+                None if piece::kind(ch) != Kind::NoKind => col_count += 1,
+                None => problems.push(Problem::FenSyntax(format!("第{row}行有无效字符'{ch}'"))),
+            }
+        }
+        if col_count != COLCOUNT {
+            problems.push(Problem::FenSyntax(format!(
+                "第{row}行应有{COLCOUNT}列，实有{col_count}列"
+            )));
+        }
+    }
+    if !problems.is_empty() {
+        return problems;
+    }
+
+    validate(&board::fen_to_pieces(fen), to_move)
+}
+
+// 返回局面的全部问题，无问题时为空
+pub fn validate(pieces: &board::Pieces, to_move: Color) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut counts = [[0; piece::KINDCOUNT]; piece::COLORCOUNT];
+    let mut king_indexs = [None; piece::COLORCOUNT];
+    for (index, piece) in pieces.iter().enumerate() {
+        if let Piece::Some(color, kind) = piece {
+            counts[*color as usize][*kind as usize] += 1;
+            if *kind == Kind::King {
+                king_indexs[*color as usize] = Some(index);
+            }
+        }
+    }
+
+    for color in piece::COLORARRAY {
+        if counts[color as usize][Kind::King as usize] == 0 {
+            problems.push(Problem::NoKing(color));
+        }
+        for kind in piece::KINDARRAY {
+            let count = counts[color as usize][kind as usize];
+            if count > KINDMAXCOUNTS[kind as usize] {
+                problems.push(Problem::TooMany(color, kind, count));
+            }
+        }
+    }
+
+    let bottom_color = get_bottom_color(&king_indexs);
+    for (index, piece) in pieces.iter().enumerate() {
+        if let Piece::Some(color, kind) = *piece {
+            let is_bottom = color == bottom_color;
+            if bit_constant::get_kind_put_indexs(kind, is_bottom).contains(&index) {
+                continue;
+            }
+
+            let coord = Coord::from_index(index).unwrap();
+            // 兵卒初始位于己方第4行(自底线起算)
+            let is_behind = if is_bottom {
+                coord.row > ROWCOUNT - 4
+            } else {
+                coord.row < 3
+            };
+            if kind == Kind::Pawn && is_behind {
+                problems.push(Problem::PawnBehind(color, coord));
+            } else {
+                problems.push(Problem::BadPlace(color, kind, coord));
+            }
+        }
+    }
+
+    // 双方将帅各一个时才能判断照面及将军
+    if let [Some(red_index), Some(black_index)] = king_indexs {
+        if counts[Color::Red as usize][Kind::King as usize] == 1
+            && counts[Color::Black as usize][Kind::King as usize] == 1
+        {
+            if is_kings_facing(pieces, red_index, black_index) {
+                problems.push(Problem::KingsFacing);
+            } else {
+                let other_color = piece::other_color(to_move);
+                if bit_board::BitBoard::new(pieces).is_checked(other_color) {
+                    problems.push(Problem::NotMoverInCheck(other_color));
+                }
+            }
+        }
+    }
+
+    problems
+}
+
+// 将帅所在的半边确定下方，无法确定时默认红方在下
+fn get_bottom_color(king_indexs: &[Option<usize>; piece::COLORCOUNT]) -> Color {
+    let is_bottom_half = |index: usize| index >= SEATCOUNT / 2;
+    match king_indexs {
+        [Some(index), _] => {
+            if is_bottom_half(*index) {
+                Color::Red
+            } else {
+                Color::Black
+            }
+        }
+        [None, Some(index)] => {
+            if is_bottom_half(*index) {
+                Color::Black
+            } else {
+                Color::Red
+            }
+        }
+        _ => Color::Red,
+    }
+}
+
+fn is_kings_facing(pieces: &board::Pieces, index: usize, other_index: usize) -> bool {
+    if index % COLCOUNT != other_index % COLCOUNT {
+        return false;
+    }
+
+    let (top_index, bottom_index) = (index.min(other_index), index.max(other_index));
+    (top_index + COLCOUNT..bottom_index)
+        .step_by(COLCOUNT)
+        .all(|index| pieces[index] == Piece::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation() {
+        assert!(validate_fen(board::FEN, Color::Red).is_empty());
+        assert!(
+            validate_fen("5a3/4ak2r/6R2/8p/9/9/9/B4N2B/4K4/3c5 r - - 0 1", Color::Red).is_empty()
+        );
+        // 黑方在下
+        assert!(validate_fen(
+            &board::fen_to_change(board::FEN, coord::ChangeType::Rotate),
+            Color::Red
+        )
+        .is_empty());
+
+        assert_eq!(
+            vec![Problem::FenSyntax(String::from("应有10行，实有9行"))],
+            validate_fen(
+                "rnbakabnr/9/1c5c1/p1p1p1p1p/9/P1P1P1P1P/1C5C1/9/RNBAKABNR",
+                Color::Red
+            )
+        );
+        let problems = validate_fen(
+            "rnbakabnx/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABN",
+            Color::Red,
+        );
+        assert_eq!(3, problems.len());
+        assert_eq!("FEN格式错误：第0行有无效字符'x'", problems[0].to_string());
+
+        // 多兵、兵在初始位置之后、相不在原位
+        let problems = validate_fen(
+            "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C2P2C1/3B5/RN1AKABNR",
+            Color::Red,
+        );
+        assert_eq!(
+            vec![
+                Problem::TooMany(Color::Red, Kind::Pawn, 6),
+                Problem::PawnBehind(Color::Red, Coord { row: 7, col: 4 }),
+                Problem::BadPlace(Color::Red, Kind::Bishop, Coord { row: 8, col: 3 }),
+            ],
+            problems
+        );
+
+        assert_eq!(
+            vec![Problem::KingsFacing],
+            validate_fen("4k4/9/9/9/9/9/9/9/9/4K4", Color::Red)
+        );
+        assert_eq!(
+            vec![Problem::NoKing(Color::Black)],
+            validate_fen("9/9/9/9/9/9/9/9/9/4K4", Color::Red)
+        );
+
+        // 红车将军，轮红走则黑方已被将军
+        let fen = "4k4/9/9/9/9/9/9/9/4R4/3K5";
+        assert!(validate_fen(fen, Color::Black).is_empty());
+        assert_eq!(
+            vec![Problem::NotMoverInCheck(Color::Black)],
+            validate_fen(fen, Color::Red)
+        );
+        assert_eq!(
+            "非轮走方将已被将军",
+            Problem::NotMoverInCheck(Color::Black).to_string()
+        );
+    }
+}
@@ -0,0 +1,353 @@
+#![allow(dead_code)]
+
+use crate::board;
+use crate::common;
+use crate::coord::{COLCOUNT, ROWCOUNT, SEATCOUNT};
+use crate::info;
+use crate::manual::{self, InfoKey};
+use encoding::all::GBK;
+use encoding::{DecoderTrap, Encoding};
+
+pub const HEADSIZE: usize = 1024;
+const PIECENUM: usize = 32;
+const OTHERSIZE: usize = 528;
+
+// XQF文件头，各字段保持原始字节，另存解密所得的棋子位置及钥匙。
+// 字节级的无损往返仅限from_bytes/to_bytes：info()是供查看的解码结果，
+// 缺少钥匙及加密的棋子位置，不能据此重建文件头，棋谱也暂不能写出XQF格式。
+#[derive(Clone, Debug, PartialEq)]
+pub struct XqfHeader {
+    // 文件标记'XQ'=$5158/版本/加密掩码/ProductId[4], 产品(厂商的产品号)
+    pub signature: [u8; 2],
+    pub version: u8,
+    pub key_mask: u8,
+    pub product_id: [u8; 4],
+    // 加密的钥匙和/棋子布局位置钥匙/棋谱起点钥匙/棋谱终点钥匙
+    pub key_ors: [u8; 4],
+    pub keys_sum: u8,
+    pub key_xy: u8,
+    pub key_xyf: u8,
+    pub key_xyt: u8,
+    // 32个棋子的原始位置(加密)
+    pub head_qizi_xy: [u8; PIECENUM],
+    pub play_step_no: u16,
+    // 该谁下 0-红先, 1-黑先/最终结果 0-未知, 1-红胜 2-黑胜, 3-和棋
+    pub who_play: u8,
+    pub play_result: u8,
+    pub play_nodes: u32,
+    pub ptree_pos: u32,
+    pub reserved1: [u8; 4],
+    // 首字节为对局类型(开,中,残等)
+    pub code_a_h: [u8; 16],
+    pub title_a: [u8; 64],
+    pub title_b: [u8; 64],
+    pub event: [u8; 64],
+    pub date: [u8; 16],
+    pub site: [u8; 16],
+    pub red: [u8; 16],
+    pub black: [u8; 16],
+    pub opening: [u8; 64],
+    pub red_time: [u8; 16],
+    pub black_time: [u8; 16],
+    pub reserved_h: [u8; 32],
+    // 棋谱评论员/文件的作者
+    pub rmk_writer: [u8; 16],
+    pub author: [u8; 16],
+    pub other: [u8; OTHERSIZE],
+
+    // 解密所得的棋子位置及着法钥匙
+    pub qizi_xy: [u8; PIECENUM],
+    pub keyxyf: usize,
+    pub keyxyt: usize,
+    pub keyrmksize: usize,
+    pub f32keys: [u8; PIECENUM],
+}
+
+fn to_array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut array = [0; N];
+    array.copy_from_slice(bytes);
+    array
+}
+
+// 首字节为长度的GBK字符串，去除空字符及首尾空白
+pub fn bytes_to_string(bytes: &[u8]) -> String {
+    let len = bytes.first().copied().unwrap_or_default() as usize;
+    let bytes = bytes.get(1..=len).or(bytes.get(1..)).unwrap_or_default();
+    GBK.decode(bytes, DecoderTrap::Ignore)
+        .unwrap()
+        .replace('\0', "")
+        .trim()
+        .into()
+}
+
+impl XqfHeader {
+    pub fn from_bytes(input: &[u8]) -> common::Result<Self> {
+        if input.len() < HEADSIZE {
+            return Err(common::Error::Xqf(format!(
+                "header needs {HEADSIZE} bytes, got {}",
+                input.len()
+            )));
+        }
+
+        let mut header = XqfHeader {
+            signature: to_array(&input[0..2]),
+            version: input[2],
+            key_mask: input[3],
+            product_id: to_array(&input[4..8]),
+            key_ors: to_array(&input[8..12]),
+            keys_sum: input[12],
+            key_xy: input[13],
+            key_xyf: input[14],
+            key_xyt: input[15],
+            head_qizi_xy: to_array(&input[16..48]),
+            play_step_no: u16::from_le_bytes(to_array(&input[48..50])),
+            who_play: input[50],
+            play_result: input[51],
+            play_nodes: u32::from_le_bytes(to_array(&input[52..56])),
+            ptree_pos: u32::from_le_bytes(to_array(&input[56..60])),
+            reserved1: to_array(&input[60..64]),
+            code_a_h: to_array(&input[64..80]),
+            title_a: to_array(&input[80..144]),
+            title_b: to_array(&input[144..208]),
+            event: to_array(&input[208..272]),
+            date: to_array(&input[272..288]),
+            site: to_array(&input[288..304]),
+            red: to_array(&input[304..320]),
+            black: to_array(&input[320..336]),
+            opening: to_array(&input[336..400]),
+            red_time: to_array(&input[400..416]),
+            black_time: to_array(&input[416..432]),
+            reserved_h: to_array(&input[432..464]),
+            rmk_writer: to_array(&input[464..480]),
+            author: to_array(&input[480..496]),
+            other: to_array(&input[496..HEADSIZE]),
+
+            qizi_xy: [0; PIECENUM],
+            keyxyf: 0,
+            keyxyt: 0,
+            keyrmksize: 0,
+            f32keys: [0; PIECENUM],
+        };
+
+        if header.signature != [0x58, 0x51] {
+            return Err(common::Error::Xqf(String::from("文件标记不符。")).at_offset(0));
+        }
+        let (keys_sum, key_xy, key_xyf, key_xyt) = (
+            header.keys_sum as usize,
+            header.key_xy as usize,
+            header.key_xyf as usize,
+            header.key_xyt as usize,
+        );
+        if (keys_sum + key_xy + key_xyf + key_xyt) % 256 != 0 {
+            return Err(
+                common::Error::Xqf(String::from("检查密码校验和不对，不等于0。")).at_offset(12),
+            );
+        }
+        if header.version > 18 {
+            return Err(common::Error::Xqf(String::from(
+                "这是一个高版本的XQF文件，您需要更高版本的XQStudio来读取这个文件。",
+            ))
+            .at_offset(2));
+        }
+
+        header.qizi_xy = header.head_qizi_xy;
+        // version <= 10 兼容1.0以前的版本
+        if header.version > 10 {
+            let calkey = |bkey, ckey| {
+                // % 256; // 保持为<256
+                ((((((bkey * bkey) * 3 + 9) * 3 + 8) * 2 + 1) * 3 + 8) * ckey) as u8 as usize
+            };
+
+            let keyxy = calkey(key_xy, key_xy);
+            header.keyxyf = calkey(key_xyf, keyxy);
+            header.keyxyt = calkey(key_xyt, header.keyxyf);
+            // % 65536
+            header.keyrmksize = ((keys_sum * 256 + key_xy) % 32000) + 767;
+            // 棋子位置循环移动
+            if header.version >= 12 {
+                for (index, qizixy) in header.head_qizi_xy.iter().enumerate() {
+                    header.qizi_xy[(index + keyxy + 1) % PIECENUM] = *qizixy;
+                }
+            }
+            for qizixy in &mut header.qizi_xy {
+                // 保持为8位无符号整数，<256
+                *qizixy = (*qizixy as isize - keyxy as isize) as u8;
+            }
+        }
+
+        let keybytes = [
+            (header.keys_sum & header.key_mask) | header.key_ors[0],
+            (header.key_xy & header.key_mask) | header.key_ors[1],
+            (header.key_xyf & header.key_mask) | header.key_ors[2],
+            (header.key_xyt & header.key_mask) | header.key_ors[3],
+        ];
+        for (index, ch) in "[(C) Copyright Mr. Dong Shiwei.]".bytes().enumerate() {
+            header.f32keys[index] = ch & keybytes[index % 4];
+        } // ord(c)
+
+        Ok(header)
+    }
+
+    // 原样还原from_bytes读入的文件头字节
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(HEADSIZE);
+        result.extend_from_slice(&self.signature);
+        result.extend_from_slice(&[self.version, self.key_mask]);
+        result.extend_from_slice(&self.product_id);
+        result.extend_from_slice(&self.key_ors);
+        result.extend_from_slice(&[self.keys_sum, self.key_xy, self.key_xyf, self.key_xyt]);
+        result.extend_from_slice(&self.head_qizi_xy);
+        result.extend_from_slice(&self.play_step_no.to_le_bytes());
+        result.extend_from_slice(&[self.who_play, self.play_result]);
+        result.extend_from_slice(&self.play_nodes.to_le_bytes());
+        result.extend_from_slice(&self.ptree_pos.to_le_bytes());
+        for bytes in [
+            &self.reserved1[..],
+            &self.code_a_h,
+            &self.title_a,
+            &self.title_b,
+            &self.event,
+            &self.date,
+            &self.site,
+            &self.red,
+            &self.black,
+            &self.opening,
+            &self.red_time,
+            &self.black_time,
+            &self.reserved_h,
+            &self.rmk_writer,
+            &self.author,
+            &self.other,
+        ] {
+            result.extend_from_slice(bytes);
+        }
+
+        result
+    }
+
+    pub fn fen(&self) -> String {
+        // 取得棋子字符串
+        let mut piece_chars = vec![b'_'; SEATCOUNT];
+        // QiziXY设定的棋子顺序
+        for (index, ch) in manual::PIECEORDERCHARS.bytes().enumerate() {
+            let xy = self.qizi_xy[index] as usize;
+            if xy < SEATCOUNT {
+                // 用单字节坐标表示, 将字节变为十进制,
+                // 十位数为X(0-8),个位数为Y(0-9),棋盘的左下角为原点(0, 0)
+                piece_chars[(ROWCOUNT - 1 - xy % ROWCOUNT) * COLCOUNT + xy / ROWCOUNT] = ch;
+            }
+        }
+
+        board::piece_chars_to_fen(&String::from_utf8(piece_chars).unwrap())
+    }
+
+    // 保留字节有内容时以十六进制表示，仅供查看，不会写回文件
+    pub fn reserved_hex(&self) -> Option<String> {
+        let reserved: Vec<u8> = [&self.reserved1[..], &self.reserved_h, &self.other].concat();
+        if reserved.iter().all(|&byte| byte == 0) {
+            return None;
+        }
+
+        Some(reserved.iter().map(|byte| format!("{byte:02x}")).collect())
+    }
+
+    // 文件头对应的信息项，原有各项总是列出，其余有内容时才列出
+    pub fn info(&self) -> Vec<(InfoKey, String)> {
+        let side = if self.who_play == 1 { "b" } else { "r" };
+        let mut info = vec![
+            (InfoKey::FEN, format!("{} {side} - - 0 1", self.fen())),
+            (InfoKey::Version, self.version.to_string()),
+            (
+                InfoKey::Win,
+                info::GameResult::from_xqf(self.play_result).to_string(),
+            ),
+            (
+                InfoKey::Atype,
+                info::GameType::from_index(self.code_a_h[0]).to_string(),
+            ),
+            (InfoKey::Title, bytes_to_string(&self.title_a)),
+            (InfoKey::Game, bytes_to_string(&self.event)),
+            (InfoKey::Date, bytes_to_string(&self.date)),
+            (InfoKey::Site, bytes_to_string(&self.site)),
+            (InfoKey::Red, bytes_to_string(&self.red)),
+            (InfoKey::Black, bytes_to_string(&self.black)),
+            (InfoKey::Opening, bytes_to_string(&self.opening)),
+            (InfoKey::Writer, bytes_to_string(&self.rmk_writer)),
+            (InfoKey::Author, bytes_to_string(&self.author)),
+        ];
+
+        let number = |num: u32| {
+            if num != 0 {
+                num.to_string()
+            } else {
+                String::new()
+            }
+        };
+        let product_id = u32::from_le_bytes(self.product_id);
+        for (key, value) in [
+            (InfoKey::TitleB, bytes_to_string(&self.title_b)),
+            (InfoKey::RedTime, bytes_to_string(&self.red_time)),
+            (InfoKey::BlackTime, bytes_to_string(&self.black_time)),
+            (InfoKey::ProductId, number(product_id)),
+            (InfoKey::PlayStepNo, number(self.play_step_no as u32)),
+            (InfoKey::PlayNodes, number(self.play_nodes)),
+            (InfoKey::TreePos, number(self.ptree_pos)),
+            (
+                InfoKey::XqfReserved,
+                self.reserved_hex().unwrap_or_default(),
+            ),
+        ] {
+            if !value.is_empty() {
+                info.push((key, value));
+            }
+        }
+
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xqf() {
+        let input = std::fs::read("tests/xqf/01.xqf").unwrap();
+        let header = XqfHeader::from_bytes(&input).unwrap();
+        assert_eq!(input[..HEADSIZE], header.to_bytes());
+        assert_eq!("5a3/4ak2r/6R2/8p/9/9/9/B4N2B/4K4/3c5", header.fen());
+        assert_eq!(None, header.reserved_hex());
+        let info = header.info();
+        assert_eq!(13, info.len());
+        assert_eq!("第01局", info[4].1);
+
+        // 保留字节、副标题、用时及黑先
+        let mut bytes = input[..HEADSIZE].to_vec();
+        bytes[50] = 1;
+        bytes[60] = 0xab;
+        bytes[144..151].copy_from_slice(b"\x04sub ti");
+        bytes[400..406].copy_from_slice(b"\x0510:00");
+        let header = XqfHeader::from_bytes(&bytes).unwrap();
+        assert_eq!(bytes, header.to_bytes());
+        let info = header.info();
+        assert!(info[0].1.ends_with(" b - - 0 1"));
+        assert_eq!("sub", info[13].1);
+        assert_eq!("10:00", info[14].1);
+        let reserved = header.reserved_hex().unwrap();
+        assert_eq!((OTHERSIZE + 36) * 2, reserved.len());
+        assert!(reserved.starts_with("ab000000"));
+        assert_eq!(
+            vec!["TitleB", "RedTime", "XqfReserved"],
+            info[13..]
+                .iter()
+                .map(|(key, _)| key.name())
+                .collect::<Vec<String>>()
+        );
+
+        let err = XqfHeader::from_bytes(&bytes[..100]).unwrap_err();
+        assert_eq!(
+            "invalid XQF data: header needs 1024 bytes, got 100",
+            err.to_string()
+        );
+    }
+}
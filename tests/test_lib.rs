@@ -3,3 +3,31 @@
 // use lib::coord;
 // use lib::manual;
 
+use lib::coord::RecordType;
+use lib::manual::Manual;
+
+#[test]
+fn test_error() {
+    // 库外可匹配错误种类并读取出错位置
+    let err = Manual::read_from(
+        &b"[Title: test]\n\n(1)\n(7,7)(7,4)(1)\n(0,7)(2,9)\n"[..],
+        RecordType::Txt,
+    )
+    .unwrap_err();
+    match &err {
+        lib::Error::Context(location, source) => {
+            assert_eq!(Some(5), location.line);
+            assert!(matches!(**source, lib::Error::ColOut));
+        }
+        _ => panic!("unexpected error: {err}"),
+    }
+    let location: &lib::Location = err.location().unwrap();
+    assert_eq!(None, location.file_name);
+    assert!(matches!(err.kind(), lib::Error::ColOut));
+
+    let result: lib::Result<Manual> = Manual::from("tests/output/no_extension");
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        lib::Error::UnknownRecordType
+    ));
+}